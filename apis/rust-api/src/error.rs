@@ -1,11 +1,59 @@
+use babibapp_models::error::{ErrorCode, ErrorResponse};
+use reqwest::StatusCode;
+
 #[derive(Debug)]
-pub struct BabibappApiError {
-    pub error: String,
+pub enum BabibappApiError {
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    Validation(String),
+    TooManyRequests(String),
+    Internal(String),
+    /// The server answered with an error that is not a babibapp error response
+    Unexpected {
+        status: StatusCode,
+        body: String,
+    },
+    /// The request could not be sent or no response was received
+    Transport(String),
+    /// The response body could not be decoded
+    Decode(String),
+}
+
+impl BabibappApiError {
+    /// HTTP status code of the response that caused the error, if there was a response
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            BabibappApiError::Unauthorized(_) => Some(StatusCode::UNAUTHORIZED),
+            BabibappApiError::Forbidden(_) => Some(StatusCode::FORBIDDEN),
+            BabibappApiError::NotFound(_) => Some(StatusCode::NOT_FOUND),
+            BabibappApiError::Conflict(_) => Some(StatusCode::CONFLICT),
+            BabibappApiError::Validation(_) => Some(StatusCode::UNPROCESSABLE_ENTITY),
+            BabibappApiError::TooManyRequests(_) => Some(StatusCode::TOO_MANY_REQUESTS),
+            BabibappApiError::Internal(_) => Some(StatusCode::INTERNAL_SERVER_ERROR),
+            BabibappApiError::Unexpected { status, .. } => Some(*status),
+            BabibappApiError::Transport(_) | BabibappApiError::Decode(_) => None,
+        }
+    }
 }
 
 impl std::fmt::Display for BabibappApiError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Babibapp API Error: {}", self.error)
+        match self {
+            BabibappApiError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
+            BabibappApiError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
+            BabibappApiError::NotFound(msg) => write!(f, "Not found: {}", msg),
+            BabibappApiError::Conflict(msg) => write!(f, "Conflict: {}", msg),
+            BabibappApiError::Validation(msg) => write!(f, "Validation failed: {}", msg),
+            BabibappApiError::TooManyRequests(msg) => write!(f, "Too many requests: {}", msg),
+            BabibappApiError::Internal(msg) => write!(f, "Internal server error: {}", msg),
+            BabibappApiError::Unexpected { status, body } => {
+                write!(f, "Unexpected response ({}): {}", status, body)
+            }
+            BabibappApiError::Transport(msg) => write!(f, "Request failed: {}", msg),
+            BabibappApiError::Decode(msg) => write!(f, "Invalid response: {}", msg),
+        }
     }
 }
 
@@ -13,8 +61,30 @@ impl std::error::Error for BabibappApiError {}
 
 impl From<reqwest::Error> for BabibappApiError {
     fn from(e: reqwest::Error) -> Self {
-        BabibappApiError {
-            error: format!("{}", e),
+        if e.is_decode() {
+            BabibappApiError::Decode(format!("{}", e))
+        } else {
+            BabibappApiError::Transport(format!("{}", e))
+        }
+    }
+}
+
+impl From<serde_json::Error> for BabibappApiError {
+    fn from(e: serde_json::Error) -> Self {
+        BabibappApiError::Decode(format!("{}", e))
+    }
+}
+
+impl From<ErrorResponse> for BabibappApiError {
+    fn from(e: ErrorResponse) -> Self {
+        match e.code {
+            ErrorCode::Unauthorized => BabibappApiError::Unauthorized(e.message),
+            ErrorCode::Forbidden => BabibappApiError::Forbidden(e.message),
+            ErrorCode::NotFound => BabibappApiError::NotFound(e.message),
+            ErrorCode::Conflict => BabibappApiError::Conflict(e.message),
+            ErrorCode::ValidationFailed => BabibappApiError::Validation(e.message),
+            ErrorCode::TooManyRequests => BabibappApiError::TooManyRequests(e.message),
+            ErrorCode::Internal => BabibappApiError::Internal(e.message),
         }
     }
 }
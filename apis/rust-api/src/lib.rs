@@ -1,24 +1,86 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
 use error::BabibappApiError;
-use reqwest::Client as HttpClient;
+use futures_util::stream::{self, Stream, TryStreamExt};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use reqwest::{Client as HttpClient, Response};
+use serde::de::DeserializeOwned;
 
 use crate::types::*;
-use babibapp_models::wrappers::{EmailWrapper, NameWrapper, PasswordWrapper, TokenWrapper};
+use babibapp_models::error::ErrorResponse;
+use babibapp_models::wrappers::{
+    BodyWrapper, ChangePasswordWrapper, EmailWrapper, LoginWrapper, ModerationWrapper, NameWrapper,
+    PasswordWrapper, ReasonWrapper, RefreshTokenWrapper, ResetPasswordWrapper, RevokeWrapper,
+    RoleWrapper, SearchQuery, SortQuery, TargetQuery, TokenPairWrapper, TokenWrapper,
+    TotpCodeWrapper, TotpLoginWrapper,
+};
 
 pub mod error;
 pub mod types;
 
+/// Tokens that expire within this margin are refreshed by `refresh_if_expiring`
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
 pub struct BabibappClient {
     pub base_url: String,
     pub token: String,
+    pub refresh_token: Option<String>,
+    /// Set if the server withheld the permissions of the user until two-factor authentication is enabled
+    pub totp_required: bool,
+    token_expires: Option<Instant>,
+    http: HttpClient,
+}
+
+/// Outcome of a correct password
+pub enum Login {
+    Done(BabibappClient),
+    /// Two-factor authentication is enabled, the login is finished with a code
+    TotpRequired(TotpChallenge),
+}
+
+pub struct TotpChallenge {
+    base_url: String,
+    challenge: String,
     http: HttpClient,
 }
 
+impl TotpChallenge {
+    /// Finishes the login with a code of the authenticator or a recovery code
+    pub async fn verify(self, code: &str) -> Result<BabibappClient, BabibappApiError> {
+        let login = TotpLoginWrapper {
+            totp_challenge: self.challenge,
+            code: code.to_string(),
+        };
+
+        let response = self
+            .http
+            .post(format!("{}/token/totp", self.base_url))
+            .json(&login)
+            .send()
+            .await?;
+        let token_pair: TokenPairWrapper = json_response(response).await?;
+
+        let mut client = BabibappClient {
+            base_url: self.base_url,
+            token: String::new(),
+            refresh_token: None,
+            totp_required: false,
+            token_expires: None,
+            http: self.http,
+        };
+        client.set_token_pair(token_pair);
+
+        Ok(client)
+    }
+}
+
 impl BabibappClient {
     pub async fn login(
         base_url: &str,
         email: &str,
         password: &str,
-    ) -> Result<BabibappClient, BabibappApiError> {
+    ) -> Result<Login, BabibappApiError> {
         let http = HttpClient::new();
 
         let login = LoginStudent {
@@ -26,19 +88,105 @@ impl BabibappClient {
             password: password.to_string(),
         };
 
-        let TokenWrapper { token } = http
+        let response = http
             .post(format!("{}/token/generate", base_url))
             .json(&login)
             .send()
-            .await?
-            .json()
             .await?;
+        let token_pair = match json_response(response).await? {
+            LoginWrapper::Tokens(token_pair) => token_pair,
+            LoginWrapper::TotpChallenge(challenge) => {
+                return Ok(Login::TotpRequired(TotpChallenge {
+                    base_url: base_url.to_string(),
+                    challenge: challenge.totp_challenge,
+                    http,
+                }))
+            }
+        };
 
-        Ok(BabibappClient {
+        let mut client = BabibappClient {
             base_url: base_url.to_string(),
+            token: String::new(),
+            refresh_token: None,
+            totp_required: false,
+            token_expires: None,
             http,
-            token,
-        })
+        };
+        client.set_token_pair(token_pair);
+
+        Ok(Login::Done(client))
+    }
+
+    /// Public keys the server signs access tokens with, for verifying them offline
+    pub async fn get_jwks(base_url: &str) -> Result<JwkSet, BabibappApiError> {
+        let response = HttpClient::new()
+            .get(format!("{}/token/jwks", base_url))
+            .send()
+            .await?;
+        let jwks = json_response(response).await?;
+        Ok(jwks)
+    }
+
+    /// Registers a new student with an invite code, values the invite is pre-filled with may be `None`
+    pub async fn signup(
+        base_url: &str,
+        code: &str,
+        email: Option<&str>,
+        first_name: Option<&str>,
+        last_name: Option<&str>,
+        password: &str,
+    ) -> Result<StudentProfile, BabibappApiError> {
+        let signup = SignupStudent {
+            code: code.to_string(),
+            email: email.map(|s| s.to_string()),
+            first_name: first_name.map(|s| s.to_string()),
+            last_name: last_name.map(|s| s.to_string()),
+            password: password.to_string(),
+        };
+
+        let response = HttpClient::new()
+            .post(format!("{}/student/signup", base_url))
+            .json(&signup)
+            .send()
+            .await?;
+        let student = json_response(response).await?;
+
+        Ok(student)
+    }
+
+    /// Asks the server to mail a password reset token, succeeds whether or not the email is registered
+    pub async fn forgot_password(base_url: &str, email: &str) -> Result<(), BabibappApiError> {
+        let email = EmailWrapper {
+            email: email.to_string(),
+        };
+
+        let response = HttpClient::new()
+            .post(format!("{}/token/forgot", base_url))
+            .json(&email)
+            .send()
+            .await?;
+        empty_response(response).await
+    }
+
+    /// Sets a new password with a token from a password reset mail
+    pub async fn reset_password(
+        base_url: &str,
+        token: &str,
+        password: &str,
+    ) -> Result<StudentProfile, BabibappApiError> {
+        let reset = ResetPasswordWrapper {
+            token: token.to_string(),
+            password: password.to_string(),
+        };
+
+        let response = HttpClient::new()
+            .post(format!("{}/token/reset", base_url))
+            .json(&reset)
+            .send()
+            .await?;
+        let student = json_response(response).await?;
+
+        Ok(student)
     }
 
     pub async fn with_token(
@@ -55,23 +203,103 @@ impl BabibappClient {
             .post(format!("{}/token/validate", base_url))
             .json(&token)
             .send()
-            .await?
-            .text()
             .await?;
 
-        if response != "Valid token" {
-            return Err(BabibappApiError {
-                error: "Invalid token".to_string(),
-            });
-        }
+        check_status(response).await?;
 
         Ok(BabibappClient {
             base_url: base_url.to_string(),
             http,
             token: token.token,
+            refresh_token: None,
+            totp_required: false,
+            token_expires: None,
         })
     }
 
+    /// Authenticates with a long-lived API key instead of a token, it is sent the same way
+    pub async fn with_api_key(
+        base_url: &str,
+        api_key: &str,
+    ) -> Result<BabibappClient, BabibappApiError> {
+        BabibappClient::with_token(base_url, api_key).await
+    }
+
+    pub async fn with_refresh_token(
+        base_url: &str,
+        refresh_token: &str,
+    ) -> Result<BabibappClient, BabibappApiError> {
+        let mut client = BabibappClient {
+            base_url: base_url.to_string(),
+            token: String::new(),
+            refresh_token: Some(refresh_token.to_string()),
+            totp_required: false,
+            token_expires: None,
+            http: HttpClient::new(),
+        };
+        client.refresh().await?;
+
+        Ok(client)
+    }
+
+    /// Exchanges the refresh token for a new access token; the refresh token is rotated as well.
+    pub async fn refresh(&mut self) -> Result<(), BabibappApiError> {
+        let refresh_token = match &self.refresh_token {
+            Some(refresh_token) => RefreshTokenWrapper {
+                refresh_token: refresh_token.clone(),
+            },
+            None => {
+                return Err(BabibappApiError::Unauthorized(
+                    "No refresh token".to_string(),
+                ))
+            }
+        };
+
+        let response = self
+            .http
+            .post(format!("{}/token/refresh", self.base_url))
+            .json(&refresh_token)
+            .send()
+            .await?;
+
+        let token_pair: TokenPairWrapper = json_response(response).await?;
+        self.set_token_pair(token_pair);
+
+        Ok(())
+    }
+
+    pub async fn refresh_if_expiring(&mut self) -> Result<(), BabibappApiError> {
+        match self.token_expires {
+            Some(expires) if expires <= Instant::now() + REFRESH_MARGIN => self.refresh().await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Revokes the current token and its session, or every session of the user if `all` is set.
+    pub async fn logout(&self, all: bool) -> Result<(), BabibappApiError> {
+        let revoke = RevokeWrapper { all };
+
+        let response = self
+            .http
+            .post(format!("{}/token/revoke", self.base_url))
+            .json(&revoke)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+
+        empty_response(response).await
+    }
+
+    fn set_token_pair(&mut self, token_pair: TokenPairWrapper) {
+        self.token = token_pair.token;
+        self.token_expires =
+            Some(Instant::now() + Duration::from_secs(token_pair.expires_in.max(0) as u64));
+        if token_pair.refresh_token.is_some() {
+            self.refresh_token = token_pair.refresh_token;
+        }
+        self.totp_required = token_pair.totp_required;
+    }
+
     pub async fn validate_token(&self) -> Result<bool, BabibappApiError> {
         let token = TokenWrapper {
             token: self.token.clone(),
@@ -82,74 +310,81 @@ impl BabibappClient {
             .post(format!("{}/token/validate", self.base_url))
             .json(&token)
             .send()
-            .await?
-            .text()
             .await?;
 
-        Ok(response == "Valid token")
+        match check_status(response).await {
+            Ok(_) => Ok(true),
+            Err(BabibappApiError::Unauthorized(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
     }
 
     pub async fn get_student(&self, student_id: i32) -> Result<StudentView, BabibappApiError> {
-        let student = self
+        let response = self
             .http
             .get(format!("{}/student/get/{}", self.base_url, student_id))
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let student = json_response(response).await?;
         Ok(student)
     }
 
-    pub async fn get_self(&self) -> Result<Student, BabibappApiError> {
-        let student = self
+    pub async fn get_self(&self) -> Result<StudentProfile, BabibappApiError> {
+        let response = self
             .http
             .get(format!("{}/student/get_self", self.base_url))
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let student = json_response(response).await?;
         Ok(student)
     }
 
-    pub async fn get_all_students(&self) -> Result<Vec<StudentView>, BabibappApiError> {
-        let students = self
+    pub async fn get_all_students(
+        &self,
+        page: &PageQuery,
+    ) -> Result<Page<StudentView>, BabibappApiError> {
+        let response = self
             .http
             .get(format!("{}/student/get_all", self.base_url))
+            .query(page)
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let students = json_response(response).await?;
         Ok(students)
     }
 
+    /// All students, the pages are requested while the stream is read
+    pub fn stream_all_students(
+        &self,
+    ) -> impl Stream<Item = Result<StudentView, BabibappApiError>> + '_ {
+        stream_pages(move |page| async move { self.get_all_students(&page).await })
+    }
+
     pub async fn register_student(
         &self,
         email: &str,
         first_name: &str,
         last_name: &str,
         password: &str,
-        admin: Option<bool>,
-    ) -> Result<Student, BabibappApiError> {
+    ) -> Result<StudentProfile, BabibappApiError> {
         let new_student = RegisterStudent {
             email: email.to_string(),
             first_name: first_name.to_string(),
             last_name: last_name.to_string(),
             password: password.to_string(),
-            admin,
         };
 
-        let student = self
+        let response = self
             .http
             .post(format!("{}/student/register", self.base_url))
             .json(&new_student)
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let student = json_response(response).await?;
 
         Ok(student)
     }
@@ -158,12 +393,12 @@ impl BabibappClient {
         &self,
         student_id: i32,
         email: &str,
-    ) -> Result<Student, BabibappApiError> {
+    ) -> Result<StudentProfile, BabibappApiError> {
         let email = EmailWrapper {
             email: email.to_string(),
         };
 
-        let student = self
+        let response = self
             .http
             .put(format!(
                 "{}/student/reset_email/{}",
@@ -172,9 +407,8 @@ impl BabibappClient {
             .json(&email)
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let student = json_response(response).await?;
 
         Ok(student)
     }
@@ -183,12 +417,12 @@ impl BabibappClient {
         &self,
         student_id: i32,
         password: &str,
-    ) -> Result<Student, BabibappApiError> {
+    ) -> Result<StudentProfile, BabibappApiError> {
         let password = PasswordWrapper {
             password: password.to_string(),
         };
 
-        let student = self
+        let response = self
             .http
             .put(format!(
                 "{}/student/reset_password/{}",
@@ -197,9 +431,30 @@ impl BabibappClient {
             .json(&password)
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let student = json_response(response).await?;
+
+        Ok(student)
+    }
+
+    pub async fn change_password(
+        &self,
+        old_password: &str,
+        new_password: &str,
+    ) -> Result<StudentProfile, BabibappApiError> {
+        let passwords = ChangePasswordWrapper {
+            old_password: old_password.to_string(),
+            new_password: new_password.to_string(),
+        };
+
+        let response = self
+            .http
+            .put(format!("{}/student/change_password", self.base_url))
+            .json(&passwords)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let student = json_response(response).await?;
 
         Ok(student)
     }
@@ -209,13 +464,13 @@ impl BabibappClient {
         student_id: i32,
         first_name: &str,
         last_name: &str,
-    ) -> Result<Student, BabibappApiError> {
+    ) -> Result<StudentProfile, BabibappApiError> {
         let name = NameWrapper {
             first_name: first_name.to_string(),
             last_name: last_name.to_string(),
         };
 
-        let student = self
+        let response = self
             .http
             .put(format!(
                 "{}/student/reset_name/{}",
@@ -224,28 +479,12 @@ impl BabibappClient {
             .json(&name)
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let student = json_response(response).await?;
 
         Ok(student)
     }
 
-    pub async fn make_student_admin(&self, student_id: i32) -> Result<Student, BabibappApiError> {
-        let student = self
-            .http
-            .put(format!(
-                "{}/student/make_admin/{}",
-                self.base_url, student_id
-            ))
-            .bearer_auth(&self.token)
-            .send()
-            .await?
-            .json()
-            .await?;
-        Ok(student)
-    }
-
     pub async fn reset_student_full(
         &self,
         student_id: i32,
@@ -253,17 +492,15 @@ impl BabibappClient {
         first_name: &str,
         last_name: &str,
         password: &str,
-        admin: Option<bool>,
-    ) -> Result<Student, BabibappApiError> {
+    ) -> Result<StudentProfile, BabibappApiError> {
         let reset_student = RegisterStudent {
             email: email.to_string(),
             first_name: first_name.to_string(),
             last_name: last_name.to_string(),
             password: password.to_string(),
-            admin,
         };
 
-        let student = self
+        let response = self
             .http
             .put(format!(
                 "{}/student/reset_full/{}",
@@ -272,64 +509,352 @@ impl BabibappClient {
             .json(&reset_student)
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let student = json_response(response).await?;
 
         Ok(student)
     }
 
-    pub async fn delete_student(&self, student_id: i32) -> Result<Student, BabibappApiError> {
-        let student = self
+    pub async fn delete_student(
+        &self,
+        student_id: i32,
+    ) -> Result<StudentProfile, BabibappApiError> {
+        let response = self
             .http
             .delete(format!("{}/student/delete/{}", self.base_url, student_id))
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let student = json_response(response).await?;
         Ok(student)
     }
 
+    pub async fn get_all_roles(&self) -> Result<Vec<RoleView>, BabibappApiError> {
+        let response = self
+            .http
+            .get(format!("{}/role/get_all", self.base_url))
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let roles = json_response(response).await?;
+        Ok(roles)
+    }
+
+    pub async fn get_student_roles(
+        &self,
+        student_id: i32,
+    ) -> Result<Vec<RoleView>, BabibappApiError> {
+        let response = self
+            .http
+            .get(format!("{}/role/get_student/{}", self.base_url, student_id))
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let roles = json_response(response).await?;
+        Ok(roles)
+    }
+
+    pub async fn create_role(
+        &self,
+        name: &str,
+        permissions: Vec<Permission>,
+    ) -> Result<RoleView, BabibappApiError> {
+        let new_role = CreateRole {
+            name: name.to_string(),
+            permissions,
+        };
+
+        let response = self
+            .http
+            .post(format!("{}/role/create", self.base_url))
+            .json(&new_role)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let role = json_response(response).await?;
+
+        Ok(role)
+    }
+
+    pub async fn delete_role(&self, role_id: i32) -> Result<Role, BabibappApiError> {
+        let response = self
+            .http
+            .delete(format!("{}/role/delete/{}", self.base_url, role_id))
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let role = json_response(response).await?;
+        Ok(role)
+    }
+
+    pub async fn grant_role(
+        &self,
+        student_id: i32,
+        role: &str,
+    ) -> Result<Vec<RoleView>, BabibappApiError> {
+        let role = RoleWrapper {
+            role: role.to_string(),
+        };
+
+        let response = self
+            .http
+            .put(format!("{}/role/grant/{}", self.base_url, student_id))
+            .json(&role)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let roles = json_response(response).await?;
+
+        Ok(roles)
+    }
+
+    pub async fn revoke_role(
+        &self,
+        student_id: i32,
+        role: &str,
+    ) -> Result<Vec<RoleView>, BabibappApiError> {
+        let role = RoleWrapper {
+            role: role.to_string(),
+        };
+
+        let response = self
+            .http
+            .put(format!("{}/role/revoke/{}", self.base_url, student_id))
+            .json(&role)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let roles = json_response(response).await?;
+
+        Ok(roles)
+    }
+
+    pub async fn create_invite(
+        &self,
+        invite: &CreateInvite,
+    ) -> Result<CreatedInvite, BabibappApiError> {
+        let response = self
+            .http
+            .post(format!("{}/invite/create", self.base_url))
+            .json(invite)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let invite = json_response(response).await?;
+
+        Ok(invite)
+    }
+
+    pub async fn get_all_invites(&self) -> Result<Vec<Invite>, BabibappApiError> {
+        let response = self
+            .http
+            .get(format!("{}/invite/get_all", self.base_url))
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let invites = json_response(response).await?;
+        Ok(invites)
+    }
+
+    pub async fn revoke_invite(&self, invite_id: i32) -> Result<Invite, BabibappApiError> {
+        let response = self
+            .http
+            .delete(format!("{}/invite/revoke/{}", self.base_url, invite_id))
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let invite = json_response(response).await?;
+        Ok(invite)
+    }
+
+    pub async fn create_api_key(
+        &self,
+        name: &str,
+        scope: ApiKeyScope,
+        student_id: Option<i32>,
+    ) -> Result<CreatedApiKey, BabibappApiError> {
+        let api_key = CreateApiKey {
+            name: name.to_string(),
+            scope,
+            student_id,
+        };
+
+        let response = self
+            .http
+            .post(format!("{}/api_key/create", self.base_url))
+            .json(&api_key)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let created = json_response(response).await?;
+        Ok(created)
+    }
+
+    pub async fn get_all_api_keys(&self) -> Result<Vec<ApiKey>, BabibappApiError> {
+        let response = self
+            .http
+            .get(format!("{}/api_key/get_all", self.base_url))
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let api_keys = json_response(response).await?;
+        Ok(api_keys)
+    }
+
+    pub async fn revoke_api_key(&self, api_key_id: i32) -> Result<ApiKey, BabibappApiError> {
+        let response = self
+            .http
+            .delete(format!("{}/api_key/revoke/{}", self.base_url, api_key_id))
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let api_key = json_response(response).await?;
+        Ok(api_key)
+    }
+
+    pub async fn get_all_lockouts(&self) -> Result<Vec<LoginLockout>, BabibappApiError> {
+        let response = self
+            .http
+            .get(format!("{}/lockout/get_all", self.base_url))
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let lockouts = json_response(response).await?;
+        Ok(lockouts)
+    }
+
+    pub async fn clear_lockout(&self, lockout_id: i32) -> Result<LoginLockout, BabibappApiError> {
+        let response = self
+            .http
+            .delete(format!("{}/lockout/clear/{}", self.base_url, lockout_id))
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let lockout = json_response(response).await?;
+        Ok(lockout)
+    }
+
+    /// Starts enabling two-factor authentication, finished by `confirm_totp`
+    pub async fn enroll_totp(&self) -> Result<TotpEnrolment, BabibappApiError> {
+        let response = self
+            .http
+            .post(format!("{}/totp/enroll", self.base_url))
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let enrolment = json_response(response).await?;
+        Ok(enrolment)
+    }
+
+    /// Enables two-factor authentication with a first code and returns the recovery codes
+    pub async fn confirm_totp(&self, code: &str) -> Result<RecoveryCodes, BabibappApiError> {
+        let code = TotpCodeWrapper {
+            code: code.to_string(),
+        };
+
+        let response = self
+            .http
+            .post(format!("{}/totp/confirm", self.base_url))
+            .json(&code)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let codes = json_response(response).await?;
+        Ok(codes)
+    }
+
+    /// Disables two-factor authentication, `code` may also be a recovery code
+    pub async fn disable_totp(&self, code: &str) -> Result<(), BabibappApiError> {
+        let code = TotpCodeWrapper {
+            code: code.to_string(),
+        };
+
+        let response = self
+            .http
+            .post(format!("{}/totp/disable", self.base_url))
+            .json(&code)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        empty_response(response).await
+    }
+
+    pub async fn regenerate_recovery_codes(
+        &self,
+        code: &str,
+    ) -> Result<RecoveryCodes, BabibappApiError> {
+        let code = TotpCodeWrapper {
+            code: code.to_string(),
+        };
+
+        let response = self
+            .http
+            .post(format!("{}/totp/recovery_codes", self.base_url))
+            .json(&code)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let codes = json_response(response).await?;
+        Ok(codes)
+    }
+
+    pub async fn reset_student_totp(&self, student_id: i32) -> Result<(), BabibappApiError> {
+        let response = self
+            .http
+            .delete(format!("{}/totp/reset/{}", self.base_url, student_id))
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        empty_response(response).await
+    }
+
     pub async fn get_teacher(&self, teacher_id: i32) -> Result<Teacher, BabibappApiError> {
-        let teacher = self
+        let response = self
             .http
             .get(format!("{}/teacher/get/{}", self.base_url, teacher_id))
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let teacher = json_response(response).await?;
         Ok(teacher)
     }
 
-    pub async fn get_all_teachers(&self) -> Result<Vec<Teacher>, BabibappApiError> {
-        let teachers = self
+    pub async fn get_all_teachers(
+        &self,
+        page: &PageQuery,
+    ) -> Result<Page<Teacher>, BabibappApiError> {
+        let response = self
             .http
             .get(format!("{}/teacher/get_all", self.base_url))
+            .query(page)
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let teachers = json_response(response).await?;
         Ok(teachers)
     }
 
+    /// All teachers, the pages are requested while the stream is read
+    pub fn stream_all_teachers(
+        &self,
+    ) -> impl Stream<Item = Result<Teacher, BabibappApiError>> + '_ {
+        stream_pages(move |page| async move { self.get_all_teachers(&page).await })
+    }
+
     pub async fn add_teacher(&self, name: &str, prefix: &str) -> Result<Teacher, BabibappApiError> {
         let new_teacher = NewTeacher {
             name: name.to_string(),
             prefix: prefix.to_string(),
         };
 
-        let teacher = self
+        let response = self
             .http
             .post(format!("{}/teacher/add", self.base_url))
             .json(&new_teacher)
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let teacher = json_response(response).await?;
 
         Ok(teacher)
     }
@@ -345,275 +870,551 @@ impl BabibappClient {
             prefix: prefix.to_string(),
         };
 
-        let teacher = self
+        let response = self
             .http
             .put(format!("{}/teacher/reset/{}", self.base_url, teacher_id))
             .json(&reset_teacher)
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let teacher = json_response(response).await?;
 
         Ok(teacher)
     }
 
     pub async fn delete_teacher(&self, teacher_id: i32) -> Result<Teacher, BabibappApiError> {
-        let teacher = self
+        let response = self
             .http
             .delete(format!("{}/teacher/delete/{}", self.base_url, teacher_id))
+            .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let teacher = json_response(response).await?;
         Ok(teacher)
     }
 
-    pub async fn get_student_comment(
+    /// URL of a comment route. The methods taking a `target` use the routes of that target if it
+    /// is given, they only find comments of the target and teacher comments by their old ids too.
+    fn comment_url(&self, target: Option<CommentTarget>, route: &str) -> String {
+        match target {
+            Some(target) => format!("{}/comment/{}/{}", self.base_url, target, route),
+            None => format!("{}/comment/{}", self.base_url, route),
+        }
+    }
+
+    pub async fn get_comment(
         &self,
+        target: Option<CommentTarget>,
         comment_id: i32,
-    ) -> Result<StudentCommentView, BabibappApiError> {
-        let comment = self
+    ) -> Result<CommentView, BabibappApiError> {
+        let response = self
             .http
-            .get(format!(
-                "{}/comment/student/get/{}",
-                self.base_url, comment_id
-            ))
+            .get(self.comment_url(target, &format!("get/{}", comment_id)))
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let comment = json_response(response).await?;
         Ok(comment)
     }
 
-    pub async fn get_all_student_comments(
+    /// Comments with their votes, ordered by `sort`. `filter.target` restricts them to one target.
+    pub async fn get_all_comments(
         &self,
-    ) -> Result<Vec<StudentCommentView>, BabibappApiError> {
-        let comments = self
+        sort: CommentSort,
+        filter: &CommentFilter,
+        page: &PageQuery,
+    ) -> Result<Page<ScoredComment>, BabibappApiError> {
+        let response = self
             .http
-            .get(format!("{}/comment/student/get_all", self.base_url))
+            .get(format!("{}/comment/get_all", self.base_url))
+            .query(&SortQuery { sort })
+            .query(filter)
+            .query(page)
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let comments = json_response(response).await?;
         Ok(comments)
     }
 
-    pub async fn get_student_comment_vote(&self, comment_id: i32) -> Result<i64, BabibappApiError> {
-        let vote = self
+    /// All comments matching `filter`, the pages are requested while the stream is read
+    pub fn stream_all_comments(
+        &self,
+        sort: CommentSort,
+        filter: CommentFilter,
+    ) -> impl Stream<Item = Result<ScoredComment, BabibappApiError>> + '_ {
+        stream_pages(move |page| {
+            let filter = filter.clone();
+            async move { self.get_all_comments(sort, &filter, &page).await }
+        })
+    }
+
+    pub async fn get_comment_vote(
+        &self,
+        target: Option<CommentTarget>,
+        comment_id: i32,
+    ) -> Result<i64, BabibappApiError> {
+        let response = self
             .http
-            .get(format!(
-                "{}/comment/student/get_vote/{}",
-                self.base_url, comment_id
-            ))
+            .get(self.comment_url(target, &format!("get_vote/{}", comment_id)))
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let vote = json_response(response).await?;
         Ok(vote)
     }
 
-    pub async fn create_student_comment(
+    /// `anonymous` is left to the server if `None`
+    pub async fn create_comment(
         &self,
+        target: CommentTarget,
         receiver_id: i32,
         body: &str,
-    ) -> Result<StudentComment, BabibappApiError> {
-        let new_comment = CreateStudentComment {
+        anonymous: Option<bool>,
+    ) -> Result<Comment, BabibappApiError> {
+        let new_comment = CreateComment {
+            target: Some(target),
             receiver_id,
             body: body.to_string(),
+            anonymous,
         };
 
-        let comment = self
+        let response = self
             .http
-            .post(format!("{}/comment/student/create", self.base_url))
+            .post(format!("{}/comment/create", self.base_url))
             .json(&new_comment)
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let comment = json_response(response).await?;
 
         Ok(comment)
     }
 
-    pub async fn upvote_student_comment(&self, comment_id: i32) -> Result<(), BabibappApiError> {
-        self.http
-            .post(format!(
-                "{}/comment/student/upvote/{}",
-                self.base_url, comment_id
-            ))
+    /// Replies to a comment, the reply is about the receiver of the comment
+    pub async fn reply_to_comment(
+        &self,
+        target: Option<CommentTarget>,
+        comment_id: i32,
+        body: &str,
+        anonymous: Option<bool>,
+    ) -> Result<Comment, BabibappApiError> {
+        let body = CreateReply {
+            body: body.to_string(),
+            anonymous,
+        };
+
+        let response = self
+            .http
+            .post(self.comment_url(target, &format!("reply/{}", comment_id)))
+            .json(&body)
             .bearer_auth(&self.token)
             .send()
             .await?;
-        Ok(())
+        let comment = json_response(response).await?;
+
+        Ok(comment)
     }
 
-    pub async fn downvote_student_comment(&self, comment_id: i32) -> Result<(), BabibappApiError> {
-        self.http
-            .post(format!(
-                "{}/comment/student/downvote/{}",
-                self.base_url, comment_id
-            ))
+    /// Comments about the logged in student that wait for their approval
+    pub async fn get_comment_inbox(&self) -> Result<Vec<CommentView>, BabibappApiError> {
+        let response = self
+            .http
+            .get(format!("{}/comment/inbox", self.base_url))
             .bearer_auth(&self.token)
             .send()
             .await?;
-        Ok(())
+        let comments = json_response(response).await?;
+        Ok(comments)
     }
 
-    pub async fn unvote_student_comment(&self, comment_id: i32) -> Result<(), BabibappApiError> {
-        self.http
-            .delete(format!(
-                "{}/comment/student/unvote/{}",
-                self.base_url, comment_id
-            ))
+    pub async fn approve_comment(
+        &self,
+        target: Option<CommentTarget>,
+        comment_id: i32,
+    ) -> Result<CommentView, BabibappApiError> {
+        let response = self
+            .http
+            .post(self.comment_url(target, &format!("approve/{}", comment_id)))
             .bearer_auth(&self.token)
             .send()
             .await?;
-        Ok(())
+        let comment = json_response(response).await?;
+        Ok(comment)
     }
 
-    pub async fn delete_student_comment(
+    pub async fn reject_comment(
         &self,
+        target: Option<CommentTarget>,
         comment_id: i32,
-    ) -> Result<StudentComment, BabibappApiError> {
-        let comment = self
+    ) -> Result<CommentView, BabibappApiError> {
+        let response = self
             .http
-            .delete(format!(
-                "{}/comment/student/delete/{}",
-                self.base_url, comment_id
-            ))
+            .post(self.comment_url(target, &format!("reject/{}", comment_id)))
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let comment = json_response(response).await?;
         Ok(comment)
     }
 
-    pub async fn get_teacher_comment(
+    /// A comment with all of its replies, each reply follows its parent
+    pub async fn get_comment_thread(
         &self,
+        target: Option<CommentTarget>,
         comment_id: i32,
-    ) -> Result<TeacherCommentView, BabibappApiError> {
-        let comment = self
+    ) -> Result<Vec<ThreadEntry>, BabibappApiError> {
+        let response = self
             .http
-            .get(format!(
-                "{}/comment/teacher/get/{}",
-                self.base_url, comment_id
-            ))
+            .get(self.comment_url(target, &format!("thread/{}", comment_id)))
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let thread = json_response(response).await?;
+        Ok(thread)
+    }
+
+    pub async fn edit_comment(
+        &self,
+        target: Option<CommentTarget>,
+        comment_id: i32,
+        body: &str,
+    ) -> Result<CommentView, BabibappApiError> {
+        let body = BodyWrapper {
+            body: body.to_string(),
+        };
+
+        let response = self
+            .http
+            .put(self.comment_url(target, &format!("edit/{}", comment_id)))
+            .json(&body)
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let comment = json_response(response).await?;
+
         Ok(comment)
     }
 
-    pub async fn get_all_teacher_comments(
+    /// Previous bodies of an edited comment, only for its author and moderators
+    pub async fn get_comment_history(
         &self,
-    ) -> Result<Vec<TeacherCommentView>, BabibappApiError> {
-        let comments = self
+        target: Option<CommentTarget>,
+        comment_id: i32,
+    ) -> Result<Vec<CommentRevision>, BabibappApiError> {
+        let response = self
             .http
-            .get(format!("{}/comment/teacher/get_all", self.base_url))
+            .get(self.comment_url(target, &format!("history/{}", comment_id)))
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
-        Ok(comments)
+        let revisions = json_response(response).await?;
+        Ok(revisions)
     }
 
-    pub async fn get_teacher_comment_vote(&self, comment_id: i32) -> Result<i64, BabibappApiError> {
-        let vote = self
+    /// Upvotes a comment, replacing an earlier vote, and returns its new vote counts
+    pub async fn upvote_comment(
+        &self,
+        target: Option<CommentTarget>,
+        comment_id: i32,
+    ) -> Result<CommentVotes, BabibappApiError> {
+        let response = self
             .http
-            .get(format!(
-                "{}/comment/teacher/get_vote/{}",
-                self.base_url, comment_id
-            ))
+            .post(self.comment_url(target, &format!("upvote/{}", comment_id)))
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
-        Ok(vote)
+        let votes = json_response(response).await?;
+        Ok(votes)
     }
 
-    pub async fn create_teacher_comment(
+    /// Downvotes a comment, replacing an earlier vote, and returns its new vote counts
+    pub async fn downvote_comment(
         &self,
-        receiver_id: i32,
-        body: &str,
-    ) -> Result<TeacherComment, BabibappApiError> {
-        let new_comment = CreateTeacherComment {
-            receiver_id,
-            body: body.to_string(),
-        };
+        target: Option<CommentTarget>,
+        comment_id: i32,
+    ) -> Result<CommentVotes, BabibappApiError> {
+        let response = self
+            .http
+            .post(self.comment_url(target, &format!("downvote/{}", comment_id)))
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let votes = json_response(response).await?;
+        Ok(votes)
+    }
 
-        let comment = self
+    /// Takes back the vote on a comment and returns its new vote counts
+    pub async fn unvote_comment(
+        &self,
+        target: Option<CommentTarget>,
+        comment_id: i32,
+    ) -> Result<CommentVotes, BabibappApiError> {
+        let response = self
             .http
-            .post(format!("{}/comment/teacher/create", self.base_url))
-            .json(&new_comment)
+            .delete(self.comment_url(target, &format!("unvote/{}", comment_id)))
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let votes = json_response(response).await?;
+        Ok(votes)
+    }
 
-        Ok(comment)
+    /// Reactions the server accepts, in its configured order
+    pub async fn get_comment_reaction_set(&self) -> Result<Vec<String>, BabibappApiError> {
+        let response = self
+            .http
+            .get(format!("{}/comment/reaction_set", self.base_url))
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let reactions = json_response(response).await?;
+        Ok(reactions)
     }
 
-    pub async fn upvote_teacher_comment(&self, comment_id: i32) -> Result<(), BabibappApiError> {
-        self.http
-            .post(format!(
-                "{}/comment/teacher/upvote/{}",
-                self.base_url, comment_id
-            ))
+    pub async fn get_comment_reactions(
+        &self,
+        target: Option<CommentTarget>,
+        comment_id: i32,
+    ) -> Result<Vec<ReactionCount>, BabibappApiError> {
+        let response = self
+            .http
+            .get(self.comment_url(target, &format!("get_reactions/{}", comment_id)))
             .bearer_auth(&self.token)
             .send()
             .await?;
-        Ok(())
+        let reactions = json_response(response).await?;
+        Ok(reactions)
     }
 
-    pub async fn downvote_teacher_comment(&self, comment_id: i32) -> Result<(), BabibappApiError> {
-        self.http
-            .post(format!(
-                "{}/comment/teacher/downvote/{}",
-                self.base_url, comment_id
+    /// Reacts to a comment and returns its new reaction counts
+    pub async fn react_to_comment(
+        &self,
+        target: Option<CommentTarget>,
+        comment_id: i32,
+        reaction: &str,
+    ) -> Result<Vec<ReactionCount>, BabibappApiError> {
+        let response = self
+            .http
+            .post(self.comment_url(
+                target,
+                &format!(
+                    "react/{}/{}",
+                    comment_id,
+                    utf8_percent_encode(reaction, NON_ALPHANUMERIC)
+                ),
             ))
             .bearer_auth(&self.token)
             .send()
             .await?;
-        Ok(())
+        let reactions = json_response(response).await?;
+        Ok(reactions)
     }
 
-    pub async fn unvote_teacher_comment(&self, comment_id: i32) -> Result<(), BabibappApiError> {
-        self.http
-            .delete(format!(
-                "{}/comment/teacher/unvote/{}",
-                self.base_url, comment_id
+    /// Takes back a reaction to a comment and returns its new reaction counts
+    pub async fn unreact_to_comment(
+        &self,
+        target: Option<CommentTarget>,
+        comment_id: i32,
+        reaction: &str,
+    ) -> Result<Vec<ReactionCount>, BabibappApiError> {
+        let response = self
+            .http
+            .delete(self.comment_url(
+                target,
+                &format!(
+                    "unreact/{}/{}",
+                    comment_id,
+                    utf8_percent_encode(reaction, NON_ALPHANUMERIC)
+                ),
             ))
             .bearer_auth(&self.token)
             .send()
             .await?;
-        Ok(())
+        let reactions = json_response(response).await?;
+        Ok(reactions)
     }
 
-    pub async fn delete_teacher_comment(
+    pub async fn delete_comment(
         &self,
+        target: Option<CommentTarget>,
         comment_id: i32,
-    ) -> Result<TeacherComment, BabibappApiError> {
-        let comment = self
+    ) -> Result<CommentView, BabibappApiError> {
+        let response = self
             .http
-            .delete(format!(
-                "{}/comment/teacher/delete/{}",
-                self.base_url, comment_id
-            ))
+            .delete(self.comment_url(target, &format!("delete/{}", comment_id)))
             .bearer_auth(&self.token)
             .send()
-            .await?
-            .json()
             .await?;
+        let comment = json_response(response).await?;
         Ok(comment)
     }
+
+    /// Reports a comment to the moderators
+    pub async fn report_comment(
+        &self,
+        target: Option<CommentTarget>,
+        comment_id: i32,
+        reason: &str,
+    ) -> Result<CommentReport, BabibappApiError> {
+        let reason = ReasonWrapper {
+            reason: reason.to_string(),
+        };
+
+        let response = self
+            .http
+            .post(self.comment_url(target, &format!("report/{}", comment_id)))
+            .json(&reason)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let report = json_response(response).await?;
+        Ok(report)
+    }
+
+    /// The moderation queue, comments with the most open reports first.
+    /// Without a target it holds the comments of all targets.
+    pub async fn get_comment_reports(
+        &self,
+        target: Option<CommentTarget>,
+    ) -> Result<Vec<ReportedComment>, BabibappApiError> {
+        let response = self
+            .http
+            .get(format!("{}/comment/reports", self.base_url))
+            .query(&TargetQuery { target })
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let queue = json_response(response).await?;
+        Ok(queue)
+    }
+
+    pub async fn moderate_comment(
+        &self,
+        target: Option<CommentTarget>,
+        comment_id: i32,
+        action: ModerationAction,
+    ) -> Result<CommentView, BabibappApiError> {
+        let action = ModerationWrapper { action };
+
+        let response = self
+            .http
+            .post(self.comment_url(target, &format!("moderate/{}", comment_id)))
+            .json(&action)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let comment = json_response(response).await?;
+        Ok(comment)
+    }
+
+    /// The comment with its author even if it is anonymous, the moderation log records the call
+    pub async fn reveal_comment_author(
+        &self,
+        target: Option<CommentTarget>,
+        comment_id: i32,
+    ) -> Result<Comment, BabibappApiError> {
+        let response = self
+            .http
+            .post(self.comment_url(target, &format!("reveal_author/{}", comment_id)))
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let comment = json_response(response).await?;
+        Ok(comment)
+    }
+
+    pub async fn get_comment_moderation_log(
+        &self,
+        target: Option<CommentTarget>,
+        comment_id: i32,
+    ) -> Result<Vec<CommentModeration>, BabibappApiError> {
+        let response = self
+            .http
+            .get(self.comment_url(target, &format!("moderation_log/{}", comment_id)))
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let log = json_response(response).await?;
+        Ok(log)
+    }
+
+    /// Comments, students and teachers matching `q`, at most `limit` of each kind
+    pub async fn search(
+        &self,
+        q: &str,
+        limit: Option<i64>,
+    ) -> Result<SearchResults, BabibappApiError> {
+        let query = SearchQuery {
+            q: q.to_owned(),
+            limit,
+        };
+
+        let response = self
+            .http
+            .get(format!("{}/search", self.base_url))
+            .query(&query)
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let results = json_response(response).await?;
+        Ok(results)
+    }
+}
+
+/// Flattens the pages returned by `fetch_page` into a stream of their items,
+/// the next page is only fetched once all items of the previous one were read
+fn stream_pages<'a, T, F, Fut>(
+    fetch_page: F,
+) -> impl Stream<Item = Result<T, BabibappApiError>> + 'a
+where
+    T: 'a,
+    F: Fn(PageQuery) -> Fut + 'a,
+    Fut: Future<Output = Result<Page<T>, BabibappApiError>> + 'a,
+{
+    // `None` after the last page
+    let first_page = Some(PageQuery::default());
+
+    stream::try_unfold(first_page, move |page| {
+        let fetched = page.map(&fetch_page);
+        async move {
+            let page = match fetched {
+                Some(fetched) => fetched.await?,
+                None => return Ok::<_, BabibappApiError>(None),
+            };
+
+            let next_page = page.next_cursor.map(|cursor| PageQuery {
+                limit: None,
+                cursor: Some(cursor),
+            });
+            let items = stream::iter(page.items.into_iter().map(Ok));
+
+            Ok(Some((items, next_page)))
+        }
+    })
+    .try_flatten()
+}
+
+/// Turns unsuccessful responses into the matching `BabibappApiError`
+async fn check_status(response: Response) -> Result<Response, BabibappApiError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.text().await?;
+    match serde_json::from_str::<ErrorResponse>(&body) {
+        Ok(error) => Err(error.into()),
+        Err(_) => Err(BabibappApiError::Unexpected { status, body }),
+    }
+}
+
+async fn json_response<T: DeserializeOwned>(response: Response) -> Result<T, BabibappApiError> {
+    let body = check_status(response).await?.bytes().await?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+async fn empty_response(response: Response) -> Result<(), BabibappApiError> {
+    check_status(response).await?;
+    Ok(())
 }
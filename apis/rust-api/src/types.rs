@@ -1,13 +1,34 @@
 pub use babibapp_models::student::{
-    LimitedViewStudent, LoginStudent, RegisterStudent, Student, StudentView,
+    LimitedViewStudent, LoginStudent, RegisterStudent, StudentProfile, StudentView,
 };
 
 pub use babibapp_models::teacher::{NewTeacher, Teacher};
 
 pub use babibapp_models::comment::{
-    CreateStudentComment, LimitedViewStudentComment, StudentComment, StudentCommentView,
+    Comment, CommentModeration, CommentReport, CommentRevision, CommentView, CreateComment,
+    CreateReply, LimitedViewComment, ReportedComment, ScoredComment, ThreadEntry,
 };
 
 pub use babibapp_models::comment::{
-    CreateTeacherComment, LimitedViewTeacherComment, TeacherComment, TeacherCommentView,
+    ApprovalStatus, CommentSort, CommentTarget, CommentVotes, ModerationAction, ReactionCount,
 };
+
+pub use babibapp_models::search::{CommentHit, SearchResults, StudentHit, TeacherHit};
+
+pub use babibapp_models::api_key::{ApiKey, ApiKeyScope, CreateApiKey, CreatedApiKey};
+
+pub use babibapp_models::role::{CreateRole, Permission, Role, RoleView};
+
+pub use babibapp_models::invite::{CreateInvite, CreatedInvite, Invite, SignupStudent};
+
+pub use babibapp_models::login::LoginLockout;
+
+pub use babibapp_models::token::{Jwk, JwkSet};
+
+pub use babibapp_models::totp::{RecoveryCodes, TotpEnrolment};
+
+pub use babibapp_models::error::{ErrorCode, ErrorResponse};
+
+pub use babibapp_models::page::Page;
+
+pub use babibapp_models::wrappers::{CommentFilter, PageQuery, TargetQuery};
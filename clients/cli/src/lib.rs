@@ -1,4 +1,5 @@
 use std::collections::VecDeque;
+use std::time::SystemTime;
 
 use chrono::offset::Local;
 use chrono::DateTime;
@@ -90,69 +91,90 @@ pub fn view_student(student: &StudentView) {
             println!("id: {}", student.id);
         }
         StudentView::Full(student) => {
-            match student.admin {
-                true => println!("{} {} (admin)", student.first_name, student.last_name),
-                false => println!("{} {}", student.first_name, student.last_name),
-            }
+            println!("{} {}", student.first_name, student.last_name);
             println!("----------------");
             println!("id: {}", student.id);
             println!("Email: {}", student.email);
-            println!("Password hash: {}", student.password_hash);
         }
     }
 }
 
-pub fn view_teacher(teacher: &Teacher) {
-    println!("{} {}", teacher.prefix, teacher.name);
+pub fn view_role(role: &RoleView) {
+    println!("{}", role.name);
     println!("----------------");
-    println!("id: {}", teacher.id);
+    println!("id: {}", role.id);
+    for permission in &role.permissions {
+        println!("- {}", permission);
+    }
 }
 
-pub fn view_student_comment_limited(
-    comment: &LimitedViewStudentComment,
-    recv: &StudentView,
-    vote: i64,
-) {
-    let recv_name = match recv {
-        StudentView::Limited(student) => (&student.first_name, &student.last_name),
-        StudentView::Full(student) => (&student.first_name, &student.last_name),
-    };
-
-    let published: DateTime<Local> = comment.published.into();
+pub fn view_invite(invite: &Invite) {
+    let expires: DateTime<Local> = invite.expires.into();
 
-    println!("-> {} {} [{}]", recv_name.0, recv_name.1, comment.id,);
+    println!("Invite [{}]", invite.id);
     println!("----------------");
-    println!("{}", comment.body);
+    println!("Used: {}/{}", invite.uses, invite.max_uses);
+    println!("Expires: {}", expires.format("%d.%m.%Y %T"));
+    if invite.revoked {
+        println!("Revoked");
+    }
+    if let Some(email) = &invite.email {
+        println!("Email: {}", email);
+    }
+    if let (Some(first_name), Some(last_name)) = (&invite.first_name, &invite.last_name) {
+        println!("Name: {} {}", first_name, last_name);
+    }
+    if let Some(role_id) = invite.role_id {
+        println!("Role id: {}", role_id);
+    }
+}
+
+pub fn view_api_key(api_key: &ApiKey) {
+    let created: DateTime<Local> = api_key.created.into();
+
+    println!("API key [{}] {}", api_key.id, api_key.name);
     println!("----------------");
-    println!(
-        "Vote: {}, published: {}",
-        vote,
-        published.format("%d.%m.%Y %T")
-    );
+    println!("Key: {}...", api_key.prefix);
+    println!("Scope: {}", api_key.scope);
+    if let Some(student_id) = api_key.student_id {
+        println!("Acts as student: {}", student_id);
+    }
+    println!("Created: {}", created.format("%d.%m.%Y %T"));
+    if let Some(last_used) = api_key.last_used {
+        let last_used: DateTime<Local> = last_used.into();
+        println!("Last used: {}", last_used.format("%d.%m.%Y %T"));
+    }
+    if api_key.revoked {
+        println!("Revoked");
+    }
 }
 
-pub fn view_student_comment_full(
-    comment: &StudentComment,
-    recv: &StudentView,
-    author: &StudentView,
-    vote: i64,
-) {
-    let recv_name = match recv {
-        StudentView::Limited(student) => (&student.first_name, &student.last_name),
-        StudentView::Full(student) => (&student.first_name, &student.last_name),
-    };
+pub fn view_lockout(lockout: &LoginLockout) {
+    println!("{}: {}", lockout.kind, lockout.value);
+    println!("----------------");
+    println!("id: {}", lockout.id);
+    println!("Failed attempts: {}", lockout.failures);
+    if let Some(locked_until) = lockout.locked_until {
+        let locked_until: DateTime<Local> = locked_until.into();
+        println!("Locked until: {}", locked_until.format("%d.%m.%Y %T"));
+    }
+}
 
-    let author_name = match author {
-        StudentView::Limited(student) => (&student.first_name, &student.last_name),
-        StudentView::Full(student) => (&student.first_name, &student.last_name),
-    };
+pub fn view_teacher(teacher: &Teacher) {
+    println!("{} {}", teacher.prefix, teacher.name);
+    println!("----------------");
+    println!("id: {}", teacher.id);
+}
 
+/// `recv_name` is the name of the student or teacher the comment is about
+pub fn view_comment_limited(comment: &LimitedViewComment, recv_name: &str, vote: i64) {
     let published: DateTime<Local> = comment.published.into();
 
-    println!(
-        "{} {} -> {} {} [{}]",
-        author_name.0, author_name.1, recv_name.0, recv_name.1, comment.id,
-    );
+    if comment.anonymous {
+        println!("anonymous -> {} [{}]", recv_name, comment.id);
+    } else {
+        println!("-> {} [{}]", recv_name, comment.id);
+    }
     println!("----------------");
     println!("{}", comment.body);
     println!("----------------");
@@ -161,16 +183,30 @@ pub fn view_student_comment_full(
         vote,
         published.format("%d.%m.%Y %T")
     );
+    if let Some(edited) = comment.edited {
+        let edited: DateTime<Local> = edited.into();
+        println!("Edited: {}", edited.format("%d.%m.%Y %T"));
+    }
+    if comment.hidden {
+        println!("Hidden by the moderators");
+    }
+    if comment.status == ApprovalStatus::Pending.as_str() {
+        println!("Waiting for the approval of the receiver");
+    } else if comment.status == ApprovalStatus::Rejected.as_str() {
+        println!("Rejected by the receiver");
+    }
 }
 
-pub fn view_teacher_comment_limited(
-    comment: &LimitedViewTeacherComment,
-    recv: &Teacher,
+/// `author` is `None` for anonymous comments
+pub fn view_comment_full(
+    comment: &Comment,
+    recv_name: &str,
+    author: Option<&StudentView>,
     vote: i64,
 ) {
     let published: DateTime<Local> = comment.published.into();
 
-    println!("-> {} {} [{}]", recv.prefix, recv.name, comment.id,);
+    println!("{} -> {} [{}]", author_name(author), recv_name, comment.id,);
     println!("----------------");
     println!("{}", comment.body);
     println!("----------------");
@@ -179,31 +215,199 @@ pub fn view_teacher_comment_limited(
         vote,
         published.format("%d.%m.%Y %T")
     );
+    if let Some(edited) = comment.edited {
+        let edited: DateTime<Local> = edited.into();
+        println!("Edited: {}", edited.format("%d.%m.%Y %T"));
+    }
+    if comment.hidden {
+        println!("Hidden by the moderators");
+    }
+    if comment.status == ApprovalStatus::Pending.as_str() {
+        println!("Waiting for the approval of the receiver");
+    } else if comment.status == ApprovalStatus::Rejected.as_str() {
+        println!("Rejected by the receiver");
+    }
 }
 
-pub fn view_teacher_comment_full(
-    comment: &TeacherComment,
-    recv: &Teacher,
-    author: &StudentView,
-    vote: i64,
+fn author_name(author: Option<&StudentView>) -> String {
+    match author {
+        Some(StudentView::Limited(student)) => {
+            format!("{} {}", student.first_name, student.last_name)
+        }
+        Some(StudentView::Full(student)) => {
+            format!("{} {}", student.first_name, student.last_name)
+        }
+        None => "anonymous".to_string(),
+    }
+}
+
+pub fn view_comment_votes(votes: &CommentVotes) {
+    println!("Upvotes: {}, downvotes: {}", votes.upvotes, votes.downvotes);
+    match votes.own_vote {
+        Some(true) => println!("You upvoted this comment"),
+        Some(false) => println!("You downvoted this comment"),
+        None => {}
+    }
+}
+
+pub fn view_comment_reactions(reactions: &[ReactionCount]) {
+    if reactions.is_empty() {
+        return;
+    }
+
+    let counts: Vec<String> = reactions
+        .iter()
+        .map(|reaction| {
+            if reaction.own {
+                format!("{} {} (you)", reaction.reaction, reaction.count)
+            } else {
+                format!("{} {}", reaction.reaction, reaction.count)
+            }
+        })
+        .collect();
+    println!("Reactions: {}", counts.join(", "));
+}
+
+/// Prints a comment of a thread, indented by its depth
+pub fn view_thread_comment(
+    depth: i32,
+    id: i32,
+    author: Option<&str>,
+    body: &str,
+    published: SystemTime,
+    deleted: bool,
 ) {
-    let author_name = match author {
-        StudentView::Limited(teacher) => (&teacher.first_name, &teacher.last_name),
-        StudentView::Full(teacher) => (&teacher.first_name, &teacher.last_name),
-    };
+    let indent = "    ".repeat(depth.max(0) as usize);
+    let published: DateTime<Local> = published.into();
 
-    let published: DateTime<Local> = comment.published.into();
+    if deleted {
+        println!("{}[{}] deleted", indent, id);
+        return;
+    }
+
+    match author {
+        Some(author) => println!(
+            "{}[{}] {}, {}",
+            indent,
+            id,
+            author,
+            published.format("%d.%m.%Y %T")
+        ),
+        None => println!("{}[{}] {}", indent, id, published.format("%d.%m.%Y %T")),
+    }
+    for line in body.lines() {
+        println!("{}  {}", indent, line);
+    }
+}
+
+pub fn view_comment_revision(
+    editor_id: Option<i32>,
+    body: &str,
+    written: SystemTime,
+    replaced: SystemTime,
+) {
+    let written: DateTime<Local> = written.into();
+    let replaced: DateTime<Local> = replaced.into();
 
     println!(
-        "{} {} -> {} {} [{}]",
-        author_name.0, author_name.1, recv.prefix, recv.name, comment.id,
+        "{} - {}",
+        written.format("%d.%m.%Y %T"),
+        replaced.format("%d.%m.%Y %T")
     );
     println!("----------------");
-    println!("{}", comment.body);
+    println!("{}", body);
+    println!("----------------");
+    match editor_id {
+        Some(editor_id) => println!("Replaced by student: {}", editor_id),
+        None => println!("Replaced by root, a deleted student or the anonymous author"),
+    }
+}
+
+/// Prints a comment of the moderation queue, its reports follow
+pub fn view_reported_comment(comment: &CommentView) {
+    let (id, author, body, hidden) = match comment {
+        CommentView::Full(comment) => (
+            comment.id,
+            format!("by student: {}", comment.author_id),
+            &comment.body,
+            comment.hidden,
+        ),
+        CommentView::Limited(comment) if comment.deleted => (
+            comment.id,
+            "deleted".to_string(),
+            &comment.body,
+            comment.hidden,
+        ),
+        CommentView::Limited(comment) => (
+            comment.id,
+            "anonymous".to_string(),
+            &comment.body,
+            comment.hidden,
+        ),
+    };
+
+    if hidden {
+        println!("[{}] {}, hidden", id, author);
+    } else {
+        println!("[{}] {}", id, author);
+    }
+    println!("----------------");
+    println!("{}", body);
     println!("----------------");
+}
+
+pub fn view_comment_report(reporter_id: i32, reason: &str, created: SystemTime) {
+    let created: DateTime<Local> = created.into();
+
     println!(
-        "Vote: {}, published: {}",
-        vote,
-        published.format("%d.%m.%Y %T")
+        "Reported by student {} at {}: {}",
+        reporter_id,
+        created.format("%d.%m.%Y %T"),
+        reason
     );
 }
+
+pub fn view_comment_moderation(
+    moderator_id: Option<i32>,
+    api_key_id: Option<i32>,
+    root: bool,
+    action: &str,
+    created: SystemTime,
+) {
+    let created: DateTime<Local> = created.into();
+    let created = created.format("%d.%m.%Y %T");
+
+    match (action, moderator_id, api_key_id) {
+        ("auto_hide", _, _) => {
+            println!("{}: hidden automatically after too many reports", created)
+        }
+        (action, Some(moderator_id), Some(api_key_id)) => println!(
+            "{}: {} by student: {} with API key: {}",
+            created, action, moderator_id, api_key_id
+        ),
+        (action, Some(moderator_id), None) => {
+            println!("{}: {} by student: {}", created, action, moderator_id)
+        }
+        (action, None, Some(api_key_id)) => {
+            println!("{}: {} with API key: {}", created, action, api_key_id)
+        }
+        (action, None, None) if root => println!("{}: {} by root", created, action),
+        (action, None, None) => println!("{}: {} by a deleted student or API key", created, action),
+    }
+}
+
+/// Prints a match of a search, matched words of the snippet are wrapped in `**`
+pub fn view_search_hit(title: &str, snippet: &str) {
+    println!("{}", title);
+    println!("----------------");
+    println!("{}", snippet);
+}
+
+pub fn view_recovery_codes(codes: &RecoveryCodes) {
+    println!("Recovery codes");
+    println!("----------------");
+    for code in &codes.recovery_codes {
+        println!("{}", code);
+    }
+    println!("Each code works once. Store them safely, they are only shown now.");
+}
@@ -1,12 +1,16 @@
+use std::env;
 use std::fs;
 use std::io::Write;
 use std::process;
+use std::str::SplitWhitespace;
 
 use clap::Parser;
-use dialoguer::theme::{ColorfulTheme, SimpleTheme};
+use dialoguer::theme::{ColorfulTheme, SimpleTheme, Theme};
+use futures_util::TryStreamExt;
 
+use babibapp_api::error::BabibappApiError;
 use babibapp_api::types::*;
-use babibapp_api::BabibappClient;
+use babibapp_api::{BabibappClient, Login};
 use babicli::{BabicliCompletion, BabicliHistory};
 
 #[derive(Parser)]
@@ -21,9 +25,98 @@ struct Cli {
         help = "Do not authenticate using a token and force to login"
     )]
     login: bool,
+
+    #[clap(
+        short,
+        long,
+        help = "Reset a forgotten password with a token sent by mail before logging in"
+    )]
+    forgot_password: bool,
+
+    #[clap(
+        short,
+        long,
+        help = "Create an account with an invite code before logging in"
+    )]
+    signup: bool,
+}
+
+/// Requests a password reset mail and sets the new password with the token from it
+async fn reset_forgotten_password(base_url: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let theme = ColorfulTheme::default();
+
+    let email: String = dialoguer::Input::with_theme(&theme)
+        .with_prompt("Your email")
+        .interact_text()?;
+
+    BabibappClient::forgot_password(base_url, &email).await?;
+    println!("If the email is registered, a reset token has been sent to it.");
+
+    loop {
+        let token: String = dialoguer::Input::with_theme(&theme)
+            .with_prompt("Reset token")
+            .interact_text()?;
+
+        let password = dialoguer::Password::with_theme(&theme)
+            .with_prompt("New password")
+            .with_confirmation("Repeat password", "The passwords do not match")
+            .interact()?;
+
+        match BabibappClient::reset_password(base_url, token.trim(), &password).await {
+            Ok(_) => {
+                println!("Password successfully reset, please login.");
+                return Ok(());
+            }
+            Err(e) => eprintln!("Failed to reset password: {}", e),
+        }
+    }
+}
+
+/// Creates an account with an invite code, asking only for what the invite doesn't pre-fill
+async fn signup(base_url: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let theme = ColorfulTheme::default();
+
+    let code: String = dialoguer::Input::with_theme(&theme)
+        .with_prompt("Invite code")
+        .interact_text()?;
+
+    let prompt_optional = |prompt: &str| -> Result<Option<String>, std::io::Error> {
+        let value: String = dialoguer::Input::with_theme(&theme)
+            .with_prompt(format!("{} (empty if given by the invite)", prompt))
+            .allow_empty(true)
+            .interact_text()?;
+        Ok(if value.is_empty() { None } else { Some(value) })
+    };
+
+    let email = prompt_optional("Your email")?;
+    let first_name = prompt_optional("First name")?;
+    let last_name = prompt_optional("Last name")?;
+
+    let password = dialoguer::Password::with_theme(&theme)
+        .with_prompt("Password")
+        .with_confirmation("Repeat password", "The passwords do not match")
+        .interact()?;
+
+    BabibappClient::signup(
+        base_url,
+        code.trim(),
+        email.as_deref(),
+        first_name.as_deref(),
+        last_name.as_deref(),
+        &password,
+    )
+    .await?;
+    println!("Account successfully created, please login.");
+
+    Ok(())
 }
 
 async fn init_babibapp_client(cli: &Cli) -> Result<BabibappClient, Box<dyn std::error::Error>> {
+    // scripts authenticate with an API key instead of logging in
+    if let Ok(api_key) = env::var("BABIBAPP_API_KEY") {
+        return Ok(BabibappClient::with_api_key(&cli.base_url, api_key.trim()).await?);
+    }
+
     let mut client: Option<BabibappClient> = None;
 
     let xdg_dirs = xdg::BaseDirectories::with_prefix("babibapp")?;
@@ -38,6 +131,20 @@ async fn init_babibapp_client(cli: &Cli) -> Result<BabibappClient, Box<dyn std::
                 }
             }
         }
+
+        // if the token expired, try to get a new one with XDG_DATA_HOME/refresh_token
+        if client.is_none() {
+            if let Some(refresh_token_file) = xdg_dirs.find_data_file("refresh_token") {
+                if let Ok(refresh_token) = fs::read_to_string(refresh_token_file) {
+                    if let Ok(babibapp) =
+                        BabibappClient::with_refresh_token(&cli.base_url, refresh_token.trim())
+                            .await
+                    {
+                        client = Some(babibapp);
+                    }
+                }
+            }
+        }
     }
 
     // if token authentication failed, create client by logging in
@@ -45,12 +152,12 @@ async fn init_babibapp_client(cli: &Cli) -> Result<BabibappClient, Box<dyn std::
         // get credentials
         let cred_theme = ColorfulTheme::default();
 
-        let email: String = dialoguer::Input::with_theme(&cred_theme)
+        let email: String = match dialoguer::Input::with_theme(&cred_theme)
             .with_prompt("Your email")
             .validate_with({
                 let mut force = None;
                 move |input: &String| -> Result<(), &str> {
-                    if input.contains('@') || force.as_ref().map_or(false, |old| old == input) {
+                    if input.contains('@') || force.as_ref().is_some_and(|old| old == input) {
                         Ok(())
                     } else {
                         force = Some(input.clone());
@@ -59,45 +166,173 @@ async fn init_babibapp_client(cli: &Cli) -> Result<BabibappClient, Box<dyn std::
                 }
             })
             .interact_text()
-            .unwrap();
+        {
+            Ok(email) => email,
+            Err(e) => {
+                eprintln!("Failed to read email");
+                return Err(e.into());
+            }
+        };
 
-        let password = dialoguer::Password::with_theme(&cred_theme)
+        let password = match dialoguer::Password::with_theme(&cred_theme)
             .with_prompt("Password")
             .interact()
-            .unwrap();
+        {
+            Ok(password) => password,
+            Err(e) => {
+                eprintln!("Failed to read password");
+                return Err(e.into());
+            }
+        };
 
         // init client
-        client = match BabibappClient::login(&cli.base_url, &email, &password).await {
-            Ok(client) => Some(client),
-            Err(_) => {
-                eprintln!("Failed to login");
+        let login = match BabibappClient::login(&cli.base_url, &email, &password).await {
+            Ok(login) => login,
+            Err(e) => {
+                eprintln!("Failed to login: {}", e);
                 continue;
             }
         };
+
+        client = match login {
+            Login::Done(client) => Some(client),
+            Login::TotpRequired(challenge) => {
+                let code: String = match dialoguer::Input::with_theme(&cred_theme)
+                    .with_prompt("Authentication code")
+                    .interact_text()
+                {
+                    Ok(code) => code,
+                    Err(e) => {
+                        eprintln!("Failed to read authentication code");
+                        return Err(e.into());
+                    }
+                };
+
+                match challenge.verify(code.trim()).await {
+                    Ok(client) => Some(client),
+                    Err(e) => {
+                        eprintln!("Failed to login: {}", e);
+                        continue;
+                    }
+                }
+            }
+        };
     }
 
     let client = client.unwrap();
 
-    // write token to XDG_DATA_HOME/token
-    let token = &client.token;
+    store_tokens(&client)?;
 
-    let token_file_path = if let Some(path) = xdg_dirs.find_data_file("token") {
-        path
-    } else {
-        xdg_dirs.place_data_file("token")?
-    };
+    Ok(client)
+}
+
+/// Writes the tokens of the client to XDG_DATA_HOME/token and XDG_DATA_HOME/refresh_token
+fn store_tokens(client: &BabibappClient) -> Result<(), Box<dyn std::error::Error>> {
+    let xdg_dirs = xdg::BaseDirectories::with_prefix("babibapp")?;
 
+    let token_file_path = xdg_dirs.place_data_file("token")?;
     let mut file = fs::File::create(token_file_path)?;
-    file.write_all(token.as_bytes())?;
+    file.write_all(client.token.as_bytes())?;
 
-    Ok(client)
+    if let Some(refresh_token) = &client.refresh_token {
+        let refresh_token_file_path = xdg_dirs.place_data_file("refresh_token")?;
+        let mut file = fs::File::create(refresh_token_file_path)?;
+        file.write_all(refresh_token.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Removes the stored tokens so the next start requires a login
+fn remove_tokens() -> Result<(), Box<dyn std::error::Error>> {
+    let xdg_dirs = xdg::BaseDirectories::with_prefix("babibapp")?;
+
+    for name in ["token", "refresh_token"] {
+        if let Some(path) = xdg_dirs.find_data_file(name) {
+            fs::remove_file(path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the target of a comment command, `student` or `teacher`, from its arguments or asks for it.
+/// The commands use the routes of the target, so they only find its comments.
+fn read_comment_target(
+    args: &mut SplitWhitespace,
+    theme: &dyn Theme,
+) -> Result<CommentTarget, String> {
+    match args.next() {
+        Some(target) => target.parse(),
+        None => dialoguer::Select::with_theme(theme)
+            .with_prompt("Target")
+            .items(&CommentTarget::ALL)
+            .interact()
+            .map(|idx| CommentTarget::ALL[idx])
+            .map_err(|_| "Failed to read target".to_string()),
+    }
+}
+
+/// Name of the student or teacher a comment is about
+async fn receiver_name(
+    babibapp: &BabibappClient,
+    target: CommentTarget,
+    receiver_id: i32,
+) -> Result<String, BabibappApiError> {
+    let name = match target {
+        CommentTarget::Student => match babibapp.get_student(receiver_id).await? {
+            StudentView::Limited(student) => {
+                format!("{} {}", student.first_name, student.last_name)
+            }
+            StudentView::Full(student) => format!("{} {}", student.first_name, student.last_name),
+        },
+        CommentTarget::Teacher => {
+            let teacher = babibapp.get_teacher(receiver_id).await?;
+            format!("{} {}", teacher.prefix, teacher.name)
+        }
+    };
+
+    Ok(name)
+}
+
+/// Prints a comment with the names of its receiver and, unless it is anonymous, its author
+async fn view_comment(
+    babibapp: &BabibappClient,
+    comment: &CommentView,
+    vote: i64,
+) -> Result<(), BabibappApiError> {
+    match comment {
+        CommentView::Limited(comment) => {
+            let recv_name = receiver_name(babibapp, comment.target, comment.receiver_id).await?;
+            babicli::view_comment_limited(comment, &recv_name, vote);
+        }
+        CommentView::Full(comment) => {
+            let recv_name = receiver_name(babibapp, comment.target, comment.receiver_id).await?;
+            let author = if comment.anonymous {
+                None
+            } else {
+                Some(babibapp.get_student(comment.author_id).await?)
+            };
+            babicli::view_comment_full(comment, &recv_name, author.as_ref(), vote);
+        }
+    }
+
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
-    let babibapp = init_babibapp_client(&cli).await?;
+    if cli.signup {
+        signup(&cli.base_url).await?;
+    }
+
+    if cli.forgot_password {
+        reset_forgotten_password(&cli.base_url).await?;
+    }
+
+    let mut babibapp = init_babibapp_client(&cli).await?;
 
     println!();
     println!("Successfully connected to {}!", cli.base_url);
@@ -106,34 +341,68 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Use the Up/Down arrows to scroll through history.");
     println!("Use the Right arrow or Tab to complete your command.");
 
+    if babibapp.totp_required {
+        println!();
+        println!("Your permissions are withheld until you enable two-factor authentication.");
+        println!("Use `enroll_totp` and login again.");
+    }
+
     let commands = vec![
         "validate_token",
+        "logout",
+        "logout_all",
         "show_student",
         "show_self",
         "show_all_students",
         "register_student",
         "reset_student",
+        "change_password",
+        "enroll_totp",
+        "disable_totp",
+        "regenerate_recovery_codes",
+        "reset_student_totp",
         "delete_student",
-        "make_student_admin",
+        "show_all_roles",
+        "show_student_roles",
+        "create_role",
+        "delete_role",
+        "grant_role",
+        "revoke_role",
+        "create_invite",
+        "show_all_invites",
+        "revoke_invite",
+        "create_api_key",
+        "show_all_api_keys",
+        "revoke_api_key",
+        "show_all_lockouts",
+        "clear_lockout",
         "show_teacher",
         "show_all_teachers",
         "add_teacher",
         "reset_teacher",
         "delete_teacher",
-        "show_student_comment",
-        "show_all_student_comments",
-        "create_student_comment",
-        "upvote_student_comment",
-        "downvote_student_comment",
-        "unvote_student_comment",
-        "delete_student_comment",
-        "show_teacher_comment",
-        "show_all_teacher_comments",
-        "create_teacher_comment",
-        "upvote_teacher_comment",
-        "downvote_teacher_comment",
-        "unvote_teacher_comment",
-        "delete_teacher_comment",
+        "show_comment",
+        "show_all_comments",
+        "create_comment",
+        "show_comment_thread",
+        "reply_comment",
+        "edit_comment",
+        "show_comment_history",
+        "upvote_comment",
+        "downvote_comment",
+        "unvote_comment",
+        "react_comment",
+        "unreact_comment",
+        "delete_comment",
+        "report_comment",
+        "show_comment_reports",
+        "moderate_comment",
+        "show_comment_moderation_log",
+        "reveal_comment_author",
+        "show_comment_inbox",
+        "approve_comment",
+        "reject_comment",
+        "search",
         "clear",
         "help",
         "exit",
@@ -149,6 +418,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let cmd_theme = ColorfulTheme::default();
         let info_theme = SimpleTheme;
 
+        let old_token = babibapp.token.clone();
+        if let Err(e) = babibapp.refresh_if_expiring().await {
+            eprintln!(
+                "Failed to refresh token, please restart and login again: {}",
+                e
+            );
+        } else if babibapp.token != old_token && store_tokens(&babibapp).is_err() {
+            eprintln!("Failed to store refreshed token");
+        }
+
         if let Ok(cmd) = dialoguer::Input::<String>::with_theme(&cmd_theme)
             .with_prompt("babicli")
             .completion_with(&completion)
@@ -160,8 +439,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 Some("validate_token") => {
                     let valid = match babibapp.validate_token().await {
                         Ok(res) => res,
-                        Err(_) => {
-                            eprintln!("Failed to validate token");
+                        Err(e) => {
+                            eprintln!("Failed to validate token: {}", e);
                             continue;
                         }
                     };
@@ -173,6 +452,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     }
                 }
 
+                Some(logout @ ("logout" | "logout_all")) => {
+                    let all = logout == "logout_all";
+
+                    if let Err(e) = babibapp.logout(all).await {
+                        eprintln!("Failed to logout: {}", e);
+                        continue;
+                    }
+
+                    if remove_tokens().is_err() {
+                        eprintln!("Failed to remove stored tokens");
+                    }
+
+                    if all {
+                        println!("Successfully logged out of all sessions!");
+                    } else {
+                        println!("Successfully logged out!");
+                    }
+                    process::exit(0);
+                }
+
                 Some("show_student") => {
                     let id = if let Some(id) = args.next() {
                         if let Ok(id) = id.parse::<i32>() {
@@ -193,8 +492,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
                     let student = match babibapp.get_student(id).await {
                         Ok(student) => student,
-                        Err(_) => {
-                            eprintln!("Failed to get student");
+                        Err(e) => {
+                            eprintln!("Failed to get student: {}", e);
                             continue;
                         }
                     };
@@ -205,8 +504,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 Some("show_self") => {
                     let me = match babibapp.get_self().await {
                         Ok(me) => me,
-                        Err(_) => {
-                            eprintln!("Failed to get self");
+                        Err(e) => {
+                            eprintln!("Failed to get self: {}", e);
                             continue;
                         }
                     };
@@ -215,13 +514,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
 
                 Some("show_all_students") => {
-                    let students = match babibapp.get_all_students().await {
-                        Ok(students) => students,
-                        Err(_) => {
-                            eprintln!("Failed to get all students");
-                            continue;
-                        }
-                    };
+                    let students =
+                        match babibapp.stream_all_students().try_collect::<Vec<_>>().await {
+                            Ok(students) => students,
+                            Err(e) => {
+                                eprintln!("Failed to get all students: {}", e);
+                                continue;
+                            }
+                        };
 
                     if students.is_empty() {
                         println!("No students found");
@@ -240,7 +540,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         .validate_with({
                             let mut force = None;
                             move |input: &String| -> Result<(), &str> {
-                                if input.contains('@') || force.as_ref().map_or(false, |old| old == input) {
+                                if input.contains('@') || force.as_ref().is_some_and(|old| old == input) {
                                     Ok(())
                                 } else {
                                     force = Some(input.clone());
@@ -290,25 +590,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         }
                     };
 
-                    let admin = match dialoguer::Confirm::with_theme(&info_theme)
-                        .with_prompt(format!("Is {} {} an admin?", first_name, last_name))
-                        .default(false)
-                        .interact()
-                    {
-                        Ok(admin) => admin,
-                        Err(_) => {
-                            eprintln!("Failed to read admin");
-                            continue;
-                        }
-                    };
-
                     let student = match babibapp
-                        .register_student(&email, &first_name, &last_name, &password, Some(admin))
+                        .register_student(&email, &first_name, &last_name, &password)
                         .await
                     {
                         Ok(student) => student,
-                        Err(_) => {
-                            eprintln!("Failed to register student");
+                        Err(e) => {
+                            eprintln!("Failed to register student: {}", e);
                             continue;
                         }
                     };
@@ -348,7 +636,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                     .validate_with({
                                         let mut force = None;
                                         move |input: &String| -> Result<(), &str> {
-                                            if input.contains('@') || force.as_ref().map_or(false, |old| old == input) {
+                                            if input.contains('@') || force.as_ref().is_some_and(|old| old == input) {
                                                 Ok(())
                                             } else {
                                                 force = Some(input.clone());
@@ -366,8 +654,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
                                 let student = match babibapp.reset_student_email(id, &email).await {
                                     Ok(student) => student,
-                                    Err(_) => {
-                                        eprintln!("Failed to reset student email");
+                                    Err(e) => {
+                                        eprintln!("Failed to reset student email: {}", e);
                                         continue;
                                     }
                                 };
@@ -394,8 +682,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 let student =
                                     match babibapp.reset_student_password(id, &password).await {
                                         Ok(student) => student,
-                                        Err(_) => {
-                                            eprintln!("Failed to reset student password");
+                                        Err(e) => {
+                                            eprintln!("Failed to reset student password: {}", e);
                                             continue;
                                         }
                                     };
@@ -432,8 +720,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                     .await
                                 {
                                     Ok(student) => student,
-                                    Err(_) => {
-                                        eprintln!("Failed to reset student name");
+                                    Err(e) => {
+                                        eprintln!("Failed to reset student name: {}", e);
                                         continue;
                                     }
                                 };
@@ -446,7 +734,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                     .validate_with({
                                         let mut force = None;
                                         move |input: &String| -> Result<(), &str> {
-                                            if input.contains('@') || force.as_ref().map_or(false, |old| old == input) {
+                                            if input.contains('@') || force.as_ref().is_some_and(|old| old == input) {
                                                 Ok(())
                                             } else {
                                                 force = Some(input.clone());
@@ -502,21 +790,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                         }
                                     };
 
-                                let admin = match dialoguer::Confirm::with_theme(&info_theme)
-                                    .with_prompt(format!(
-                                        "Is {} {} an admin?",
-                                        first_name, last_name
-                                    ))
-                                    .default(false)
-                                    .interact()
-                                {
-                                    Ok(admin) => admin,
-                                    Err(_) => {
-                                        eprintln!("Failed to read admin");
-                                        continue;
-                                    }
-                                };
-
                                 let student = match babibapp
                                     .reset_student_full(
                                         id,
@@ -524,13 +797,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                         &first_name,
                                         &last_name,
                                         &password,
-                                        Some(admin),
                                     )
                                     .await
                                 {
                                     Ok(student) => student,
-                                    Err(_) => {
-                                        eprintln!("Failed to reset student");
+                                    Err(e) => {
+                                        eprintln!("Failed to reset student: {}", e);
                                         continue;
                                     }
                                 };
@@ -552,7 +824,124 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     babicli::view_student(&StudentView::Full(student));
                 }
 
-                Some("make_student_admin") => {
+                Some("change_password") => {
+                    let old_password: String = match dialoguer::Password::with_theme(&info_theme)
+                        .with_prompt("Current password")
+                        .interact()
+                    {
+                        Ok(password) => password,
+                        Err(_) => {
+                            eprintln!("Failed to read current password");
+                            continue;
+                        }
+                    };
+
+                    let new_password: String = match dialoguer::Password::with_theme(&info_theme)
+                        .with_prompt("New password")
+                        .with_confirmation("Repeat password", "The passwords do not match")
+                        .interact()
+                    {
+                        Ok(password) => password,
+                        Err(_) => {
+                            eprintln!("Failed to read new password");
+                            continue;
+                        }
+                    };
+
+                    let student = match babibapp.change_password(&old_password, &new_password).await
+                    {
+                        Ok(student) => student,
+                        Err(e) => {
+                            eprintln!("Failed to change password: {}", e);
+                            continue;
+                        }
+                    };
+
+                    println!("Password successfully changed!");
+                    babicli::view_student(&StudentView::Full(student));
+                }
+
+                Some("enroll_totp") => {
+                    let enrolment = match babibapp.enroll_totp().await {
+                        Ok(enrolment) => enrolment,
+                        Err(e) => {
+                            eprintln!("Failed to enroll two-factor authentication: {}", e);
+                            continue;
+                        }
+                    };
+
+                    println!("Add this account to your authenticator app:");
+                    println!("Secret: {}", enrolment.secret);
+                    println!("URI: {}\n", enrolment.uri);
+
+                    let code: String = match dialoguer::Input::with_theme(&info_theme)
+                        .with_prompt("Authentication code")
+                        .interact_text()
+                    {
+                        Ok(code) => code,
+                        Err(_) => {
+                            eprintln!("Failed to read authentication code");
+                            continue;
+                        }
+                    };
+
+                    let codes = match babibapp.confirm_totp(code.trim()).await {
+                        Ok(codes) => codes,
+                        Err(e) => {
+                            eprintln!("Failed to confirm two-factor authentication: {}", e);
+                            continue;
+                        }
+                    };
+
+                    println!("\nTwo-factor authentication successfully enabled!");
+                    babicli::view_recovery_codes(&codes);
+                }
+
+                Some("disable_totp") => {
+                    let code: String = match dialoguer::Input::with_theme(&info_theme)
+                        .with_prompt("Authentication or recovery code")
+                        .interact_text()
+                    {
+                        Ok(code) => code,
+                        Err(_) => {
+                            eprintln!("Failed to read authentication code");
+                            continue;
+                        }
+                    };
+
+                    if let Err(e) = babibapp.disable_totp(code.trim()).await {
+                        eprintln!("Failed to disable two-factor authentication: {}", e);
+                        continue;
+                    }
+
+                    println!("Two-factor authentication successfully disabled!");
+                }
+
+                Some("regenerate_recovery_codes") => {
+                    let code: String = match dialoguer::Input::with_theme(&info_theme)
+                        .with_prompt("Authentication code")
+                        .interact_text()
+                    {
+                        Ok(code) => code,
+                        Err(_) => {
+                            eprintln!("Failed to read authentication code");
+                            continue;
+                        }
+                    };
+
+                    let codes = match babibapp.regenerate_recovery_codes(code.trim()).await {
+                        Ok(codes) => codes,
+                        Err(e) => {
+                            eprintln!("Failed to regenerate recovery codes: {}", e);
+                            continue;
+                        }
+                    };
+
+                    println!("Recovery codes successfully regenerated!");
+                    babicli::view_recovery_codes(&codes);
+                }
+
+                Some("reset_student_totp") => {
                     let id = if let Some(id) = args.next() {
                         if let Ok(id) = id.parse::<i32>() {
                             id
@@ -570,16 +959,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         continue;
                     };
 
-                    let student = match babibapp.make_student_admin(id).await {
-                        Ok(student) => student,
-                        Err(_) => {
-                            eprintln!("Failed to make student admin");
-                            continue;
-                        }
-                    };
+                    if let Err(e) = babibapp.reset_student_totp(id).await {
+                        eprintln!("Failed to reset two-factor authentication: {}", e);
+                        continue;
+                    }
 
-                    println!("Student successfully made admin!");
-                    babicli::view_student(&StudentView::Full(student));
+                    println!("Two-factor authentication successfully reset!");
                 }
 
                 Some("delete_student") => {
@@ -602,8 +987,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
                     let student = match babibapp.delete_student(id).await {
                         Ok(student) => student,
-                        Err(_) => {
-                            eprintln!("Failed to delete student");
+                        Err(e) => {
+                            eprintln!("Failed to delete student: {}", e);
                             continue;
                         }
                     };
@@ -612,12 +997,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     babicli::view_student(&StudentView::Full(student));
                 }
 
-                Some("show_teacher") => {
+                Some("show_all_roles") => {
+                    let roles = match babibapp.get_all_roles().await {
+                        Ok(roles) => roles,
+                        Err(e) => {
+                            eprintln!("Failed to get all roles: {}", e);
+                            continue;
+                        }
+                    };
+
+                    if roles.is_empty() {
+                        println!("No roles found");
+                    }
+
+                    for role in &roles {
+                        babicli::view_role(role);
+                        println!();
+                    }
+                }
+
+                Some("show_student_roles") => {
                     let id = if let Some(id) = args.next() {
                         if let Ok(id) = id.parse::<i32>() {
                             id
                         } else {
-                            eprintln!("Invalid teacher id");
+                            eprintln!("Invalid student id");
                             continue;
                         }
                     } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
@@ -626,82 +1030,72 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     {
                         id
                     } else {
-                        eprintln!("Invalid teacher id");
+                        eprintln!("Invalid student id");
                         continue;
                     };
 
-                    let teacher = match babibapp.get_teacher(id).await {
-                        Ok(teacher) => teacher,
-                        Err(_) => {
-                            eprintln!("Failed to get teacher");
-                            continue;
-                        }
-                    };
-
-                    babicli::view_teacher(&teacher);
-                }
-
-                Some("show_all_teachers") => {
-                    let teachers = match babibapp.get_all_teachers().await {
-                        Ok(teachers) => teachers,
-                        Err(_) => {
-                            eprintln!("Failed to get all teachers");
+                    let roles = match babibapp.get_student_roles(id).await {
+                        Ok(roles) => roles,
+                        Err(e) => {
+                            eprintln!("Failed to get student roles: {}", e);
                             continue;
                         }
                     };
 
-                    if teachers.is_empty() {
-                        println!("No teachers found");
-                        continue;
+                    if roles.is_empty() {
+                        println!("No roles found");
                     }
 
-                    for teacher in &teachers {
-                        babicli::view_teacher(teacher);
+                    for role in &roles {
+                        babicli::view_role(role);
                         println!();
                     }
                 }
 
-                Some("add_teacher") => {
+                Some("create_role") => {
                     let name: String = match dialoguer::Input::with_theme(&info_theme)
                         .with_prompt("Name")
                         .interact_text()
                     {
                         Ok(name) => name,
                         Err(_) => {
-                            eprintln!("Failed to read teacher name");
+                            eprintln!("Failed to read name");
                             continue;
                         }
                     };
 
-                    let prefix: String = match dialoguer::Input::with_theme(&info_theme)
-                        .with_prompt("Prefix")
-                        .interact_text()
+                    let selection = match dialoguer::MultiSelect::with_theme(&info_theme)
+                        .with_prompt("Permissions")
+                        .items(&Permission::ALL)
+                        .interact()
                     {
-                        Ok(prefix) => prefix,
+                        Ok(selection) => selection,
                         Err(_) => {
-                            eprintln!("Failed to read teacher prefix");
+                            eprintln!("Failed to read permissions");
                             continue;
                         }
                     };
 
-                    let teacher = match babibapp.add_teacher(&name, &prefix).await {
-                        Ok(teacher) => teacher,
-                        Err(_) => {
-                            eprintln!("Failed to add teacher");
+                    let permissions = selection.into_iter().map(|i| Permission::ALL[i]).collect();
+
+                    let role = match babibapp.create_role(&name, permissions).await {
+                        Ok(role) => role,
+                        Err(e) => {
+                            eprintln!("Failed to create role: {}", e);
                             continue;
                         }
                     };
 
-                    println!("Teacher successfully added!");
-                    babicli::view_teacher(&teacher);
+                    println!("\nRole successfully created!");
+                    babicli::view_role(&role);
                 }
 
-                Some("reset_teacher") => {
+                Some("delete_role") => {
                     let id = if let Some(id) = args.next() {
                         if let Ok(id) = id.parse::<i32>() {
                             id
                         } else {
-                            eprintln!("Invalid teacher id");
+                            eprintln!("Invalid role id");
                             continue;
                         }
                     } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
@@ -710,50 +1104,793 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     {
                         id
                     } else {
-                        eprintln!("Invalid teacher id");
+                        eprintln!("Invalid role id");
                         continue;
                     };
 
-                    let name: String = match dialoguer::Input::with_theme(&info_theme)
-                        .with_prompt("Name")
+                    let role = match babibapp.delete_role(id).await {
+                        Ok(role) => role,
+                        Err(e) => {
+                            eprintln!("Failed to delete role: {}", e);
+                            continue;
+                        }
+                    };
+
+                    println!("Role {} successfully deleted!", role.name);
+                }
+
+                Some("grant_role") => {
+                    let id = if let Some(id) = args.next() {
+                        if let Ok(id) = id.parse::<i32>() {
+                            id
+                        } else {
+                            eprintln!("Invalid student id");
+                            continue;
+                        }
+                    } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
+                        .with_prompt("id")
+                        .interact_text()
+                    {
+                        id
+                    } else {
+                        eprintln!("Invalid student id");
+                        continue;
+                    };
+
+                    let role = if let Some(role) = args.next() {
+                        role.to_string()
+                    } else if let Ok(role) = dialoguer::Input::<String>::with_theme(&info_theme)
+                        .with_prompt("Role")
+                        .interact_text()
+                    {
+                        role
+                    } else {
+                        eprintln!("Invalid role");
+                        continue;
+                    };
+
+                    let roles = match babibapp.grant_role(id, &role).await {
+                        Ok(roles) => roles,
+                        Err(e) => {
+                            eprintln!("Failed to grant role: {}", e);
+                            continue;
+                        }
+                    };
+
+                    println!("Role successfully granted!");
+                    for role in &roles {
+                        babicli::view_role(role);
+                        println!();
+                    }
+                }
+
+                Some("revoke_role") => {
+                    let id = if let Some(id) = args.next() {
+                        if let Ok(id) = id.parse::<i32>() {
+                            id
+                        } else {
+                            eprintln!("Invalid student id");
+                            continue;
+                        }
+                    } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
+                        .with_prompt("id")
+                        .interact_text()
+                    {
+                        id
+                    } else {
+                        eprintln!("Invalid student id");
+                        continue;
+                    };
+
+                    let role = if let Some(role) = args.next() {
+                        role.to_string()
+                    } else if let Ok(role) = dialoguer::Input::<String>::with_theme(&info_theme)
+                        .with_prompt("Role")
+                        .interact_text()
+                    {
+                        role
+                    } else {
+                        eprintln!("Invalid role");
+                        continue;
+                    };
+
+                    let roles = match babibapp.revoke_role(id, &role).await {
+                        Ok(roles) => roles,
+                        Err(e) => {
+                            eprintln!("Failed to revoke role: {}", e);
+                            continue;
+                        }
+                    };
+
+                    println!("Role successfully revoked!");
+                    for role in &roles {
+                        babicli::view_role(role);
+                        println!();
+                    }
+                }
+
+                Some("create_invite") => {
+                    let max_uses: i32 = match dialoguer::Input::with_theme(&info_theme)
+                        .with_prompt("Uses")
+                        .default(1)
+                        .interact_text()
+                    {
+                        Ok(max_uses) => max_uses,
+                        Err(_) => {
+                            eprintln!("Failed to read uses");
+                            continue;
+                        }
+                    };
+
+                    let expiration_days: i64 = match dialoguer::Input::with_theme(&info_theme)
+                        .with_prompt("Valid for days")
+                        .default(7)
+                        .interact_text()
+                    {
+                        Ok(expiration_days) => expiration_days,
+                        Err(_) => {
+                            eprintln!("Failed to read days");
+                            continue;
+                        }
+                    };
+
+                    let email: String = match dialoguer::Input::with_theme(&info_theme)
+                        .with_prompt("Email (optional)")
+                        .allow_empty(true)
+                        .interact_text()
+                    {
+                        Ok(email) => email,
+                        Err(_) => {
+                            eprintln!("Failed to read email");
+                            continue;
+                        }
+                    };
+
+                    let first_name: String = match dialoguer::Input::with_theme(&info_theme)
+                        .with_prompt("First name (optional)")
+                        .allow_empty(true)
+                        .interact_text()
+                    {
+                        Ok(first_name) => first_name,
+                        Err(_) => {
+                            eprintln!("Failed to read first name");
+                            continue;
+                        }
+                    };
+
+                    let last_name: String = match dialoguer::Input::with_theme(&info_theme)
+                        .with_prompt("Last name (optional)")
+                        .allow_empty(true)
+                        .interact_text()
+                    {
+                        Ok(last_name) => last_name,
+                        Err(_) => {
+                            eprintln!("Failed to read last name");
+                            continue;
+                        }
+                    };
+
+                    let role: String = match dialoguer::Input::with_theme(&info_theme)
+                        .with_prompt("Role (optional)")
+                        .allow_empty(true)
+                        .interact_text()
+                    {
+                        Ok(role) => role,
+                        Err(_) => {
+                            eprintln!("Failed to read role");
+                            continue;
+                        }
+                    };
+
+                    let non_empty = |s: String| if s.is_empty() { None } else { Some(s) };
+
+                    let invite = CreateInvite {
+                        max_uses: Some(max_uses),
+                        expiration_days: Some(expiration_days),
+                        email: non_empty(email),
+                        first_name: non_empty(first_name),
+                        last_name: non_empty(last_name),
+                        role: non_empty(role),
+                    };
+
+                    let created = match babibapp.create_invite(&invite).await {
+                        Ok(created) => created,
+                        Err(e) => {
+                            eprintln!("Failed to create invite: {}", e);
+                            continue;
+                        }
+                    };
+
+                    println!("\nInvite successfully created!");
+                    println!("Code: {}", created.code);
+                    println!("The code is only shown once.\n");
+                    babicli::view_invite(&created.invite);
+                }
+
+                Some("show_all_invites") => {
+                    let invites = match babibapp.get_all_invites().await {
+                        Ok(invites) => invites,
+                        Err(e) => {
+                            eprintln!("Failed to get all invites: {}", e);
+                            continue;
+                        }
+                    };
+
+                    if invites.is_empty() {
+                        println!("No invites found");
+                    }
+
+                    for invite in &invites {
+                        babicli::view_invite(invite);
+                        println!();
+                    }
+                }
+
+                Some("revoke_invite") => {
+                    let id = if let Some(id) = args.next() {
+                        if let Ok(id) = id.parse::<i32>() {
+                            id
+                        } else {
+                            eprintln!("Invalid invite id");
+                            continue;
+                        }
+                    } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
+                        .with_prompt("id")
+                        .interact_text()
+                    {
+                        id
+                    } else {
+                        eprintln!("Invalid invite id");
+                        continue;
+                    };
+
+                    let invite = match babibapp.revoke_invite(id).await {
+                        Ok(invite) => invite,
+                        Err(e) => {
+                            eprintln!("Failed to revoke invite: {}", e);
+                            continue;
+                        }
+                    };
+
+                    println!("Invite successfully revoked!");
+                    babicli::view_invite(&invite);
+                }
+
+                Some("create_api_key") => {
+                    let name: String = match dialoguer::Input::with_theme(&info_theme)
+                        .with_prompt("Name")
+                        .interact_text()
+                    {
+                        Ok(name) => name,
+                        Err(_) => {
+                            eprintln!("Failed to read name");
+                            continue;
+                        }
+                    };
+
+                    let scope = match dialoguer::Select::with_theme(&info_theme)
+                        .with_prompt("Scope")
+                        .items(&ApiKeyScope::ALL)
+                        .interact()
+                    {
+                        Ok(idx) => ApiKeyScope::ALL[idx],
+                        Err(_) => {
+                            eprintln!("Failed to read scope");
+                            continue;
+                        }
+                    };
+
+                    let student_id: String = match dialoguer::Input::with_theme(&info_theme)
+                        .with_prompt("Acts as student id (optional)")
+                        .allow_empty(true)
+                        .interact_text()
+                    {
+                        Ok(student_id) => student_id,
+                        Err(_) => {
+                            eprintln!("Failed to read student id");
+                            continue;
+                        }
+                    };
+
+                    let student_id = if student_id.trim().is_empty() {
+                        None
+                    } else if let Ok(id) = student_id.trim().parse::<i32>() {
+                        Some(id)
+                    } else {
+                        eprintln!("Invalid student id");
+                        continue;
+                    };
+
+                    let created = match babibapp.create_api_key(&name, scope, student_id).await {
+                        Ok(created) => created,
+                        Err(e) => {
+                            eprintln!("Failed to create API key: {}", e);
+                            continue;
+                        }
+                    };
+
+                    println!("\nAPI key successfully created!");
+                    println!("Key: {}", created.key);
+                    println!("The key is only shown once.\n");
+                    babicli::view_api_key(&created.api_key);
+                }
+
+                Some("show_all_api_keys") => {
+                    let api_keys = match babibapp.get_all_api_keys().await {
+                        Ok(api_keys) => api_keys,
+                        Err(e) => {
+                            eprintln!("Failed to get all API keys: {}", e);
+                            continue;
+                        }
+                    };
+
+                    if api_keys.is_empty() {
+                        println!("No API keys found");
+                    }
+
+                    for api_key in &api_keys {
+                        babicli::view_api_key(api_key);
+                        println!();
+                    }
+                }
+
+                Some("revoke_api_key") => {
+                    let id = if let Some(id) = args.next() {
+                        if let Ok(id) = id.parse::<i32>() {
+                            id
+                        } else {
+                            eprintln!("Invalid API key id");
+                            continue;
+                        }
+                    } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
+                        .with_prompt("id")
+                        .interact_text()
+                    {
+                        id
+                    } else {
+                        eprintln!("Invalid API key id");
+                        continue;
+                    };
+
+                    let api_key = match babibapp.revoke_api_key(id).await {
+                        Ok(api_key) => api_key,
+                        Err(e) => {
+                            eprintln!("Failed to revoke API key: {}", e);
+                            continue;
+                        }
+                    };
+
+                    println!("API key successfully revoked!");
+                    babicli::view_api_key(&api_key);
+                }
+
+                Some("show_all_lockouts") => {
+                    let lockouts = match babibapp.get_all_lockouts().await {
+                        Ok(lockouts) => lockouts,
+                        Err(e) => {
+                            eprintln!("Failed to get all lockouts: {}", e);
+                            continue;
+                        }
+                    };
+
+                    if lockouts.is_empty() {
+                        println!("No lockouts found");
+                    }
+
+                    for lockout in &lockouts {
+                        babicli::view_lockout(lockout);
+                        println!();
+                    }
+                }
+
+                Some("clear_lockout") => {
+                    let id = if let Some(id) = args.next() {
+                        if let Ok(id) = id.parse::<i32>() {
+                            id
+                        } else {
+                            eprintln!("Invalid lockout id");
+                            continue;
+                        }
+                    } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
+                        .with_prompt("id")
                         .interact_text()
                     {
-                        Ok(name) => name,
-                        Err(_) => {
-                            eprintln!("Failed to read teacher name");
+                        id
+                    } else {
+                        eprintln!("Invalid lockout id");
+                        continue;
+                    };
+
+                    let lockout = match babibapp.clear_lockout(id).await {
+                        Ok(lockout) => lockout,
+                        Err(e) => {
+                            eprintln!("Failed to clear lockout: {}", e);
+                            continue;
+                        }
+                    };
+
+                    println!("Lockout successfully cleared!");
+                    babicli::view_lockout(&lockout);
+                }
+
+                Some("show_teacher") => {
+                    let id = if let Some(id) = args.next() {
+                        if let Ok(id) = id.parse::<i32>() {
+                            id
+                        } else {
+                            eprintln!("Invalid teacher id");
+                            continue;
+                        }
+                    } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
+                        .with_prompt("id")
+                        .interact_text()
+                    {
+                        id
+                    } else {
+                        eprintln!("Invalid teacher id");
+                        continue;
+                    };
+
+                    let teacher = match babibapp.get_teacher(id).await {
+                        Ok(teacher) => teacher,
+                        Err(e) => {
+                            eprintln!("Failed to get teacher: {}", e);
+                            continue;
+                        }
+                    };
+
+                    babicli::view_teacher(&teacher);
+                }
+
+                Some("show_all_teachers") => {
+                    let teachers =
+                        match babibapp.stream_all_teachers().try_collect::<Vec<_>>().await {
+                            Ok(teachers) => teachers,
+                            Err(e) => {
+                                eprintln!("Failed to get all teachers: {}", e);
+                                continue;
+                            }
+                        };
+
+                    if teachers.is_empty() {
+                        println!("No teachers found");
+                        continue;
+                    }
+
+                    for teacher in &teachers {
+                        babicli::view_teacher(teacher);
+                        println!();
+                    }
+                }
+
+                Some("add_teacher") => {
+                    let name: String = match dialoguer::Input::with_theme(&info_theme)
+                        .with_prompt("Name")
+                        .interact_text()
+                    {
+                        Ok(name) => name,
+                        Err(_) => {
+                            eprintln!("Failed to read teacher name");
+                            continue;
+                        }
+                    };
+
+                    let prefix: String = match dialoguer::Input::with_theme(&info_theme)
+                        .with_prompt("Prefix")
+                        .interact_text()
+                    {
+                        Ok(prefix) => prefix,
+                        Err(_) => {
+                            eprintln!("Failed to read teacher prefix");
+                            continue;
+                        }
+                    };
+
+                    let teacher = match babibapp.add_teacher(&name, &prefix).await {
+                        Ok(teacher) => teacher,
+                        Err(e) => {
+                            eprintln!("Failed to add teacher: {}", e);
+                            continue;
+                        }
+                    };
+
+                    println!("Teacher successfully added!");
+                    babicli::view_teacher(&teacher);
+                }
+
+                Some("reset_teacher") => {
+                    let id = if let Some(id) = args.next() {
+                        if let Ok(id) = id.parse::<i32>() {
+                            id
+                        } else {
+                            eprintln!("Invalid teacher id");
+                            continue;
+                        }
+                    } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
+                        .with_prompt("id")
+                        .interact_text()
+                    {
+                        id
+                    } else {
+                        eprintln!("Invalid teacher id");
+                        continue;
+                    };
+
+                    let name: String = match dialoguer::Input::with_theme(&info_theme)
+                        .with_prompt("Name")
+                        .interact_text()
+                    {
+                        Ok(name) => name,
+                        Err(_) => {
+                            eprintln!("Failed to read teacher name");
+                            continue;
+                        }
+                    };
+
+                    let prefix: String = match dialoguer::Input::with_theme(&info_theme)
+                        .with_prompt("Prefix")
+                        .interact_text()
+                    {
+                        Ok(prefix) => prefix,
+                        Err(_) => {
+                            eprintln!("Failed to read teacher prefix");
+                            continue;
+                        }
+                    };
+
+                    let teacher = match babibapp.reset_teacher(id, &name, &prefix).await {
+                        Ok(teacher) => teacher,
+                        Err(e) => {
+                            eprintln!("Failed to reset teacher: {}", e);
+                            continue;
+                        }
+                    };
+
+                    println!("Teacher successfully reset!");
+                    babicli::view_teacher(&teacher);
+                }
+
+                Some("delete_teacher") => {
+                    let id = if let Some(id) = args.next() {
+                        if let Ok(id) = id.parse::<i32>() {
+                            id
+                        } else {
+                            eprintln!("Invalid teacher id");
+                            continue;
+                        }
+                    } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
+                        .with_prompt("id")
+                        .interact_text()
+                    {
+                        id
+                    } else {
+                        eprintln!("Invalid teacher id");
+                        continue;
+                    };
+
+                    let teacher = match babibapp.delete_teacher(id).await {
+                        Ok(teacher) => teacher,
+                        Err(e) => {
+                            eprintln!("Failed to delete teacher: {}", e);
+                            continue;
+                        }
+                    };
+
+                    println!("Teacher successfully deleted!");
+                    babicli::view_teacher(&teacher);
+                }
+
+                Some("show_comment") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+
+                    let id = if let Some(id) = args.next() {
+                        if let Ok(id) = id.parse::<i32>() {
+                            id
+                        } else {
+                            eprintln!("Invalid comment id");
+                            continue;
+                        }
+                    } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
+                        .with_prompt("id")
+                        .interact_text()
+                    {
+                        id
+                    } else {
+                        eprintln!("Invalid comment id");
+                        continue;
+                    };
+
+                    let comment = match babibapp.get_comment(Some(target), id).await {
+                        Ok(comment) => comment,
+                        Err(e) => {
+                            eprintln!("Failed to get {} comment: {}", target, e);
+                            continue;
+                        }
+                    };
+
+                    let vote = match babibapp.get_comment_vote(Some(target), id).await {
+                        Ok(vote) => vote,
+                        Err(e) => {
+                            eprintln!("Failed to get {} comment vote: {}", target, e);
+                            continue;
+                        }
+                    };
+
+                    let reactions = match babibapp.get_comment_reactions(Some(target), id).await {
+                        Ok(reactions) => reactions,
+                        Err(e) => {
+                            eprintln!("Failed to get {} comment reactions: {}", target, e);
+                            continue;
+                        }
+                    };
+
+                    if let Err(e) = view_comment(&babibapp, &comment, vote).await {
+                        eprintln!("Failed to show {} comment: {}", target, e);
+                        continue;
+                    }
+                    babicli::view_comment_reactions(&reactions);
+                }
+
+                Some("show_all_comments") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+
+                    let sort = match args.next().map(|sort| sort.parse::<CommentSort>()) {
+                        Some(Ok(sort)) => sort,
+                        Some(Err(e)) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                        None => CommentSort::default(),
+                    };
+
+                    let comments = match babibapp
+                        .stream_all_comments(
+                            sort,
+                            CommentFilter {
+                                target: Some(target),
+                                ..Default::default()
+                            },
+                        )
+                        .try_collect::<Vec<_>>()
+                        .await
+                    {
+                        Ok(comments) => comments,
+                        Err(e) => {
+                            eprintln!("Failed to get all {} comments: {}", target, e);
                             continue;
                         }
                     };
 
-                    let prefix: String = match dialoguer::Input::with_theme(&info_theme)
-                        .with_prompt("Prefix")
+                    if comments.is_empty() {
+                        println!("No {} comments found!", target);
+                        continue;
+                    }
+
+                    for scored in &comments {
+                        if let Err(e) =
+                            view_comment(&babibapp, &scored.comment, scored.votes.score).await
+                        {
+                            eprintln!("Failed to show {} comment: {}", target, e);
+                            continue;
+                        }
+                        babicli::view_comment_votes(&scored.votes);
+                        babicli::view_comment_reactions(&scored.reactions);
+                        println!();
+                    }
+                }
+
+                Some("create_comment") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+
+                    let recv_id = if let Some(id) = args.next() {
+                        if let Ok(id) = id.parse::<i32>() {
+                            id
+                        } else {
+                            eprintln!("Invalid receiver id");
+                            continue;
+                        }
+                    } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
+                        .with_prompt("Receiver id")
                         .interact_text()
                     {
-                        Ok(prefix) => prefix,
-                        Err(_) => {
-                            eprintln!("Failed to read teacher prefix");
+                        id
+                    } else {
+                        eprintln!("Invalid receiver id");
+                        continue;
+                    };
+
+                    let body = match dialoguer::Editor::new().edit("Enter your comment") {
+                        Ok(Some(body)) => body,
+                        _ => {
+                            eprintln!("Abort!");
                             continue;
                         }
                     };
 
-                    let teacher = match babibapp.reset_teacher(id, &name, &prefix).await {
-                        Ok(teacher) => teacher,
+                    if body.trim().is_empty() {
+                        eprintln!("Empty comment, abort!");
+                        continue;
+                    }
+
+                    let anonymous = match dialoguer::Confirm::with_theme(&info_theme)
+                        .with_prompt("Post anonymously?")
+                        .default(false)
+                        .interact()
+                    {
+                        Ok(anonymous) => anonymous,
                         Err(_) => {
-                            eprintln!("Failed to reset teacher");
+                            eprintln!("Abort!");
                             continue;
                         }
                     };
 
-                    println!("Teacher successfully reset!");
-                    babicli::view_teacher(&teacher);
+                    let comment = match babibapp
+                        .create_comment(target, recv_id, &body, Some(anonymous))
+                        .await
+                    {
+                        Ok(comment) => comment,
+                        Err(e) => {
+                            eprintln!("Failed to create {} comment: {}", target, e);
+                            continue;
+                        }
+                    };
+
+                    let recv_name = match receiver_name(&babibapp, target, recv_id).await {
+                        Ok(recv_name) => recv_name,
+                        Err(e) => {
+                            eprintln!("Failed to get receiver: {}", e);
+                            continue;
+                        }
+                    };
+
+                    let author = if comment.anonymous {
+                        None
+                    } else {
+                        match babibapp.get_self().await {
+                            Ok(author) => Some(StudentView::Full(author)),
+                            Err(e) => {
+                                eprintln!("Failed to get self: {}", e);
+                                continue;
+                            }
+                        }
+                    };
+
+                    println!("Comment successfully created!");
+                    babicli::view_comment_full(&comment, &recv_name, author.as_ref(), 0);
                 }
 
-                Some("delete_teacher") => {
+                Some("upvote_comment") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+
                     let id = if let Some(id) = args.next() {
                         if let Ok(id) = id.parse::<i32>() {
                             id
                         } else {
-                            eprintln!("Invalid teacher id");
+                            eprintln!("Invalid comment id");
                             continue;
                         }
                     } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
@@ -762,28 +1899,37 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     {
                         id
                     } else {
-                        eprintln!("Invalid teacher id");
+                        eprintln!("Invalid comment id");
                         continue;
                     };
 
-                    let teacher = match babibapp.delete_teacher(id).await {
-                        Ok(teacher) => teacher,
-                        Err(_) => {
-                            eprintln!("Failed to delete teacher");
+                    let votes = match babibapp.upvote_comment(Some(target), id).await {
+                        Ok(votes) => votes,
+                        Err(e) => {
+                            eprintln!("Failed to upvote {} comment: {}", target, e);
                             continue;
                         }
                     };
 
-                    println!("Teacher successfully deleted!");
-                    babicli::view_teacher(&teacher);
+                    println!("Comment successfully upvoted!");
+                    println!("Score: {}", votes.score);
+                    babicli::view_comment_votes(&votes);
                 }
 
-                Some("show_student_comment") => {
+                Some("downvote_comment") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+
                     let id = if let Some(id) = args.next() {
                         if let Ok(id) = id.parse::<i32>() {
                             id
                         } else {
-                            eprintln!("Invalid student comment id");
+                            eprintln!("Invalid comment id");
                             continue;
                         }
                     } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
@@ -792,152 +1938,307 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     {
                         id
                     } else {
-                        eprintln!("Invalid student comment id");
+                        eprintln!("Invalid comment id");
                         continue;
                     };
 
-                    let comment = match babibapp.get_student_comment(id).await {
-                        Ok(comment) => comment,
-                        Err(_) => {
-                            eprintln!("Failed to get student comment");
+                    let votes = match babibapp.downvote_comment(Some(target), id).await {
+                        Ok(votes) => votes,
+                        Err(e) => {
+                            eprintln!("Failed to downvote {} comment: {}", target, e);
                             continue;
                         }
                     };
 
-                    let vote = match babibapp.get_student_comment_vote(id).await {
-                        Ok(vote) => vote,
-                        Err(_) => {
-                            eprintln!("Failed to get student comment vote");
+                    println!("Comment successfully downvoted!");
+                    println!("Score: {}", votes.score);
+                    babicli::view_comment_votes(&votes);
+                }
+
+                Some("unvote_comment") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
                             continue;
                         }
                     };
 
-                    match comment {
-                        StudentCommentView::Limited(comment) => {
-                            let receiver = match babibapp.get_student(comment.receiver_id).await {
-                                Ok(student) => student,
-                                Err(_) => {
-                                    eprintln!("Failed to get receiver student");
-                                    continue;
-                                }
-                            };
+                    let id = if let Some(id) = args.next() {
+                        if let Ok(id) = id.parse::<i32>() {
+                            id
+                        } else {
+                            eprintln!("Invalid comment id");
+                            continue;
+                        }
+                    } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
+                        .with_prompt("id")
+                        .interact_text()
+                    {
+                        id
+                    } else {
+                        eprintln!("Invalid comment id");
+                        continue;
+                    };
 
-                            babicli::view_student_comment_limited(&comment, &receiver, vote);
+                    let votes = match babibapp.unvote_comment(Some(target), id).await {
+                        Ok(votes) => votes,
+                        Err(e) => {
+                            eprintln!("Failed to unvote {} comment: {}", target, e);
+                            continue;
                         }
-                        StudentCommentView::Full(comment) => {
-                            let receiver = match babibapp.get_student(comment.receiver_id).await {
-                                Ok(student) => student,
-                                Err(_) => {
-                                    eprintln!("Failed to get receiver student");
-                                    continue;
-                                }
-                            };
+                    };
 
-                            let author = match babibapp.get_student(comment.author_id).await {
-                                Ok(student) => student,
-                                Err(_) => {
-                                    eprintln!("Failed to get author");
-                                    continue;
-                                }
-                            };
+                    println!("Comment successfully unvoted!");
+                    println!("Score: {}", votes.score);
+                    babicli::view_comment_votes(&votes);
+                }
 
-                            babicli::view_student_comment_full(&comment, &receiver, &author, vote);
+                Some("react_comment") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
                         }
-                    }
+                    };
+
+                    let id = if let Some(id) = args.next() {
+                        if let Ok(id) = id.parse::<i32>() {
+                            id
+                        } else {
+                            eprintln!("Invalid comment id");
+                            continue;
+                        }
+                    } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
+                        .with_prompt("id")
+                        .interact_text()
+                    {
+                        id
+                    } else {
+                        eprintln!("Invalid comment id");
+                        continue;
+                    };
+
+                    let reaction = if let Some(reaction) = args.next() {
+                        reaction.to_string()
+                    } else {
+                        let reaction_set = match babibapp.get_comment_reaction_set().await {
+                            Ok(reaction_set) => reaction_set,
+                            Err(e) => {
+                                eprintln!("Failed to get reactions: {}", e);
+                                continue;
+                            }
+                        };
+
+                        match dialoguer::Select::with_theme(&info_theme)
+                            .with_prompt("Reaction")
+                            .items(&reaction_set)
+                            .interact()
+                        {
+                            Ok(idx) => reaction_set[idx].clone(),
+                            Err(_) => {
+                                eprintln!("Failed to read reaction");
+                                continue;
+                            }
+                        }
+                    };
+
+                    let reactions =
+                        match babibapp.react_to_comment(Some(target), id, &reaction).await {
+                            Ok(reactions) => reactions,
+                            Err(e) => {
+                                eprintln!("Failed to react to {} comment: {}", target, e);
+                                continue;
+                            }
+                        };
+
+                    println!("Reacted to comment!");
+                    babicli::view_comment_reactions(&reactions);
                 }
 
-                Some("show_all_student_comments") => {
-                    let comments = match babibapp.get_all_student_comments().await {
-                        Ok(comments) => comments,
-                        Err(_) => {
-                            eprintln!("Failed to get all student comments");
+                Some("unreact_comment") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
                             continue;
                         }
                     };
 
-                    if comments.is_empty() {
-                        println!("No student comments found!");
+                    let id = if let Some(id) = args.next() {
+                        if let Ok(id) = id.parse::<i32>() {
+                            id
+                        } else {
+                            eprintln!("Invalid comment id");
+                            continue;
+                        }
+                    } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
+                        .with_prompt("id")
+                        .interact_text()
+                    {
+                        id
+                    } else {
+                        eprintln!("Invalid comment id");
                         continue;
-                    }
+                    };
 
-                    for comment in &comments {
-                        match comment {
-                            StudentCommentView::Limited(comment) => {
-                                let vote = match babibapp.get_student_comment_vote(comment.id).await
-                                {
-                                    Ok(vote) => vote,
-                                    Err(_) => {
-                                        eprintln!("Failed to get student comment vote");
-                                        continue;
-                                    }
-                                };
+                    let reaction = if let Some(reaction) = args.next() {
+                        reaction.to_string()
+                    } else {
+                        let own_reactions: Vec<String> =
+                            match babibapp.get_comment_reactions(Some(target), id).await {
+                                Ok(reactions) => reactions
+                                    .into_iter()
+                                    .filter(|reaction| reaction.own)
+                                    .map(|reaction| reaction.reaction)
+                                    .collect(),
+                                Err(e) => {
+                                    eprintln!("Failed to get {} comment reactions: {}", target, e);
+                                    continue;
+                                }
+                            };
 
-                                let receiver = match babibapp.get_student(comment.receiver_id).await
-                                {
-                                    Ok(student) => student,
-                                    Err(_) => {
-                                        eprintln!("Failed to get receiver student");
-                                        continue;
-                                    }
-                                };
+                        if own_reactions.is_empty() {
+                            println!("You didn't react to this comment");
+                            continue;
+                        }
 
-                                babicli::view_student_comment_limited(comment, &receiver, vote);
+                        match dialoguer::Select::with_theme(&info_theme)
+                            .with_prompt("Reaction")
+                            .items(&own_reactions)
+                            .interact()
+                        {
+                            Ok(idx) => own_reactions[idx].clone(),
+                            Err(_) => {
+                                eprintln!("Failed to read reaction");
+                                continue;
                             }
-                            StudentCommentView::Full(comment) => {
-                                let vote = match babibapp.get_student_comment_vote(comment.id).await
-                                {
-                                    Ok(vote) => vote,
-                                    Err(_) => {
-                                        eprintln!("Failed to get student comment vote");
-                                        continue;
-                                    }
-                                };
+                        }
+                    };
 
-                                let receiver = match babibapp.get_student(comment.receiver_id).await
-                                {
-                                    Ok(student) => student,
-                                    Err(_) => {
-                                        eprintln!("Failed to get receiver student");
-                                        continue;
-                                    }
-                                };
+                    let reactions = match babibapp
+                        .unreact_to_comment(Some(target), id, &reaction)
+                        .await
+                    {
+                        Ok(reactions) => reactions,
+                        Err(e) => {
+                            eprintln!("Failed to take back reaction: {}", e);
+                            continue;
+                        }
+                    };
 
-                                let author = match babibapp.get_student(comment.author_id).await {
-                                    Ok(student) => student,
-                                    Err(_) => {
-                                        eprintln!("Failed to get author");
-                                        continue;
-                                    }
-                                };
+                    println!("Reaction successfully taken back!");
+                    babicli::view_comment_reactions(&reactions);
+                }
+
+                Some("show_comment_thread") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+
+                    let id = if let Some(id) = args.next() {
+                        if let Ok(id) = id.parse::<i32>() {
+                            id
+                        } else {
+                            eprintln!("Invalid comment id");
+                            continue;
+                        }
+                    } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
+                        .with_prompt("id")
+                        .interact_text()
+                    {
+                        id
+                    } else {
+                        eprintln!("Invalid comment id");
+                        continue;
+                    };
+
+                    let thread = match babibapp.get_comment_thread(Some(target), id).await {
+                        Ok(thread) => thread,
+                        Err(e) => {
+                            eprintln!("Failed to get {} comment thread: {}", target, e);
+                            continue;
+                        }
+                    };
 
-                                babicli::view_student_comment_full(
-                                    comment, &receiver, &author, vote,
-                                );
+                    let students =
+                        match babibapp.stream_all_students().try_collect::<Vec<_>>().await {
+                            Ok(students) => students,
+                            Err(e) => {
+                                eprintln!("Failed to get all students: {}", e);
+                                continue;
                             }
+                        };
+
+                    let author_name = |comment: &Comment| {
+                        if comment.anonymous {
+                            return Some("anonymous".to_string());
+                        }
+                        students.iter().find_map(|student| match student {
+                            StudentView::Limited(s) if s.id == comment.author_id => {
+                                Some(format!("{} {}", s.first_name, s.last_name))
+                            }
+                            StudentView::Full(s) if s.id == comment.author_id => {
+                                Some(format!("{} {}", s.first_name, s.last_name))
+                            }
+                            _ => None,
+                        })
+                    };
+
+                    for entry in &thread {
+                        match &entry.comment {
+                            CommentView::Limited(comment) => babicli::view_thread_comment(
+                                entry.depth,
+                                comment.id,
+                                comment.anonymous.then_some("anonymous"),
+                                &comment.body,
+                                comment.published,
+                                comment.deleted,
+                            ),
+                            CommentView::Full(comment) => babicli::view_thread_comment(
+                                entry.depth,
+                                comment.id,
+                                author_name(comment).as_deref(),
+                                &comment.body,
+                                comment.published,
+                                comment.deleted,
+                            ),
                         }
-                        println!();
                     }
                 }
 
-                Some("create_student_comment") => {
-                    let recv_id = if let Some(id) = args.next() {
+                Some("reply_comment") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+
+                    let id = if let Some(id) = args.next() {
                         if let Ok(id) = id.parse::<i32>() {
                             id
                         } else {
-                            eprintln!("Invalid student comment id");
+                            eprintln!("Invalid comment id");
                             continue;
                         }
                     } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
-                        .with_prompt("Receiver id")
+                        .with_prompt("id")
                         .interact_text()
                     {
                         id
                     } else {
-                        eprintln!("Invalid student comment id");
+                        eprintln!("Invalid comment id");
                         continue;
                     };
 
-                    let body = match dialoguer::Editor::new().edit("Enter your comment") {
+                    let body = match dialoguer::Editor::new().edit("Enter your reply") {
                         Ok(Some(body)) => body,
                         _ => {
                             eprintln!("Abort!");
@@ -950,45 +2251,54 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         continue;
                     }
 
-                    let comment = match babibapp.create_student_comment(recv_id, &body).await {
-                        Ok(comment) => comment,
-                        Err(_) => {
-                            eprintln!("Failed to create student comment");
-                            continue;
-                        }
-                    };
-
-                    let receiver = match babibapp.get_student(recv_id).await {
-                        Ok(recv) => recv,
+                    let anonymous = match dialoguer::Confirm::with_theme(&info_theme)
+                        .with_prompt("Post anonymously?")
+                        .default(false)
+                        .interact()
+                    {
+                        Ok(anonymous) => anonymous,
                         Err(_) => {
-                            eprintln!("Failed to get receiver");
+                            eprintln!("Abort!");
                             continue;
                         }
                     };
 
-                    let author = match babibapp.get_self().await {
-                        Ok(author) => author,
-                        Err(_) => {
-                            eprintln!("Failed to get self");
+                    let comment = match babibapp
+                        .reply_to_comment(Some(target), id, &body, Some(anonymous))
+                        .await
+                    {
+                        Ok(comment) => comment,
+                        Err(e) => {
+                            eprintln!("Failed to reply to {} comment: {}", target, e);
                             continue;
                         }
                     };
 
-                    println!("Student comment successfully created!");
-                    babicli::view_student_comment_full(
-                        &comment,
-                        &receiver,
-                        &StudentView::Full(author),
+                    println!("Reply successfully created!");
+                    babicli::view_thread_comment(
                         0,
+                        comment.id,
+                        None,
+                        &comment.body,
+                        comment.published,
+                        comment.deleted,
                     );
                 }
 
-                Some("upvote_student_comment") => {
+                Some("edit_comment") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+
                     let id = if let Some(id) = args.next() {
                         if let Ok(id) = id.parse::<i32>() {
                             id
                         } else {
-                            eprintln!("Invalid student comment id");
+                            eprintln!("Invalid comment id");
                             continue;
                         }
                     } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
@@ -997,50 +2307,68 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     {
                         id
                     } else {
-                        eprintln!("Invalid student comment id");
+                        eprintln!("Invalid comment id");
                         continue;
                     };
 
-                    if babibapp.upvote_student_comment(id).await.is_err() {
-                        eprintln!("Failed to upvote student comment");
+                    let old_body = match babibapp.get_comment(Some(target), id).await {
+                        Ok(CommentView::Limited(comment)) => comment.body,
+                        Ok(CommentView::Full(comment)) => comment.body,
+                        Err(e) => {
+                            eprintln!("Failed to get {} comment: {}", target, e);
+                            continue;
+                        }
+                    };
+
+                    let body = match dialoguer::Editor::new().edit(&old_body) {
+                        Ok(Some(body)) => body,
+                        _ => {
+                            eprintln!("Abort!");
+                            continue;
+                        }
+                    };
+
+                    if body.trim().is_empty() {
+                        eprintln!("Empty comment, abort!");
                         continue;
                     }
 
-                    println!("Student comment successfully upvoted!");
-                }
+                    let comment = match babibapp.edit_comment(Some(target), id, &body).await {
+                        Ok(comment) => comment,
+                        Err(e) => {
+                            eprintln!("Failed to edit {} comment: {}", target, e);
+                            continue;
+                        }
+                    };
 
-                Some("downvote_student_comment") => {
-                    let id = if let Some(id) = args.next() {
-                        if let Ok(id) = id.parse::<i32>() {
-                            id
-                        } else {
-                            eprintln!("Invalid student comment id");
+                    let vote = match babibapp.get_comment_vote(Some(target), id).await {
+                        Ok(vote) => vote,
+                        Err(e) => {
+                            eprintln!("Failed to get {} comment vote: {}", target, e);
                             continue;
                         }
-                    } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
-                        .with_prompt("id")
-                        .interact_text()
-                    {
-                        id
-                    } else {
-                        eprintln!("Invalid student comment id");
-                        continue;
                     };
 
-                    if babibapp.downvote_student_comment(id).await.is_err() {
-                        eprintln!("Failed to downvote student comment");
-                        continue;
+                    println!("Comment successfully edited!");
+                    if let Err(e) = view_comment(&babibapp, &comment, vote).await {
+                        eprintln!("Failed to show {} comment: {}", target, e);
                     }
-
-                    println!("Student comment successfully downvoted!");
                 }
 
-                Some("unvote_student_comment") => {
+                Some("show_comment_history") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+
                     let id = if let Some(id) = args.next() {
                         if let Ok(id) = id.parse::<i32>() {
                             id
                         } else {
-                            eprintln!("Invalid student comment id");
+                            eprintln!("Invalid comment id");
                             continue;
                         }
                     } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
@@ -1049,24 +2377,47 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     {
                         id
                     } else {
-                        eprintln!("Invalid student comment id");
+                        eprintln!("Invalid comment id");
                         continue;
                     };
 
-                    if babibapp.unvote_student_comment(id).await.is_err() {
-                        eprintln!("Failed to unvote student comment");
-                        continue;
+                    let revisions = match babibapp.get_comment_history(Some(target), id).await {
+                        Ok(revisions) => revisions,
+                        Err(e) => {
+                            eprintln!("Failed to get {} comment history: {}", target, e);
+                            continue;
+                        }
+                    };
+
+                    if revisions.is_empty() {
+                        println!("The comment was never edited");
                     }
 
-                    println!("Student comment successfully unvoted!");
+                    for revision in &revisions {
+                        babicli::view_comment_revision(
+                            revision.editor_id,
+                            &revision.body,
+                            revision.written,
+                            revision.replaced,
+                        );
+                        println!();
+                    }
                 }
 
-                Some("delete_student_comment") => {
+                Some("delete_comment") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+
                     let id = if let Some(id) = args.next() {
                         if let Ok(id) = id.parse::<i32>() {
                             id
                         } else {
-                            eprintln!("Invalid student comment id");
+                            eprintln!("Invalid comment id");
                             continue;
                         }
                     } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
@@ -1075,27 +2426,35 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     {
                         id
                     } else {
-                        eprintln!("Invalid student comment id");
+                        eprintln!("Invalid comment id");
                         continue;
                     };
 
-                    let _ = match babibapp.delete_student_comment(id).await {
+                    let _ = match babibapp.delete_comment(Some(target), id).await {
                         Ok(comment) => comment,
-                        Err(_) => {
-                            eprintln!("Failed to delete student comment");
+                        Err(e) => {
+                            eprintln!("Failed to delete {} comment: {}", target, e);
                             continue;
                         }
                     };
 
-                    println!("Student comment successfully deleted!");
+                    println!("Comment successfully deleted!");
                 }
 
-                Some("show_teacher_comment") => {
+                Some("report_comment") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+
                     let id = if let Some(id) = args.next() {
                         if let Ok(id) = id.parse::<i32>() {
                             id
                         } else {
-                            eprintln!("Invalid teacher comment id");
+                            eprintln!("Invalid comment id");
                             continue;
                         }
                     } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
@@ -1104,203 +2463,151 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     {
                         id
                     } else {
-                        eprintln!("Invalid teacher comment id");
+                        eprintln!("Invalid comment id");
                         continue;
                     };
 
-                    let comment = match babibapp.get_teacher_comment(id).await {
-                        Ok(comment) => comment,
+                    let reason: String = match dialoguer::Input::with_theme(&info_theme)
+                        .with_prompt("Reason")
+                        .interact_text()
+                    {
+                        Ok(reason) => reason,
                         Err(_) => {
-                            eprintln!("Failed to get teacher comment");
+                            eprintln!("Failed to read reason");
                             continue;
                         }
                     };
 
-                    let vote = match babibapp.get_teacher_comment_vote(id).await {
-                        Ok(vote) => vote,
-                        Err(_) => {
-                            eprintln!("Failed to get teacher comment vote");
+                    let _ = match babibapp.report_comment(Some(target), id, &reason).await {
+                        Ok(report) => report,
+                        Err(e) => {
+                            eprintln!("Failed to report {} comment: {}", target, e);
                             continue;
                         }
                     };
 
-                    match comment {
-                        TeacherCommentView::Limited(comment) => {
-                            let receiver = match babibapp.get_teacher(comment.receiver_id).await {
-                                Ok(teacher) => teacher,
-                                Err(_) => {
-                                    eprintln!("Failed to get receiver teacher");
-                                    continue;
-                                }
-                            };
-
-                            babicli::view_teacher_comment_limited(&comment, &receiver, vote);
-                        }
-                        TeacherCommentView::Full(comment) => {
-                            let receiver = match babibapp.get_teacher(comment.receiver_id).await {
-                                Ok(teacher) => teacher,
-                                Err(_) => {
-                                    eprintln!("Failed to get receiver teacher");
-                                    continue;
-                                }
-                            };
-
-                            let author = match babibapp.get_student(comment.author_id).await {
-                                Ok(teacher) => teacher,
-                                Err(_) => {
-                                    eprintln!("Failed to get author");
-                                    continue;
-                                }
-                            };
+                    println!("Comment successfully reported!");
+                }
 
-                            babicli::view_teacher_comment_full(&comment, &receiver, &author, vote);
+                Some("show_comment_reports") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
                         }
-                    }
-                }
+                    };
 
-                Some("show_all_teacher_comments") => {
-                    let comments = match babibapp.get_all_teacher_comments().await {
-                        Ok(comments) => comments,
-                        Err(_) => {
-                            eprintln!("Failed to get all teacher comments");
+                    let queue = match babibapp.get_comment_reports(Some(target)).await {
+                        Ok(queue) => queue,
+                        Err(e) => {
+                            eprintln!("Failed to get reported {} comments: {}", target, e);
                             continue;
                         }
                     };
 
-                    if comments.is_empty() {
-                        println!("No teacher comments found!");
-                        continue;
+                    if queue.is_empty() {
+                        println!("No reported comments");
                     }
 
-                    for comment in &comments {
-                        match comment {
-                            TeacherCommentView::Limited(comment) => {
-                                let vote = match babibapp.get_teacher_comment_vote(comment.id).await
-                                {
-                                    Ok(vote) => vote,
-                                    Err(_) => {
-                                        eprintln!("Failed to get teacher comment vote");
-                                        continue;
-                                    }
-                                };
-
-                                let receiver = match babibapp.get_teacher(comment.receiver_id).await
-                                {
-                                    Ok(teacher) => teacher,
-                                    Err(_) => {
-                                        eprintln!("Failed to get receiver teacher");
-                                        continue;
-                                    }
-                                };
-
-                                babicli::view_teacher_comment_limited(comment, &receiver, vote);
-                            }
-                            TeacherCommentView::Full(comment) => {
-                                let vote = match babibapp.get_teacher_comment_vote(comment.id).await
-                                {
-                                    Ok(vote) => vote,
-                                    Err(_) => {
-                                        eprintln!("Failed to get teacher comment vote");
-                                        continue;
-                                    }
-                                };
-
-                                let receiver = match babibapp.get_teacher(comment.receiver_id).await
-                                {
-                                    Ok(teacher) => teacher,
-                                    Err(_) => {
-                                        eprintln!("Failed to get receiver teacher");
-                                        continue;
-                                    }
-                                };
-
-                                let author = match babibapp.get_student(comment.author_id).await {
-                                    Ok(teacher) => teacher,
-                                    Err(_) => {
-                                        eprintln!("Failed to get author");
-                                        continue;
-                                    }
-                                };
-
-                                babicli::view_teacher_comment_full(
-                                    comment, &receiver, &author, vote,
-                                );
-                            }
+                    for entry in &queue {
+                        babicli::view_reported_comment(&entry.comment);
+                        for report in &entry.reports {
+                            babicli::view_comment_report(
+                                report.reporter_id,
+                                &report.reason,
+                                report.created,
+                            );
                         }
                         println!();
                     }
                 }
 
-                Some("create_teacher_comment") => {
-                    let recv_id = if let Some(id) = args.next() {
+                Some("moderate_comment") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+
+                    let id = if let Some(id) = args.next() {
                         if let Ok(id) = id.parse::<i32>() {
                             id
                         } else {
-                            eprintln!("Invalid teacher comment id");
+                            eprintln!("Invalid comment id");
                             continue;
                         }
                     } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
-                        .with_prompt("Receiver id")
+                        .with_prompt("id")
                         .interact_text()
                     {
                         id
                     } else {
-                        eprintln!("Invalid teacher comment id");
+                        eprintln!("Invalid comment id");
                         continue;
                     };
 
-                    let body = match dialoguer::Editor::new().edit("Enter your comment") {
-                        Ok(Some(body)) => body,
-                        _ => {
-                            eprintln!("Abort!");
-                            continue;
-                        }
-                    };
-
-                    if body.trim().is_empty() {
-                        eprintln!("Empty comment, abort!");
-                        continue;
-                    }
+                    let actions = [
+                        ModerationAction::Hide,
+                        ModerationAction::Restore,
+                        ModerationAction::Delete,
+                        ModerationAction::Dismiss,
+                    ];
 
-                    let comment = match babibapp.create_teacher_comment(recv_id, &body).await {
-                        Ok(comment) => comment,
+                    let action = match dialoguer::Select::with_theme(&info_theme)
+                        .with_prompt("Action")
+                        .items(&actions)
+                        .interact()
+                    {
+                        Ok(idx) => actions[idx],
                         Err(_) => {
-                            eprintln!("Failed to create teacher comment");
+                            eprintln!("Failed to read action");
                             continue;
                         }
                     };
 
-                    let receiver = match babibapp.get_teacher(recv_id).await {
-                        Ok(recv) => recv,
-                        Err(_) => {
-                            eprintln!("Failed to get receiver");
+                    let _ = match babibapp.moderate_comment(Some(target), id, action).await {
+                        Ok(comment) => comment,
+                        Err(e) => {
+                            eprintln!("Failed to moderate {} comment: {}", target, e);
                             continue;
                         }
                     };
 
-                    let author = match babibapp.get_self().await {
-                        Ok(author) => author,
-                        Err(_) => {
-                            eprintln!("Failed to get self");
+                    println!("Comment successfully moderated!");
+                }
+
+                Some("show_comment_inbox") => {
+                    let comments = match babibapp.get_comment_inbox().await {
+                        Ok(comments) => comments,
+                        Err(e) => {
+                            eprintln!("Failed to get comment inbox: {}", e);
                             continue;
                         }
                     };
 
-                    println!("Teacher comment successfully created!");
-                    babicli::view_teacher_comment_full(
-                        &comment,
-                        &receiver,
-                        &StudentView::Full(author),
-                        0,
-                    );
+                    if comments.is_empty() {
+                        println!("No comments waiting for your approval");
+                    }
+
+                    for comment in &comments {
+                        let (id, body, published) = match comment {
+                            CommentView::Limited(c) => (c.id, &c.body, c.published),
+                            CommentView::Full(c) => (c.id, &c.body, c.published),
+                        };
+                        babicli::view_thread_comment(0, id, None, body, published, false);
+                        println!();
+                    }
                 }
 
-                Some("upvote_teacher_comment") => {
+                Some("approve_comment") => {
                     let id = if let Some(id) = args.next() {
                         if let Ok(id) = id.parse::<i32>() {
                             id
                         } else {
-                            eprintln!("Invalid teacher comment id");
+                            eprintln!("Invalid comment id");
                             continue;
                         }
                     } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
@@ -1309,24 +2616,31 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     {
                         id
                     } else {
-                        eprintln!("Invalid teacher comment id");
+                        eprintln!("Invalid comment id");
                         continue;
                     };
 
-                    if babibapp.upvote_teacher_comment(id).await.is_err() {
-                        eprintln!("Failed to upvote teacher comment");
-                        continue;
-                    }
+                    // only students approve the comments about them
+                    let _ = match babibapp
+                        .approve_comment(Some(CommentTarget::Student), id)
+                        .await
+                    {
+                        Ok(comment) => comment,
+                        Err(e) => {
+                            eprintln!("Failed to approve comment: {}", e);
+                            continue;
+                        }
+                    };
 
-                    println!("Teacher comment successfully upvoted!");
+                    println!("Comment successfully approved!");
                 }
 
-                Some("downvote_teacher_comment") => {
+                Some("reject_comment") => {
                     let id = if let Some(id) = args.next() {
                         if let Ok(id) = id.parse::<i32>() {
                             id
                         } else {
-                            eprintln!("Invalid teacher comment id");
+                            eprintln!("Invalid comment id");
                             continue;
                         }
                     } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
@@ -1335,24 +2649,38 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     {
                         id
                     } else {
-                        eprintln!("Invalid teacher comment id");
+                        eprintln!("Invalid comment id");
                         continue;
                     };
 
-                    if babibapp.downvote_teacher_comment(id).await.is_err() {
-                        eprintln!("Failed to downvote teacher comment");
-                        continue;
-                    }
+                    let _ = match babibapp
+                        .reject_comment(Some(CommentTarget::Student), id)
+                        .await
+                    {
+                        Ok(comment) => comment,
+                        Err(e) => {
+                            eprintln!("Failed to reject comment: {}", e);
+                            continue;
+                        }
+                    };
 
-                    println!("Teacher comment successfully downvoted!");
+                    println!("Comment successfully rejected!");
                 }
 
-                Some("unvote_teacher_comment") => {
+                Some("show_comment_moderation_log") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+
                     let id = if let Some(id) = args.next() {
                         if let Ok(id) = id.parse::<i32>() {
                             id
                         } else {
-                            eprintln!("Invalid teacher comment id");
+                            eprintln!("Invalid comment id");
                             continue;
                         }
                     } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
@@ -1361,24 +2689,47 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     {
                         id
                     } else {
-                        eprintln!("Invalid teacher comment id");
+                        eprintln!("Invalid comment id");
                         continue;
                     };
 
-                    if babibapp.unvote_teacher_comment(id).await.is_err() {
-                        eprintln!("Failed to unvote teacher comment");
-                        continue;
+                    let log = match babibapp.get_comment_moderation_log(Some(target), id).await {
+                        Ok(log) => log,
+                        Err(e) => {
+                            eprintln!("Failed to get {} comment moderation log: {}", target, e);
+                            continue;
+                        }
+                    };
+
+                    if log.is_empty() {
+                        println!("The comment was never moderated");
                     }
 
-                    println!("Teacher comment successfully unvoted!");
+                    for moderation in &log {
+                        babicli::view_comment_moderation(
+                            moderation.moderator_id,
+                            moderation.api_key_id,
+                            moderation.root,
+                            &moderation.action,
+                            moderation.created,
+                        );
+                    }
                 }
 
-                Some("delete_teacher_comment") => {
+                Some("reveal_comment_author") => {
+                    let target = match read_comment_target(&mut args, &info_theme) {
+                        Ok(target) => target,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            continue;
+                        }
+                    };
+
                     let id = if let Some(id) = args.next() {
                         if let Ok(id) = id.parse::<i32>() {
                             id
                         } else {
-                            eprintln!("Invalid teacher comment id");
+                            eprintln!("Invalid comment id");
                             continue;
                         }
                     } else if let Ok(id) = dialoguer::Input::<i32>::with_theme(&info_theme)
@@ -1387,19 +2738,95 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     {
                         id
                     } else {
-                        eprintln!("Invalid teacher comment id");
+                        eprintln!("Invalid comment id");
                         continue;
                     };
 
-                    let _ = match babibapp.delete_teacher_comment(id).await {
+                    match dialoguer::Confirm::with_theme(&info_theme)
+                        .with_prompt(
+                            "Revealing the author is recorded in the moderation log, continue?",
+                        )
+                        .default(false)
+                        .interact()
+                    {
+                        Ok(true) => {}
+                        _ => {
+                            eprintln!("Abort!");
+                            continue;
+                        }
+                    }
+
+                    let comment = match babibapp.reveal_comment_author(Some(target), id).await {
                         Ok(comment) => comment,
-                        Err(_) => {
-                            eprintln!("Failed to delete teacher comment");
+                        Err(e) => {
+                            eprintln!("Failed to reveal {} comment author: {}", target, e);
+                            continue;
+                        }
+                    };
+
+                    let author = match babibapp.get_student(comment.author_id).await {
+                        Ok(author) => author,
+                        Err(e) => {
+                            eprintln!("Failed to get author: {}", e);
+                            continue;
+                        }
+                    };
+
+                    println!("Written by:");
+                    babicli::view_student(&author);
+                }
+
+                Some("search") => {
+                    let words: Vec<&str> = args.by_ref().collect();
+                    let query = if !words.is_empty() {
+                        words.join(" ")
+                    } else if let Ok(query) = dialoguer::Input::<String>::with_theme(&info_theme)
+                        .with_prompt("Search")
+                        .interact_text()
+                    {
+                        query
+                    } else {
+                        eprintln!("Failed to read search query");
+                        continue;
+                    };
+
+                    let results = match babibapp.search(&query, None).await {
+                        Ok(results) => results,
+                        Err(e) => {
+                            eprintln!("Failed to search: {}", e);
                             continue;
                         }
                     };
 
-                    println!("Teacher comment successfully deleted!");
+                    if results.comments.is_empty()
+                        && results.students.is_empty()
+                        && results.teachers.is_empty()
+                    {
+                        println!("Nothing found!");
+                        continue;
+                    }
+
+                    for hit in &results.students {
+                        let title = format!("Student [{}]", hit.student.id());
+                        babicli::view_search_hit(&title, &hit.snippet);
+                        println!();
+                    }
+
+                    for hit in &results.teachers {
+                        let title = format!("Teacher [{}]", hit.teacher.id);
+                        babicli::view_search_hit(&title, &hit.snippet);
+                        println!();
+                    }
+
+                    for hit in &results.comments {
+                        let title = format!(
+                            "Comment [{}] about a {}",
+                            hit.comment.id(),
+                            hit.comment.target()
+                        );
+                        babicli::view_search_hit(&title, &hit.snippet);
+                        println!();
+                    }
                 }
 
                 Some("help") => {
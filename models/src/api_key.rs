@@ -0,0 +1,122 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use babibapp_schema::schema::api_keys;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    /// only reading requests
+    ReadOnly,
+    /// reading, and writing comments and votes as the student of the key
+    Comments,
+    /// everything, with every permission
+    Admin,
+}
+
+impl ApiKeyScope {
+    pub const ALL: [ApiKeyScope; 3] = [
+        ApiKeyScope::ReadOnly,
+        ApiKeyScope::Comments,
+        ApiKeyScope::Admin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::ReadOnly => "read_only",
+            ApiKeyScope::Comments => "comments",
+            ApiKeyScope::Admin => "admin",
+        }
+    }
+}
+
+impl fmt::Display for ApiKeyScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ApiKeyScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ApiKeyScope::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == s)
+            .ok_or(format!("Unknown API key scope: {}", s))
+    }
+}
+
+/// A row of the api_keys table, only for use inside the server, responses use `ApiKey`
+#[derive(Debug, Clone, Queryable)]
+pub struct ApiKeyRecord {
+    pub id: i32,
+    pub name: String,
+    pub key_hash: String,
+    pub prefix: String,
+    pub scope: String,
+    pub student_id: Option<i32>,
+    pub created_by: Option<i32>,
+    pub created: SystemTime,
+    pub last_used: Option<SystemTime>,
+    pub revoked: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub scope: ApiKeyScope,
+    pub student_id: Option<i32>,
+    pub created_by: Option<i32>,
+    pub created: SystemTime,
+    pub last_used: Option<SystemTime>,
+    pub revoked: bool,
+}
+
+impl From<ApiKeyRecord> for ApiKey {
+    fn from(record: ApiKeyRecord) -> Self {
+        ApiKey {
+            id: record.id,
+            name: record.name,
+            prefix: record.prefix,
+            // unknown scopes may be left over by newer versions and grant the least
+            scope: record.scope.parse().unwrap_or(ApiKeyScope::ReadOnly),
+            student_id: record.student_id,
+            created_by: record.created_by,
+            created: record.created,
+            last_used: record.last_used,
+            revoked: record.revoked,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[table_name = "api_keys"]
+pub struct NewApiKey {
+    pub name: String,
+    pub key_hash: String,
+    pub prefix: String,
+    pub scope: String,
+    pub student_id: Option<i32>,
+    pub created_by: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateApiKey {
+    pub name: String,
+    pub scope: ApiKeyScope,
+    /// student the key acts as, required for the comments scope
+    pub student_id: Option<i32>,
+}
+
+/// Answer to creating an API key, the key can't be looked up later
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedApiKey {
+    pub key: String,
+    pub api_key: ApiKey,
+}
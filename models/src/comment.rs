@@ -1,116 +1,520 @@
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
 use std::time::SystemTime;
 
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::Text;
 use serde::{Deserialize, Serialize};
 
-use babibapp_schema::schema::student_comment_votes;
-use babibapp_schema::schema::student_comments;
-use babibapp_schema::schema::teacher_comment_votes;
-use babibapp_schema::schema::teacher_comments;
+use babibapp_schema::schema::comment_moderations;
+use babibapp_schema::schema::comment_reactions;
+use babibapp_schema::schema::comment_reports;
+use babibapp_schema::schema::comment_revisions;
+use babibapp_schema::schema::comment_votes;
+use babibapp_schema::schema::comments;
 
-#[derive(Debug, Serialize, Deserialize, Clone, Queryable)]
-pub struct StudentComment {
+/// What a comment is about, each kind has its own receiver column in the comments table
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, AsExpression, FromSqlRow,
+)]
+#[serde(rename_all = "snake_case")]
+#[sql_type = "Text"]
+pub enum CommentTarget {
+    Student,
+    Teacher,
+}
+
+impl CommentTarget {
+    pub const ALL: [CommentTarget; 2] = [CommentTarget::Student, CommentTarget::Teacher];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommentTarget::Student => "student",
+            CommentTarget::Teacher => "teacher",
+        }
+    }
+
+    /// Receivers who are students can log in, so they decide whether comments about them are shown
+    pub fn receiver_is_student(&self) -> bool {
+        matches!(self, CommentTarget::Student)
+    }
+
+    /// Values of the `student_id` and `teacher_id` columns for a receiver of this kind
+    pub fn receiver_columns(&self, receiver_id: i32) -> (Option<i32>, Option<i32>) {
+        match self {
+            CommentTarget::Student => (Some(receiver_id), None),
+            CommentTarget::Teacher => (None, Some(receiver_id)),
+        }
+    }
+}
+
+impl fmt::Display for CommentTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for CommentTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CommentTarget::ALL
+            .into_iter()
+            .find(|target| target.as_str() == s)
+            .ok_or(format!("Unknown comment target: {}", s))
+    }
+}
+
+impl ToSql<Text, Pg> for CommentTarget {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        ToSql::<Text, Pg>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Text, Pg> for CommentTarget {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let target = <String as FromSql<Text, Pg>>::from_sql(bytes)?;
+        Ok(target.parse()?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationAction {
+    /// hide the comment from everyone but its author and moderators
+    Hide,
+    /// show a hidden comment again
+    Restore,
+    Delete,
+    /// close the reports without touching the comment
+    Dismiss,
+    /// hidden because of too many reports, not available to moderators
+    AutoHide,
+    /// the author of an anonymous comment was revealed, only through `/reveal_author`
+    RevealAuthor,
+}
+
+impl ModerationAction {
+    pub const ALL: [ModerationAction; 6] = [
+        ModerationAction::Hide,
+        ModerationAction::Restore,
+        ModerationAction::Delete,
+        ModerationAction::Dismiss,
+        ModerationAction::AutoHide,
+        ModerationAction::RevealAuthor,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModerationAction::Hide => "hide",
+            ModerationAction::Restore => "restore",
+            ModerationAction::Delete => "delete",
+            ModerationAction::Dismiss => "dismiss",
+            ModerationAction::AutoHide => "auto_hide",
+            ModerationAction::RevealAuthor => "reveal_author",
+        }
+    }
+}
+
+impl fmt::Display for ModerationAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ModerationAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ModerationAction::ALL
+            .into_iter()
+            .find(|action| action.as_str() == s)
+            .ok_or(format!("Unknown moderation action: {}", s))
+    }
+}
+
+/// Order of comment listings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommentSort {
+    /// highest score first
+    Top,
+    /// newest first
+    #[default]
+    New,
+    /// many votes split evenly between up and down first
+    Controversial,
+    /// lower bound of the Wilson score interval of the upvote share, few votes don't rank high
+    Wilson,
+}
+
+impl CommentSort {
+    pub const ALL: [CommentSort; 4] = [
+        CommentSort::Top,
+        CommentSort::New,
+        CommentSort::Controversial,
+        CommentSort::Wilson,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommentSort::Top => "top",
+            CommentSort::New => "new",
+            CommentSort::Controversial => "controversial",
+            CommentSort::Wilson => "wilson",
+        }
+    }
+}
+
+impl fmt::Display for CommentSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for CommentSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CommentSort::ALL
+            .into_iter()
+            .find(|sort| sort.as_str() == s)
+            .ok_or(format!("Unknown sort: {}", s))
+    }
+}
+
+/// Votes of a comment as seen by the requesting user
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommentVotes {
+    /// upvotes minus downvotes
+    pub score: i64,
+    pub upvotes: i64,
+    pub downvotes: i64,
+    /// `Some(true)` if the requesting student upvoted the comment, `Some(false)` for a downvote
+    pub own_vote: Option<bool>,
+}
+
+impl CommentVotes {
+    pub fn new(upvotes: i64, downvotes: i64, own_vote: Option<bool>) -> Self {
+        CommentVotes {
+            score: upvotes - downvotes,
+            upvotes,
+            downvotes,
+            own_vote,
+        }
+    }
+}
+
+/// How often a comment got one of the reactions
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReactionCount {
+    pub reaction: String,
+    pub count: i64,
+    /// whether the requesting student is among them
+    pub own: bool,
+}
+
+/// Whether the receiver allowed a comment about them to be shown to others
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl ApprovalStatus {
+    pub const ALL: [ApprovalStatus; 3] = [
+        ApprovalStatus::Pending,
+        ApprovalStatus::Approved,
+        ApprovalStatus::Rejected,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApprovalStatus::Pending => "pending",
+            ApprovalStatus::Approved => "approved",
+            ApprovalStatus::Rejected => "rejected",
+        }
+    }
+}
+
+impl fmt::Display for ApprovalStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ApprovalStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ApprovalStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == s)
+            .ok_or(format!("Unknown approval status: {}", s))
+    }
+}
+
+/// A row of the comments table, only for use inside the server, responses use `Comment`
+#[derive(Debug, Clone, Queryable)]
+pub struct CommentRecord {
     pub id: i32,
-    pub author_id: i32,
+    pub target: CommentTarget,
+    pub student_id: Option<i32>,
+    pub teacher_id: Option<i32>,
     pub receiver_id: i32,
+    pub author_id: i32,
     pub body: String,
     pub published: SystemTime,
+    pub edited: Option<SystemTime>,
+    pub parent_id: Option<i32>,
+    pub depth: i32,
+    pub deleted: bool,
+    pub hidden: bool,
+    pub status: String,
+    /// id of teacher comments from before all comments shared one table
+    pub legacy_teacher_id: Option<i32>,
+    pub anonymous: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Insertable)]
-#[table_name = "student_comments"]
-pub struct NewStudentComment {
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Comment {
+    pub id: i32,
+    pub target: CommentTarget,
     pub author_id: i32,
+    /// id of the student, teacher, ... the comment is about, depending on `target`
     pub receiver_id: i32,
     pub body: String,
+    pub published: SystemTime,
+    pub edited: Option<SystemTime>,
+    /// the comment this one replies to
+    pub parent_id: Option<i32>,
+    pub depth: i32,
+    /// set for deleted comments that are kept because of their replies, their body is empty
+    pub deleted: bool,
+    /// hidden by a moderator or by reports, only the author and moderators see it
+    pub hidden: bool,
+    /// `pending`, `approved` or `rejected`, only approved comments are shown to everyone
+    pub status: String,
+    /// the author is hidden from moderators as well, only `/reveal_author` tells them
+    pub anonymous: bool,
+}
+
+impl From<CommentRecord> for Comment {
+    fn from(record: CommentRecord) -> Self {
+        Comment {
+            id: record.id,
+            target: record.target,
+            author_id: record.author_id,
+            receiver_id: record.receiver_id,
+            body: record.body,
+            published: record.published,
+            edited: record.edited,
+            parent_id: record.parent_id,
+            depth: record.depth,
+            deleted: record.deleted,
+            hidden: record.hidden,
+            status: record.status,
+            anonymous: record.anonymous,
+        }
+    }
+}
+
+/// Built with `CommentTarget::receiver_columns`, only the column of the target is set
+#[derive(Debug, Clone, Insertable)]
+#[table_name = "comments"]
+pub struct NewComment {
+    pub target: CommentTarget,
+    pub student_id: Option<i32>,
+    pub teacher_id: Option<i32>,
+    pub author_id: i32,
+    pub body: String,
     pub published: Option<SystemTime>,
+    pub parent_id: Option<i32>,
+    pub depth: i32,
+    pub status: String,
+    pub anonymous: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct CreateStudentComment {
+pub struct CreateComment {
+    /// may be left out on the routes of a single target, like `/comment/student/create`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<CommentTarget>,
     pub receiver_id: i32,
     pub body: String,
+    /// left out to take the default of the server, which may also refuse or enforce it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anonymous: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct LimitedViewStudentComment {
+pub struct CreateReply {
+    pub body: String,
+    /// like `CreateComment::anonymous`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anonymous: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LimitedViewComment {
     pub id: i32,
+    pub target: CommentTarget,
     pub receiver_id: i32,
     pub body: String,
     pub published: SystemTime,
+    pub edited: Option<SystemTime>,
+    pub parent_id: Option<i32>,
+    pub depth: i32,
+    pub deleted: bool,
+    pub hidden: bool,
+    pub status: String,
+    pub anonymous: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum CommentView {
+    Limited(LimitedViewComment),
+    Full(Comment),
+}
+
+impl CommentView {
+    pub fn id(&self) -> i32 {
+        match self {
+            CommentView::Limited(comment) => comment.id,
+            CommentView::Full(comment) => comment.id,
+        }
+    }
+
+    pub fn target(&self) -> CommentTarget {
+        match self {
+            CommentView::Limited(comment) => comment.target,
+            CommentView::Full(comment) => comment.target,
+        }
+    }
+}
+
+/// A comment of a thread, `depth` counts from the comment the thread was requested for
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ThreadEntry {
+    pub depth: i32,
+    pub comment: CommentView,
 }
 
+/// A comment of a listing together with its votes
 #[derive(Debug, Serialize, Deserialize, Clone)]
-pub enum StudentCommentView {
-    Limited(LimitedViewStudentComment),
-    Full(StudentComment),
+pub struct ScoredComment {
+    pub comment: CommentView,
+    pub votes: CommentVotes,
+    /// most frequent reaction first
+    pub reactions: Vec<ReactionCount>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Queryable)]
-pub struct StudentCommentVote {
+pub struct CommentReport {
     pub id: i32,
     pub comment_id: i32,
-    pub student_id: i32,
-    pub upvote: bool,
+    pub reporter_id: i32,
+    pub reason: String,
+    pub created: SystemTime,
+    pub resolved: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Insertable)]
-#[table_name = "student_comment_votes"]
-pub struct NewStudentCommentVote {
+#[table_name = "comment_reports"]
+pub struct NewCommentReport {
     pub comment_id: i32,
-    pub student_id: i32,
-    pub upvote: bool,
+    pub reporter_id: i32,
+    pub reason: String,
+}
+
+/// A comment of the moderation queue with its open reports
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReportedComment {
+    pub comment: CommentView,
+    pub reports: Vec<CommentReport>,
 }
 
+/// An entry of the moderation log
 #[derive(Debug, Serialize, Deserialize, Clone, Queryable)]
-pub struct TeacherComment {
+pub struct CommentModeration {
     pub id: i32,
-    pub author_id: i32,
-    pub receiver_id: i32,
-    pub body: String,
-    pub published: SystemTime,
+    pub comment_id: i32,
+    /// `None` for root, API keys without a student, deleted moderators and automatic actions
+    pub moderator_id: Option<i32>,
+    pub action: String,
+    pub created: SystemTime,
+    /// the API key the action was taken with, if it still exists
+    pub api_key_id: Option<i32>,
+    /// taken by root
+    pub root: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Insertable)]
-#[table_name = "teacher_comments"]
-pub struct NewTeacherComment {
-    pub author_id: i32,
-    pub receiver_id: i32,
+#[table_name = "comment_moderations"]
+pub struct NewCommentModeration {
+    pub comment_id: i32,
+    pub moderator_id: Option<i32>,
+    pub action: String,
+    pub api_key_id: Option<i32>,
+    pub root: bool,
+}
+
+/// A previous body of an edited comment
+#[derive(Debug, Serialize, Deserialize, Clone, Queryable)]
+pub struct CommentRevision {
+    pub id: i32,
+    pub comment_id: i32,
+    /// `None` if root or a deleted student replaced the body,
+    /// also for the author of an anonymous comment unless they ask themselves
+    pub editor_id: Option<i32>,
     pub body: String,
-    pub published: Option<SystemTime>,
+    /// when the body was published or last edited
+    pub written: SystemTime,
+    pub replaced: SystemTime,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct CreateTeacherComment {
-    pub receiver_id: i32,
+#[derive(Debug, Serialize, Deserialize, Clone, Insertable)]
+#[table_name = "comment_revisions"]
+pub struct NewCommentRevision {
+    pub comment_id: i32,
+    pub editor_id: Option<i32>,
     pub body: String,
+    pub written: SystemTime,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct LimitedViewTeacherComment {
+#[derive(Debug, Serialize, Deserialize, Clone, Queryable)]
+pub struct CommentVote {
     pub id: i32,
-    pub receiver_id: i32,
-    pub body: String,
-    pub published: SystemTime,
+    pub comment_id: i32,
+    pub student_id: i32,
+    pub upvote: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub enum TeacherCommentView {
-    Limited(LimitedViewTeacherComment),
-    Full(TeacherComment),
+#[derive(Debug, Serialize, Deserialize, Clone, Insertable)]
+#[table_name = "comment_votes"]
+pub struct NewCommentVote {
+    pub comment_id: i32,
+    pub student_id: i32,
+    pub upvote: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Queryable)]
-pub struct TeacherCommentVote {
+pub struct CommentReaction {
     pub id: i32,
     pub comment_id: i32,
     pub student_id: i32,
-    pub upvote: bool,
+    pub reaction: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Insertable)]
-#[table_name = "teacher_comment_votes"]
-pub struct NewTeacherCommentVote {
+#[table_name = "comment_reactions"]
+pub struct NewCommentReaction {
     pub comment_id: i32,
     pub student_id: i32,
-    pub upvote: bool,
+    pub reaction: String,
 }
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// Machine-readable error codes of the babibapp API
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    Unauthorized,
+    Forbidden,
+    Conflict,
+    ValidationFailed,
+    NotFound,
+    TooManyRequests,
+    Internal,
+}
+
+/// Body of every error response of the babibapp server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub code: ErrorCode,
+    pub message: String,
+}
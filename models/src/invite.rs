@@ -0,0 +1,98 @@
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use babibapp_schema::schema::invites;
+
+/// A row of the invites table, only for use inside the server, responses use `Invite`
+#[derive(Debug, Clone, Queryable)]
+pub struct InviteRecord {
+    pub id: i32,
+    pub code_hash: String,
+    pub created_by: Option<i32>,
+    pub created: SystemTime,
+    pub expires: SystemTime,
+    pub max_uses: i32,
+    pub uses: i32,
+    pub revoked: bool,
+    pub email: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub role_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Invite {
+    pub id: i32,
+    pub created_by: Option<i32>,
+    pub created: SystemTime,
+    pub expires: SystemTime,
+    pub max_uses: i32,
+    pub uses: i32,
+    pub revoked: bool,
+    pub email: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub role_id: Option<i32>,
+}
+
+impl From<InviteRecord> for Invite {
+    fn from(record: InviteRecord) -> Self {
+        Invite {
+            id: record.id,
+            created_by: record.created_by,
+            created: record.created,
+            expires: record.expires,
+            max_uses: record.max_uses,
+            uses: record.uses,
+            revoked: record.revoked,
+            email: record.email,
+            first_name: record.first_name,
+            last_name: record.last_name,
+            role_id: record.role_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[table_name = "invites"]
+pub struct NewInvite {
+    pub code_hash: String,
+    pub created_by: Option<i32>,
+    pub expires: SystemTime,
+    pub max_uses: i32,
+    pub email: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub role_id: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateInvite {
+    /// defaults to a single use
+    pub max_uses: Option<i32>,
+    /// defaults to 7 days
+    pub expiration_days: Option<i64>,
+    pub email: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    /// name of a role granted to students signing up with the invite
+    pub role: Option<String>,
+}
+
+/// Answer to creating an invite, the code can't be looked up later
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedInvite {
+    pub code: String,
+    pub invite: Invite,
+}
+
+/// Values the invite is not pre-filled with have to be given
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignupStudent {
+    pub code: String,
+    pub email: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub password: String,
+}
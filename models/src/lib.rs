@@ -1,7 +1,16 @@
 #[macro_use]
 extern crate diesel;
 
+pub mod api_key;
 pub mod comment;
+pub mod error;
+pub mod invite;
+pub mod login;
+pub mod page;
+pub mod role;
+pub mod search;
 pub mod student;
 pub mod teacher;
+pub mod token;
+pub mod totp;
 pub mod wrappers;
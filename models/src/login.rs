@@ -0,0 +1,41 @@
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use babibapp_schema::schema::login_attempts;
+use babibapp_schema::schema::login_lockouts;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable)]
+pub struct LoginAttempt {
+    pub id: i32,
+    pub email: String,
+    pub ip: String,
+    pub success: bool,
+    pub attempted: SystemTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable)]
+#[table_name = "login_attempts"]
+pub struct NewLoginAttempt {
+    pub email: String,
+    pub ip: String,
+    pub success: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable)]
+pub struct LoginLockout {
+    pub id: i32,
+    /// `email` or `ip`
+    pub kind: String,
+    pub value: String,
+    pub failures: i32,
+    pub last_failure: SystemTime,
+    pub locked_until: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable)]
+#[table_name = "login_lockouts"]
+pub struct NewLoginLockout {
+    pub kind: String,
+    pub value: String,
+}
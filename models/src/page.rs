@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// One page of a listing
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// passed as `cursor` to get the following page, `None` on the last page
+    pub next_cursor: Option<String>,
+    /// number of items on all pages together
+    pub total: i64,
+}
+
+impl<T> Page<T> {
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            total: self.total,
+        }
+    }
+}
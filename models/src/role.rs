@@ -0,0 +1,95 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use babibapp_schema::schema::role_permissions;
+use babibapp_schema::schema::roles;
+use babibapp_schema::schema::student_roles;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    /// register, reset and delete any student
+    ManageStudents,
+    /// create and delete roles and grant them to students
+    ManageRoles,
+    /// add, reset and delete teachers
+    ManageTeachers,
+    /// see comment authors and delete any comment
+    ModerateComments,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 4] = [
+        Permission::ManageStudents,
+        Permission::ManageRoles,
+        Permission::ManageTeachers,
+        Permission::ModerateComments,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::ManageStudents => "manage_students",
+            Permission::ManageRoles => "manage_roles",
+            Permission::ManageTeachers => "manage_teachers",
+            Permission::ModerateComments => "moderate_comments",
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Permission {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Permission::ALL
+            .into_iter()
+            .find(|permission| permission.as_str() == s)
+            .ok_or(format!("Unknown permission: {}", s))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable)]
+pub struct Role {
+    pub id: i32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable)]
+#[table_name = "roles"]
+pub struct NewRole {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
+#[table_name = "role_permissions"]
+pub struct RolePermission {
+    pub role_id: i32,
+    pub permission: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
+#[table_name = "student_roles"]
+pub struct StudentRole {
+    pub student_id: i32,
+    pub role_id: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateRole {
+    pub name: String,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleView {
+    pub id: i32,
+    pub name: String,
+    pub permissions: Vec<Permission>,
+}
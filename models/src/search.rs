@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+use crate::comment::CommentView;
+use crate::student::StudentView;
+use crate::teacher::Teacher;
+
+/// Matches of a search, best first. Snippets mark matched words with `**`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub comments: Vec<CommentHit>,
+    pub students: Vec<StudentHit>,
+    pub teachers: Vec<TeacherHit>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommentHit {
+    pub comment: CommentView,
+    pub snippet: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StudentHit {
+    pub student: StudentView,
+    pub snippet: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TeacherHit {
+    pub teacher: Teacher,
+    pub snippet: String,
+}
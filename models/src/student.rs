@@ -1,15 +1,50 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
 use babibapp_schema::schema::students;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Queryable)]
-pub struct Student {
+/// A row of the students table, only for use inside the server.
+/// It is deliberately not serializable, responses use `StudentProfile`.
+#[derive(Clone, Queryable)]
+pub struct StudentRecord {
     pub id: i32,
     pub email: String,
     pub first_name: String,
     pub last_name: String,
     pub password_hash: String,
-    pub admin: bool,
+}
+
+impl fmt::Debug for StudentRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StudentRecord")
+            .field("id", &self.id)
+            .field("email", &self.email)
+            .field("first_name", &self.first_name)
+            .field("last_name", &self.last_name)
+            .field("password_hash", &"[redacted]")
+            .finish()
+    }
+}
+
+/// Everything about a student that may be shown to the student and to student managers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StudentProfile {
+    pub id: i32,
+    pub email: String,
+    pub first_name: String,
+    pub last_name: String,
+}
+
+impl From<StudentRecord> for StudentProfile {
+    fn from(record: StudentRecord) -> Self {
+        StudentProfile {
+            id: record.id,
+            email: record.email,
+            first_name: record.first_name,
+            last_name: record.last_name,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Insertable)]
@@ -19,7 +54,6 @@ pub struct NewStudent {
     pub first_name: String,
     pub last_name: String,
     pub password_hash: String,
-    pub admin: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,7 +62,6 @@ pub struct RegisterStudent {
     pub first_name: String,
     pub last_name: String,
     pub password: String,
-    pub admin: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,11 +75,19 @@ pub struct LimitedViewStudent {
     pub id: i32,
     pub first_name: String,
     pub last_name: String,
-    // should admin be added?
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum StudentView {
     Limited(LimitedViewStudent),
-    Full(Student),
+    Full(StudentProfile),
+}
+
+impl StudentView {
+    pub fn id(&self) -> i32 {
+        match self {
+            StudentView::Limited(student) => student.id,
+            StudentView::Full(student) => student.id,
+        }
+    }
 }
@@ -0,0 +1,76 @@
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use babibapp_schema::schema::password_reset_tokens;
+use babibapp_schema::schema::refresh_tokens;
+use babibapp_schema::schema::revoked_tokens;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable)]
+pub struct RefreshToken {
+    pub id: i32,
+    pub student_id: i32,
+    pub token_hash: String,
+    pub created: SystemTime,
+    pub expires: SystemTime,
+    pub revoked: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable)]
+#[table_name = "refresh_tokens"]
+pub struct NewRefreshToken {
+    pub student_id: i32,
+    pub token_hash: String,
+    pub expires: SystemTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Insertable)]
+#[table_name = "revoked_tokens"]
+pub struct RevokedToken {
+    pub jti: String,
+    pub expires: SystemTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable)]
+pub struct PasswordResetToken {
+    pub id: i32,
+    pub student_id: i32,
+    pub token_hash: String,
+    pub created: SystemTime,
+    pub expires: SystemTime,
+    pub used: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Insertable)]
+#[table_name = "password_reset_tokens"]
+pub struct NewPasswordResetToken {
+    pub student_id: i32,
+    pub token_hash: String,
+    pub expires: SystemTime,
+}
+
+/// A public key in the JSON Web Key format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Jwk {
+    pub kty: String,
+    pub kid: String,
+    pub alg: String,
+    #[serde(rename = "use")]
+    pub key_use: String,
+    /// RSA modulus
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    /// RSA exponent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    /// Ed25519 public key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
@@ -0,0 +1,70 @@
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use babibapp_schema::schema::login_challenges;
+use babibapp_schema::schema::student_totp;
+use babibapp_schema::schema::totp_recovery_codes;
+
+#[derive(Debug, Clone, Queryable)]
+pub struct StudentTotp {
+    pub student_id: i32,
+    pub secret: String,
+    pub enabled: bool,
+    pub last_step: Option<i64>,
+    pub created: SystemTime,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[table_name = "student_totp"]
+pub struct NewStudentTotp {
+    pub student_id: i32,
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Queryable)]
+pub struct RecoveryCode {
+    pub id: i32,
+    pub student_id: i32,
+    pub code_hash: String,
+    pub used: bool,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[table_name = "totp_recovery_codes"]
+pub struct NewRecoveryCode {
+    pub student_id: i32,
+    pub code_hash: String,
+}
+
+#[derive(Debug, Clone, Queryable)]
+pub struct LoginChallenge {
+    pub id: i32,
+    pub student_id: i32,
+    pub token_hash: String,
+    pub expires: SystemTime,
+    pub failures: i32,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[table_name = "login_challenges"]
+pub struct NewLoginChallenge {
+    pub student_id: i32,
+    pub token_hash: String,
+    pub expires: SystemTime,
+}
+
+/// Everything an authenticator app needs, the enrolment is finished by confirming a first code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TotpEnrolment {
+    /// base32 encoded secret
+    pub secret: String,
+    /// `otpauth://` URI, usually shown as QR code
+    pub uri: String,
+}
+
+/// Single-use codes to log in without the authenticator, only shown once
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
@@ -1,10 +1,58 @@
 use serde::{Deserialize, Serialize};
 
+use crate::comment::{CommentSort, CommentTarget, ModerationAction};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TokenWrapper {
     pub token: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenPairWrapper {
+    pub token: String,
+    pub expires_in: i64,
+    pub refresh_token: Option<String>,
+    /// Set if the permissions of the student were withheld until two-factor authentication is enabled
+    #[serde(default)]
+    pub totp_required: bool,
+}
+
+/// Answer to a correct password when two-factor authentication is enabled,
+/// the challenge is sent back to `/token/totp` together with a code
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpChallengeWrapper {
+    pub totp_challenge: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LoginWrapper {
+    Tokens(TokenPairWrapper),
+    TotpChallenge(TotpChallengeWrapper),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TotpLoginWrapper {
+    pub totp_challenge: String,
+    /// a code of the authenticator or a recovery code
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TotpCodeWrapper {
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RefreshTokenWrapper {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RevokeWrapper {
+    pub all: bool,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct EmailWrapper {
     pub email: String,
@@ -20,3 +68,84 @@ pub struct NameWrapper {
     pub first_name: String,
     pub last_name: String,
 }
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ChangePasswordWrapper {
+    pub old_password: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ResetPasswordWrapper {
+    pub token: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RoleWrapper {
+    pub role: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BodyWrapper {
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReasonWrapper {
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ModerationWrapper {
+    pub action: ModerationAction,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TargetQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<CommentTarget>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SortQuery {
+    #[serde(default)]
+    pub sort: CommentSort,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PageQuery {
+    /// page size, the server picks one if it is missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    /// `next_cursor` of the previous page, the first page is returned without one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+/// Restricts comment listings, timestamps are seconds since the unix epoch
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CommentFilter {
+    /// set by the routes of a single target, like `/comment/student/get_all`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<CommentTarget>,
+    /// only used together with `target`, ids of different targets overlap
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receiver_id: Option<i32>,
+    /// only moderators may look for the comments of other authors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published_after: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published_before: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SearchQuery {
+    /// words to look for, `"quoted phrases"`, `or` and `-excluded` words are understood
+    pub q: String,
+    /// maximum number of matches of each kind, the server picks one if it is missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+}
@@ -1,45 +1,84 @@
 table! {
-    student_comments (id) {
+    api_keys (id) {
         id -> Int4,
-        author_id -> Int4,
+        name -> Text,
+        key_hash -> Text,
+        prefix -> Text,
+        scope -> Text,
+        student_id -> Nullable<Int4>,
+        created_by -> Nullable<Int4>,
+        created -> Timestamp,
+        last_used -> Nullable<Timestamp>,
+        revoked -> Bool,
+    }
+}
+
+table! {
+    comments (id) {
+        id -> Int4,
+        target -> Text,
+        student_id -> Nullable<Int4>,
+        teacher_id -> Nullable<Int4>,
         receiver_id -> Int4,
+        author_id -> Int4,
         body -> Text,
         published -> Timestamp,
+        edited -> Nullable<Timestamp>,
+        parent_id -> Nullable<Int4>,
+        depth -> Int4,
+        deleted -> Bool,
+        hidden -> Bool,
+        status -> Text,
+        legacy_teacher_id -> Nullable<Int4>,
+        anonymous -> Bool,
+    }
+}
+
+table! {
+    comment_moderations (id) {
+        id -> Int4,
+        comment_id -> Int4,
+        moderator_id -> Nullable<Int4>,
+        action -> Text,
+        created -> Timestamp,
+        api_key_id -> Nullable<Int4>,
+        root -> Bool,
     }
 }
 
 table! {
-    student_comment_votes (id) {
+    comment_reactions (id) {
         id -> Int4,
         comment_id -> Int4,
         student_id -> Int4,
-        upvote -> Bool,
+        reaction -> Text,
     }
 }
 
 table! {
-    students (id) {
+    comment_reports (id) {
         id -> Int4,
-        email -> Text,
-        first_name -> Varchar,
-        last_name -> Varchar,
-        password_hash -> Text,
-        admin -> Bool,
+        comment_id -> Int4,
+        reporter_id -> Int4,
+        reason -> Text,
+        created -> Timestamp,
+        resolved -> Bool,
     }
 }
 
 table! {
-    teacher_comments (id) {
+    comment_revisions (id) {
         id -> Int4,
-        author_id -> Int4,
-        receiver_id -> Int4,
+        comment_id -> Int4,
+        editor_id -> Nullable<Int4>,
         body -> Text,
-        published -> Timestamp,
+        written -> Timestamp,
+        replaced -> Timestamp,
     }
 }
 
 table! {
-    teacher_comment_votes (id) {
+    comment_votes (id) {
         id -> Int4,
         comment_id -> Int4,
         student_id -> Int4,
@@ -47,6 +86,124 @@ table! {
     }
 }
 
+table! {
+    invites (id) {
+        id -> Int4,
+        code_hash -> Text,
+        created_by -> Nullable<Int4>,
+        created -> Timestamp,
+        expires -> Timestamp,
+        max_uses -> Int4,
+        uses -> Int4,
+        revoked -> Bool,
+        email -> Nullable<Text>,
+        first_name -> Nullable<Varchar>,
+        last_name -> Nullable<Varchar>,
+        role_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    login_attempts (id) {
+        id -> Int4,
+        email -> Text,
+        ip -> Text,
+        success -> Bool,
+        attempted -> Timestamp,
+    }
+}
+
+table! {
+    login_challenges (id) {
+        id -> Int4,
+        student_id -> Int4,
+        token_hash -> Text,
+        expires -> Timestamp,
+        failures -> Int4,
+    }
+}
+
+table! {
+    login_lockouts (id) {
+        id -> Int4,
+        kind -> Varchar,
+        value -> Text,
+        failures -> Int4,
+        last_failure -> Timestamp,
+        locked_until -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    password_reset_tokens (id) {
+        id -> Int4,
+        student_id -> Int4,
+        token_hash -> Text,
+        created -> Timestamp,
+        expires -> Timestamp,
+        used -> Bool,
+    }
+}
+
+table! {
+    refresh_tokens (id) {
+        id -> Int4,
+        student_id -> Int4,
+        token_hash -> Text,
+        created -> Timestamp,
+        expires -> Timestamp,
+        revoked -> Bool,
+    }
+}
+
+table! {
+    revoked_tokens (jti) {
+        jti -> Text,
+        expires -> Timestamp,
+    }
+}
+
+table! {
+    role_permissions (role_id, permission) {
+        role_id -> Int4,
+        permission -> Text,
+    }
+}
+
+table! {
+    roles (id) {
+        id -> Int4,
+        name -> Varchar,
+    }
+}
+
+table! {
+    student_roles (student_id, role_id) {
+        student_id -> Int4,
+        role_id -> Int4,
+    }
+}
+
+table! {
+    student_totp (student_id) {
+        student_id -> Int4,
+        secret -> Text,
+        enabled -> Bool,
+        last_step -> Nullable<Int8>,
+        created -> Timestamp,
+    }
+}
+
+table! {
+    students (id) {
+        id -> Int4,
+        email -> Text,
+        first_name -> Varchar,
+        last_name -> Varchar,
+        password_hash -> Text,
+    }
+}
+
 table! {
     teachers (id) {
         id -> Int4,
@@ -55,18 +212,58 @@ table! {
     }
 }
 
-joinable!(student_comment_votes -> student_comments (comment_id));
-joinable!(student_comment_votes -> students (student_id));
-joinable!(teacher_comment_votes -> students (student_id));
-joinable!(teacher_comment_votes -> teacher_comments (comment_id));
-joinable!(teacher_comments -> students (author_id));
-joinable!(teacher_comments -> teachers (receiver_id));
+table! {
+    totp_recovery_codes (id) {
+        id -> Int4,
+        student_id -> Int4,
+        code_hash -> Text,
+        used -> Bool,
+    }
+}
+
+joinable!(api_keys -> students (student_id));
+joinable!(comment_moderations -> api_keys (api_key_id));
+joinable!(comment_moderations -> students (moderator_id));
+joinable!(comment_reactions -> comments (comment_id));
+joinable!(comment_reactions -> students (student_id));
+joinable!(comment_reports -> comments (comment_id));
+joinable!(comment_reports -> students (reporter_id));
+joinable!(comment_revisions -> comments (comment_id));
+joinable!(comment_revisions -> students (editor_id));
+joinable!(comment_votes -> comments (comment_id));
+joinable!(comment_votes -> students (student_id));
+joinable!(comments -> teachers (teacher_id));
+joinable!(invites -> roles (role_id));
+joinable!(invites -> students (created_by));
+joinable!(login_challenges -> students (student_id));
+joinable!(password_reset_tokens -> students (student_id));
+joinable!(refresh_tokens -> students (student_id));
+joinable!(role_permissions -> roles (role_id));
+joinable!(student_roles -> roles (role_id));
+joinable!(student_roles -> students (student_id));
+joinable!(student_totp -> students (student_id));
+joinable!(totp_recovery_codes -> students (student_id));
 
 allow_tables_to_appear_in_same_query!(
-    student_comments,
-    student_comment_votes,
+    api_keys,
+    comments,
+    comment_moderations,
+    comment_reactions,
+    comment_reports,
+    comment_revisions,
+    comment_votes,
+    invites,
+    login_attempts,
+    login_challenges,
+    login_lockouts,
+    password_reset_tokens,
+    refresh_tokens,
+    revoked_tokens,
+    role_permissions,
+    roles,
+    student_roles,
+    student_totp,
     students,
-    teacher_comments,
-    teacher_comment_votes,
     teachers,
+    totp_recovery_codes,
 );
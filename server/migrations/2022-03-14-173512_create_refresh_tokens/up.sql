@@ -0,0 +1,13 @@
+CREATE TABLE refresh_tokens (
+	id SERIAL PRIMARY KEY,
+	student_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	token_hash TEXT UNIQUE NOT NULL, -- sha256 of the refresh token, the token itself is never stored
+	created TIMESTAMP DEFAULT NOW() NOT NULL,
+	expires TIMESTAMP NOT NULL,
+	revoked BOOLEAN DEFAULT FALSE NOT NULL
+);
+
+CREATE TABLE revoked_tokens (
+	jti TEXT PRIMARY KEY,
+	expires TIMESTAMP NOT NULL -- rows can be dropped once the access token would have expired anyway
+);
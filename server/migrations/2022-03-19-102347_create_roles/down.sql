@@ -0,0 +1,11 @@
+ALTER TABLE students ADD COLUMN admin BOOLEAN DEFAULT FALSE NOT NULL;
+
+UPDATE students SET admin = TRUE
+	FROM student_roles, roles
+	WHERE student_roles.student_id = students.id
+		AND student_roles.role_id = roles.id
+		AND roles.name = 'admin';
+
+DROP TABLE student_roles;
+DROP TABLE role_permissions;
+DROP TABLE roles;
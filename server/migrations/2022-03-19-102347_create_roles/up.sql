@@ -0,0 +1,36 @@
+CREATE TABLE roles (
+	id SERIAL PRIMARY KEY,
+	name VARCHAR(30) UNIQUE NOT NULL
+);
+
+CREATE TABLE role_permissions (
+	role_id INT REFERENCES roles ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	permission TEXT NOT NULL, -- one of the snake_case names of `Permission`
+	PRIMARY KEY(role_id, permission)
+);
+
+CREATE TABLE student_roles (
+	student_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	role_id INT REFERENCES roles ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	PRIMARY KEY(student_id, role_id)
+);
+
+INSERT INTO roles (name) VALUES ('admin'), ('editor'), ('moderator');
+
+INSERT INTO role_permissions (role_id, permission)
+	SELECT roles.id, permissions.permission
+	FROM roles, (VALUES ('manage_students'), ('manage_roles'), ('manage_teachers'), ('moderate_comments')) AS permissions(permission)
+	WHERE roles.name = 'admin';
+
+INSERT INTO role_permissions (role_id, permission)
+	SELECT id, 'manage_teachers' FROM roles WHERE name = 'editor';
+
+INSERT INTO role_permissions (role_id, permission)
+	SELECT id, 'moderate_comments' FROM roles WHERE name = 'moderator';
+
+-- every former admin gets the admin role
+INSERT INTO student_roles (student_id, role_id)
+	SELECT students.id, roles.id FROM students, roles
+	WHERE students.admin AND roles.name = 'admin';
+
+ALTER TABLE students DROP COLUMN admin;
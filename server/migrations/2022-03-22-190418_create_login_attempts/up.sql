@@ -0,0 +1,19 @@
+CREATE TABLE login_attempts (
+	id SERIAL PRIMARY KEY,
+	email TEXT NOT NULL,
+	ip TEXT NOT NULL,
+	success BOOLEAN NOT NULL,
+	attempted TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+CREATE INDEX login_attempts_attempted_idx ON login_attempts (attempted);
+
+CREATE TABLE login_lockouts (
+	id SERIAL PRIMARY KEY,
+	kind VARCHAR(10) NOT NULL, -- what is counted, either 'email' or 'ip'
+	value TEXT NOT NULL,
+	failures INT DEFAULT 0 NOT NULL,
+	last_failure TIMESTAMP DEFAULT NOW() NOT NULL,
+	locked_until TIMESTAMP,
+	UNIQUE (kind, value)
+);
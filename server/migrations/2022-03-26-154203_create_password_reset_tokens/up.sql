@@ -0,0 +1,8 @@
+CREATE TABLE password_reset_tokens (
+	id SERIAL PRIMARY KEY,
+	student_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	token_hash TEXT UNIQUE NOT NULL, -- sha256 of the reset token, the token itself is only sent by mail
+	created TIMESTAMP DEFAULT NOW() NOT NULL,
+	expires TIMESTAMP NOT NULL,
+	used BOOLEAN DEFAULT FALSE NOT NULL
+);
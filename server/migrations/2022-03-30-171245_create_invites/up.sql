@@ -0,0 +1,15 @@
+CREATE TABLE invites (
+	id SERIAL PRIMARY KEY,
+	code_hash TEXT UNIQUE NOT NULL, -- sha256 of the invite code, the code is only shown once
+	created_by INT REFERENCES students ON UPDATE CASCADE ON DELETE SET NULL, -- NULL for root
+	created TIMESTAMP DEFAULT NOW() NOT NULL,
+	expires TIMESTAMP NOT NULL,
+	max_uses INT DEFAULT 1 NOT NULL,
+	uses INT DEFAULT 0 NOT NULL,
+	revoked BOOLEAN DEFAULT FALSE NOT NULL,
+	-- optional values the new student gets instead of choosing them
+	email TEXT,
+	first_name VARCHAR(20),
+	last_name VARCHAR(20),
+	role_id INT REFERENCES roles ON UPDATE CASCADE ON DELETE SET NULL
+);
@@ -0,0 +1,23 @@
+CREATE TABLE student_totp (
+	student_id INT PRIMARY KEY REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE,
+	secret TEXT NOT NULL, -- base32, needed in plain text to compute the codes
+	enabled BOOLEAN DEFAULT FALSE NOT NULL, -- set once the first code was confirmed
+	last_step BIGINT, -- time step of the last accepted code, a code can't be used twice
+	created TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+CREATE TABLE totp_recovery_codes (
+	id SERIAL PRIMARY KEY,
+	student_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	code_hash TEXT NOT NULL,
+	used BOOLEAN DEFAULT FALSE NOT NULL
+);
+
+-- logins whose password was correct and that wait for the second factor
+CREATE TABLE login_challenges (
+	id SERIAL PRIMARY KEY,
+	student_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	token_hash TEXT UNIQUE NOT NULL,
+	expires TIMESTAMP NOT NULL,
+	failures INT DEFAULT 0 NOT NULL
+);
@@ -0,0 +1,12 @@
+CREATE TABLE api_keys (
+	id SERIAL PRIMARY KEY,
+	name TEXT UNIQUE NOT NULL,
+	key_hash TEXT UNIQUE NOT NULL, -- sha256 of the key, the key is only shown once
+	prefix TEXT NOT NULL, -- start of the key to tell keys apart
+	scope TEXT NOT NULL, -- read_only, comments or admin
+	student_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE, -- the student the key acts as, if any
+	created_by INT REFERENCES students ON UPDATE CASCADE ON DELETE SET NULL, -- NULL for root
+	created TIMESTAMP DEFAULT NOW() NOT NULL,
+	last_used TIMESTAMP,
+	revoked BOOLEAN DEFAULT FALSE NOT NULL
+);
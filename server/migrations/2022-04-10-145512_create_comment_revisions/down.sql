@@ -0,0 +1,5 @@
+DROP TABLE teacher_comment_revisions;
+DROP TABLE student_comment_revisions;
+
+ALTER TABLE teacher_comments DROP COLUMN edited;
+ALTER TABLE student_comments DROP COLUMN edited;
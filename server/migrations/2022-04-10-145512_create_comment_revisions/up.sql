@@ -0,0 +1,21 @@
+ALTER TABLE student_comments ADD COLUMN edited TIMESTAMP;
+ALTER TABLE teacher_comments ADD COLUMN edited TIMESTAMP;
+
+-- previous bodies of edited comments
+CREATE TABLE student_comment_revisions (
+	id SERIAL PRIMARY KEY,
+	comment_id INT REFERENCES student_comments ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	editor_id INT REFERENCES students ON UPDATE CASCADE ON DELETE SET NULL, -- who replaced the body, NULL for root
+	body TEXT NOT NULL,
+	written TIMESTAMP NOT NULL, -- when the body was published or last edited
+	replaced TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+CREATE TABLE teacher_comment_revisions (
+	id SERIAL PRIMARY KEY,
+	comment_id INT REFERENCES teacher_comments ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	editor_id INT REFERENCES students ON UPDATE CASCADE ON DELETE SET NULL,
+	body TEXT NOT NULL,
+	written TIMESTAMP NOT NULL,
+	replaced TIMESTAMP DEFAULT NOW() NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE teacher_comments DROP COLUMN deleted, DROP COLUMN depth, DROP COLUMN parent_id;
+ALTER TABLE student_comments DROP COLUMN deleted, DROP COLUMN depth, DROP COLUMN parent_id;
@@ -0,0 +1,12 @@
+ALTER TABLE student_comments
+	ADD COLUMN parent_id INT REFERENCES student_comments ON UPDATE CASCADE ON DELETE CASCADE,
+	ADD COLUMN depth INT DEFAULT 0 NOT NULL, -- 0 for comments that don't reply to another
+	ADD COLUMN deleted BOOLEAN DEFAULT FALSE NOT NULL; -- tombstone of a deleted comment that still has replies
+
+ALTER TABLE teacher_comments
+	ADD COLUMN parent_id INT REFERENCES teacher_comments ON UPDATE CASCADE ON DELETE CASCADE,
+	ADD COLUMN depth INT DEFAULT 0 NOT NULL,
+	ADD COLUMN deleted BOOLEAN DEFAULT FALSE NOT NULL;
+
+CREATE INDEX student_comments_parent_id_idx ON student_comments (parent_id);
+CREATE INDEX teacher_comments_parent_id_idx ON teacher_comments (parent_id);
@@ -0,0 +1,7 @@
+DROP TABLE teacher_comment_moderations;
+DROP TABLE student_comment_moderations;
+DROP TABLE teacher_comment_reports;
+DROP TABLE student_comment_reports;
+
+ALTER TABLE teacher_comments DROP COLUMN hidden;
+ALTER TABLE student_comments DROP COLUMN hidden;
@@ -0,0 +1,42 @@
+ALTER TABLE student_comments ADD COLUMN hidden BOOLEAN DEFAULT FALSE NOT NULL;
+ALTER TABLE teacher_comments ADD COLUMN hidden BOOLEAN DEFAULT FALSE NOT NULL;
+
+CREATE TABLE student_comment_reports (
+	id SERIAL PRIMARY KEY,
+	comment_id INT REFERENCES student_comments ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	reporter_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	reason TEXT NOT NULL,
+	created TIMESTAMP DEFAULT NOW() NOT NULL,
+	resolved BOOLEAN DEFAULT FALSE NOT NULL -- set once a moderator acted on the comment
+);
+
+-- a student can only have one open report per comment
+CREATE UNIQUE INDEX student_comment_reports_open_idx ON student_comment_reports (comment_id, reporter_id) WHERE NOT resolved;
+
+CREATE TABLE teacher_comment_reports (
+	id SERIAL PRIMARY KEY,
+	comment_id INT REFERENCES teacher_comments ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	reporter_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	reason TEXT NOT NULL,
+	created TIMESTAMP DEFAULT NOW() NOT NULL,
+	resolved BOOLEAN DEFAULT FALSE NOT NULL
+);
+
+CREATE UNIQUE INDEX teacher_comment_reports_open_idx ON teacher_comment_reports (comment_id, reporter_id) WHERE NOT resolved;
+
+-- the log outlives deleted comments, so comment_id is no foreign key
+CREATE TABLE student_comment_moderations (
+	id SERIAL PRIMARY KEY,
+	comment_id INT NOT NULL,
+	moderator_id INT REFERENCES students ON UPDATE CASCADE ON DELETE SET NULL, -- NULL for root and automatic actions
+	action TEXT NOT NULL, -- hide, restore, delete, dismiss or auto_hide
+	created TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+CREATE TABLE teacher_comment_moderations (
+	id SERIAL PRIMARY KEY,
+	comment_id INT NOT NULL,
+	moderator_id INT REFERENCES students ON UPDATE CASCADE ON DELETE SET NULL,
+	action TEXT NOT NULL,
+	created TIMESTAMP DEFAULT NOW() NOT NULL
+);
@@ -0,0 +1,3 @@
+DROP INDEX student_comments_receiver_status_idx;
+
+ALTER TABLE student_comments DROP COLUMN status;
@@ -0,0 +1,4 @@
+-- existing comments were visible before, so they count as approved
+ALTER TABLE student_comments ADD COLUMN status TEXT DEFAULT 'approved' NOT NULL CHECK (status IN ('pending', 'approved', 'rejected'));
+
+CREATE INDEX student_comments_receiver_status_idx ON student_comments (receiver_id, status);
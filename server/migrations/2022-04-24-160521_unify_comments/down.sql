@@ -0,0 +1,151 @@
+CREATE TABLE student_comments (
+	id SERIAL PRIMARY KEY,
+	author_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	receiver_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	body TEXT NOT NULL,
+	published TIMESTAMP DEFAULT NOW() NOT NULL,
+	edited TIMESTAMP,
+	parent_id INT REFERENCES student_comments ON UPDATE CASCADE ON DELETE CASCADE,
+	depth INT DEFAULT 0 NOT NULL,
+	deleted BOOLEAN DEFAULT FALSE NOT NULL,
+	hidden BOOLEAN DEFAULT FALSE NOT NULL,
+	status TEXT DEFAULT 'approved' NOT NULL CHECK (status IN ('pending', 'approved', 'rejected'))
+);
+
+CREATE TABLE teacher_comments (
+	id SERIAL PRIMARY KEY,
+	author_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	receiver_id INT REFERENCES teachers ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	body TEXT NOT NULL,
+	published TIMESTAMP DEFAULT NOW() NOT NULL,
+	edited TIMESTAMP,
+	parent_id INT REFERENCES teacher_comments ON UPDATE CASCADE ON DELETE CASCADE,
+	depth INT DEFAULT 0 NOT NULL,
+	deleted BOOLEAN DEFAULT FALSE NOT NULL,
+	hidden BOOLEAN DEFAULT FALSE NOT NULL
+);
+
+CREATE INDEX student_comments_parent_id_idx ON student_comments (parent_id);
+CREATE INDEX teacher_comments_parent_id_idx ON teacher_comments (parent_id);
+CREATE INDEX student_comments_receiver_status_idx ON student_comments (receiver_id, status);
+
+CREATE TABLE student_comment_votes (
+	id SERIAL PRIMARY KEY,
+	comment_id INT REFERENCES student_comments ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	student_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	upvote BOOLEAN NOT NULL,
+	UNIQUE(comment_id, student_id)
+);
+
+CREATE TABLE teacher_comment_votes (
+	id SERIAL PRIMARY KEY,
+	comment_id INT REFERENCES teacher_comments ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	student_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	upvote BOOLEAN NOT NULL,
+	UNIQUE(comment_id, student_id)
+);
+
+CREATE TABLE student_comment_revisions (
+	id SERIAL PRIMARY KEY,
+	comment_id INT REFERENCES student_comments ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	editor_id INT REFERENCES students ON UPDATE CASCADE ON DELETE SET NULL,
+	body TEXT NOT NULL,
+	written TIMESTAMP NOT NULL,
+	replaced TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+CREATE TABLE teacher_comment_revisions (
+	id SERIAL PRIMARY KEY,
+	comment_id INT REFERENCES teacher_comments ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	editor_id INT REFERENCES students ON UPDATE CASCADE ON DELETE SET NULL,
+	body TEXT NOT NULL,
+	written TIMESTAMP NOT NULL,
+	replaced TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+CREATE TABLE student_comment_reports (
+	id SERIAL PRIMARY KEY,
+	comment_id INT REFERENCES student_comments ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	reporter_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	reason TEXT NOT NULL,
+	created TIMESTAMP DEFAULT NOW() NOT NULL,
+	resolved BOOLEAN DEFAULT FALSE NOT NULL
+);
+
+CREATE UNIQUE INDEX student_comment_reports_open_idx ON student_comment_reports (comment_id, reporter_id) WHERE NOT resolved;
+
+CREATE TABLE teacher_comment_reports (
+	id SERIAL PRIMARY KEY,
+	comment_id INT REFERENCES teacher_comments ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	reporter_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	reason TEXT NOT NULL,
+	created TIMESTAMP DEFAULT NOW() NOT NULL,
+	resolved BOOLEAN DEFAULT FALSE NOT NULL
+);
+
+CREATE UNIQUE INDEX teacher_comment_reports_open_idx ON teacher_comment_reports (comment_id, reporter_id) WHERE NOT resolved;
+
+CREATE TABLE student_comment_moderations (
+	id SERIAL PRIMARY KEY,
+	comment_id INT NOT NULL,
+	moderator_id INT REFERENCES students ON UPDATE CASCADE ON DELETE SET NULL,
+	action TEXT NOT NULL,
+	created TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+CREATE TABLE teacher_comment_moderations (
+	id SERIAL PRIMARY KEY,
+	comment_id INT NOT NULL,
+	moderator_id INT REFERENCES students ON UPDATE CASCADE ON DELETE SET NULL,
+	action TEXT NOT NULL,
+	created TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+-- comments keep their ids, they are unique across both tables
+INSERT INTO student_comments (id, author_id, receiver_id, body, published, edited, parent_id, depth, deleted, hidden, status)
+	SELECT id, author_id, student_id, body, published, edited, parent_id, depth, deleted, hidden, status
+	FROM comments WHERE target = 'student' ORDER BY id;
+
+INSERT INTO teacher_comments (id, author_id, receiver_id, body, published, edited, parent_id, depth, deleted, hidden)
+	SELECT id, author_id, teacher_id, body, published, edited, parent_id, depth, deleted, hidden
+	FROM comments WHERE target = 'teacher' ORDER BY id;
+
+SELECT setval('student_comments_id_seq', GREATEST(
+	(SELECT MAX(id) FROM comments),
+	(SELECT MAX(comment_id) FROM comment_moderations),
+	0
+) + 1, false);
+SELECT setval('teacher_comments_id_seq', GREATEST(
+	(SELECT MAX(id) FROM comments),
+	(SELECT MAX(comment_id) FROM comment_moderations),
+	0
+) + 1, false);
+
+INSERT INTO student_comment_votes (comment_id, student_id, upvote)
+	SELECT v.comment_id, v.student_id, v.upvote FROM comment_votes v JOIN student_comments c ON c.id = v.comment_id ORDER BY v.id;
+INSERT INTO teacher_comment_votes (comment_id, student_id, upvote)
+	SELECT v.comment_id, v.student_id, v.upvote FROM comment_votes v JOIN teacher_comments c ON c.id = v.comment_id ORDER BY v.id;
+
+INSERT INTO student_comment_revisions (comment_id, editor_id, body, written, replaced)
+	SELECT r.comment_id, r.editor_id, r.body, r.written, r.replaced FROM comment_revisions r JOIN student_comments c ON c.id = r.comment_id ORDER BY r.id;
+INSERT INTO teacher_comment_revisions (comment_id, editor_id, body, written, replaced)
+	SELECT r.comment_id, r.editor_id, r.body, r.written, r.replaced FROM comment_revisions r JOIN teacher_comments c ON c.id = r.comment_id ORDER BY r.id;
+
+INSERT INTO student_comment_reports (comment_id, reporter_id, reason, created, resolved)
+	SELECT r.comment_id, r.reporter_id, r.reason, r.created, r.resolved FROM comment_reports r JOIN student_comments c ON c.id = r.comment_id ORDER BY r.id;
+INSERT INTO teacher_comment_reports (comment_id, reporter_id, reason, created, resolved)
+	SELECT r.comment_id, r.reporter_id, r.reason, r.created, r.resolved FROM comment_reports r JOIN teacher_comments c ON c.id = r.comment_id ORDER BY r.id;
+
+-- the log of deleted comments doesn't know their kind anymore, it stays with the student comments
+INSERT INTO teacher_comment_moderations (comment_id, moderator_id, action, created)
+	SELECT m.comment_id, m.moderator_id, m.action, m.created FROM comment_moderations m
+	WHERE m.comment_id IN (SELECT id FROM teacher_comments) ORDER BY m.id;
+INSERT INTO student_comment_moderations (comment_id, moderator_id, action, created)
+	SELECT m.comment_id, m.moderator_id, m.action, m.created FROM comment_moderations m
+	WHERE m.comment_id NOT IN (SELECT id FROM teacher_comments) ORDER BY m.id;
+
+DROP TABLE comment_moderations;
+DROP TABLE comment_reports;
+DROP TABLE comment_revisions;
+DROP TABLE comment_votes;
+DROP TABLE comments;
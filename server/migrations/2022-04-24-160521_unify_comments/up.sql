@@ -0,0 +1,149 @@
+-- comments on every kind of target live in one table, each kind has its own receiver column,
+-- so the receivers keep their foreign keys. A new kind adds a column and extends the checks.
+CREATE TABLE comments (
+	id SERIAL PRIMARY KEY,
+	target TEXT NOT NULL CHECK (target IN ('student', 'teacher')),
+	student_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE, -- receiver of comments on students
+	teacher_id INT REFERENCES teachers ON UPDATE CASCADE ON DELETE CASCADE, -- receiver of comments on teachers
+	receiver_id INT GENERATED ALWAYS AS (COALESCE(student_id, teacher_id)) STORED,
+	author_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	body TEXT NOT NULL,
+	published TIMESTAMP DEFAULT NOW() NOT NULL,
+	edited TIMESTAMP,
+	parent_id INT REFERENCES comments ON UPDATE CASCADE ON DELETE CASCADE,
+	depth INT DEFAULT 0 NOT NULL,
+	deleted BOOLEAN DEFAULT FALSE NOT NULL,
+	hidden BOOLEAN DEFAULT FALSE NOT NULL,
+	status TEXT DEFAULT 'approved' NOT NULL CHECK (status IN ('pending', 'approved', 'rejected')),
+	legacy_teacher_id INT UNIQUE, -- id of teacher comments from before, the routes below /comment/teacher still find them by it
+	-- exactly the receiver column of the target is set
+	CHECK ((student_id IS NOT NULL) = (target = 'student')),
+	CHECK ((teacher_id IS NOT NULL) = (target = 'teacher'))
+);
+
+CREATE INDEX comments_parent_id_idx ON comments (parent_id);
+CREATE INDEX comments_target_receiver_status_idx ON comments (target, receiver_id, status);
+
+CREATE TABLE comment_votes (
+	id SERIAL PRIMARY KEY,
+	comment_id INT REFERENCES comments ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	student_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	upvote BOOLEAN NOT NULL, -- true -> upvote; false -> downvote, no row -> no vote
+	UNIQUE(comment_id, student_id)
+);
+
+CREATE TABLE comment_revisions (
+	id SERIAL PRIMARY KEY,
+	comment_id INT REFERENCES comments ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	editor_id INT REFERENCES students ON UPDATE CASCADE ON DELETE SET NULL, -- who replaced the body, NULL for root
+	body TEXT NOT NULL,
+	written TIMESTAMP NOT NULL, -- when the body was published or last edited
+	replaced TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+CREATE TABLE comment_reports (
+	id SERIAL PRIMARY KEY,
+	comment_id INT REFERENCES comments ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	reporter_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	reason TEXT NOT NULL,
+	created TIMESTAMP DEFAULT NOW() NOT NULL,
+	resolved BOOLEAN DEFAULT FALSE NOT NULL -- set once a moderator acted on the comment
+);
+
+-- a student can only have one open report per comment
+CREATE UNIQUE INDEX comment_reports_open_idx ON comment_reports (comment_id, reporter_id) WHERE NOT resolved;
+
+-- the log outlives deleted comments, so comment_id is no foreign key
+CREATE TABLE comment_moderations (
+	id SERIAL PRIMARY KEY,
+	comment_id INT NOT NULL,
+	moderator_id INT REFERENCES students ON UPDATE CASCADE ON DELETE SET NULL, -- NULL for root and automatic actions
+	action TEXT NOT NULL, -- hide, restore, delete, dismiss, auto_hide or reveal_author
+	created TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+-- student comments keep their ids
+INSERT INTO comments (id, target, student_id, author_id, body, published, edited, parent_id, depth, deleted, hidden, status)
+	SELECT id, 'student', receiver_id, author_id, body, published, edited, parent_id, depth, deleted, hidden, status
+	FROM student_comments
+	ORDER BY id;
+
+-- the moderation log may name deleted comments with higher ids. Every new id is higher than the old
+-- teacher comment ids as well, so an id on the teacher routes is either an old or a new one.
+SELECT setval('comments_id_seq', GREATEST(
+	(SELECT MAX(id) FROM student_comments),
+	(SELECT MAX(comment_id) FROM student_comment_moderations),
+	(SELECT MAX(id) FROM teacher_comments),
+	(SELECT MAX(comment_id) FROM teacher_comment_moderations),
+	0
+) + 1, false);
+
+-- teacher comments are numbered after all old ids, ids only left in the moderation log are moved as well
+CREATE TEMPORARY TABLE teacher_comment_ids AS
+	SELECT old_id, nextval('comments_id_seq') AS new_id
+	FROM (
+		SELECT id AS old_id FROM teacher_comments
+		UNION
+		SELECT comment_id FROM teacher_comment_moderations
+		ORDER BY old_id
+	) AS old_ids;
+
+INSERT INTO comments (id, target, teacher_id, author_id, body, published, edited, parent_id, depth, deleted, hidden, status, legacy_teacher_id)
+	SELECT ids.new_id, 'teacher', c.receiver_id, c.author_id, c.body, c.published, c.edited, parents.new_id, c.depth, c.deleted, c.hidden, 'approved', c.id
+	FROM teacher_comments c
+	JOIN teacher_comment_ids ids ON ids.old_id = c.id
+	LEFT JOIN teacher_comment_ids parents ON parents.old_id = c.parent_id
+	ORDER BY ids.new_id;
+
+INSERT INTO comment_votes (comment_id, student_id, upvote)
+	SELECT comment_id, student_id, upvote FROM (
+		SELECT v.id, 0 AS kind, v.comment_id, v.student_id, v.upvote
+		FROM student_comment_votes v
+		UNION ALL
+		SELECT v.id, 1, ids.new_id, v.student_id, v.upvote
+		FROM teacher_comment_votes v JOIN teacher_comment_ids ids ON ids.old_id = v.comment_id
+	) AS votes
+	ORDER BY kind, id;
+
+INSERT INTO comment_revisions (comment_id, editor_id, body, written, replaced)
+	SELECT comment_id, editor_id, body, written, replaced FROM (
+		SELECT r.comment_id, r.editor_id, r.body, r.written, r.replaced
+		FROM student_comment_revisions r
+		UNION ALL
+		SELECT ids.new_id, r.editor_id, r.body, r.written, r.replaced
+		FROM teacher_comment_revisions r JOIN teacher_comment_ids ids ON ids.old_id = r.comment_id
+	) AS revisions
+	ORDER BY replaced;
+
+INSERT INTO comment_reports (comment_id, reporter_id, reason, created, resolved)
+	SELECT comment_id, reporter_id, reason, created, resolved FROM (
+		SELECT r.comment_id, r.reporter_id, r.reason, r.created, r.resolved
+		FROM student_comment_reports r
+		UNION ALL
+		SELECT ids.new_id, r.reporter_id, r.reason, r.created, r.resolved
+		FROM teacher_comment_reports r JOIN teacher_comment_ids ids ON ids.old_id = r.comment_id
+	) AS reports
+	ORDER BY created;
+
+INSERT INTO comment_moderations (comment_id, moderator_id, action, created)
+	SELECT comment_id, moderator_id, action, created FROM (
+		SELECT m.comment_id, m.moderator_id, m.action, m.created
+		FROM student_comment_moderations m
+		UNION ALL
+		SELECT ids.new_id, m.moderator_id, m.action, m.created
+		FROM teacher_comment_moderations m JOIN teacher_comment_ids ids ON ids.old_id = m.comment_id
+	) AS moderations
+	ORDER BY created;
+
+DROP TABLE teacher_comment_ids;
+
+DROP TABLE teacher_comment_moderations;
+DROP TABLE student_comment_moderations;
+DROP TABLE teacher_comment_reports;
+DROP TABLE student_comment_reports;
+DROP TABLE teacher_comment_revisions;
+DROP TABLE student_comment_revisions;
+DROP TABLE teacher_comment_votes;
+DROP TABLE student_comment_votes;
+DROP TABLE teacher_comments;
+DROP TABLE student_comments;
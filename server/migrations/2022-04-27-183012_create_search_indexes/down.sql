@@ -0,0 +1,3 @@
+DROP INDEX teachers_name_search_idx;
+DROP INDEX students_name_search_idx;
+DROP INDEX comments_body_search_idx;
@@ -0,0 +1,6 @@
+-- The documents are only computed inside the indexes, so the tables keep their columns.
+-- Queries have to repeat the exact expressions to use them.
+-- 'simple' doesn't stem, names and comments are matched as written in any language.
+CREATE INDEX comments_body_search_idx ON comments USING GIN (to_tsvector('simple', body));
+CREATE INDEX students_name_search_idx ON students USING GIN (to_tsvector('simple', first_name || ' ' || last_name));
+CREATE INDEX teachers_name_search_idx ON teachers USING GIN (to_tsvector('simple', prefix || ' ' || name));
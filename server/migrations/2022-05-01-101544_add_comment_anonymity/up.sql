@@ -0,0 +1 @@
+ALTER TABLE comments ADD COLUMN anonymous BOOLEAN DEFAULT FALSE NOT NULL;
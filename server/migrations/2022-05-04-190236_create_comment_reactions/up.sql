@@ -0,0 +1,7 @@
+CREATE TABLE comment_reactions (
+	id SERIAL PRIMARY KEY,
+	comment_id INT REFERENCES comments ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	student_id INT REFERENCES students ON UPDATE CASCADE ON DELETE CASCADE NOT NULL,
+	reaction TEXT NOT NULL, -- one of the reactions of the settings when it was given
+	UNIQUE(comment_id, student_id, reaction)
+);
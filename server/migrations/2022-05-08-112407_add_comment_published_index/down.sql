@@ -0,0 +1 @@
+DROP INDEX comments_published_idx;
@@ -0,0 +1,2 @@
+-- newest comments are listed first, page by page
+CREATE INDEX comments_published_idx ON comments (published, id);
@@ -0,0 +1,2 @@
+ALTER TABLE comment_moderations DROP COLUMN root;
+ALTER TABLE comment_moderations DROP COLUMN api_key_id;
@@ -0,0 +1,3 @@
+-- root and API keys without a student moderate as well, the log tells them apart
+ALTER TABLE comment_moderations ADD COLUMN api_key_id INT REFERENCES api_keys ON UPDATE CASCADE ON DELETE SET NULL; -- the key the action was taken with, if any
+ALTER TABLE comment_moderations ADD COLUMN root BOOLEAN DEFAULT FALSE NOT NULL; -- taken by root
@@ -1,54 +1,218 @@
-use actix_web::HttpRequest;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration as StdDuration, SystemTime};
+
+use actix_web::dev::Payload;
+use actix_web::http::Method;
+use actix_web::{web, FromRequest, HttpRequest};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use jsonwebtoken::{Header, Validation};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use babibapp_models as models;
+use babibapp_schema::schema;
 
-pub use babibapp_models::wrappers::TokenWrapper;
+pub use babibapp_models::api_key::ApiKeyScope;
+pub use babibapp_models::role::Permission;
+pub use babibapp_models::wrappers::{TokenPairWrapper, TokenWrapper};
 
+use crate::db;
 use crate::error::BabibappError;
+use crate::keys::TokenKeys;
+use crate::request::RequestContext;
+use crate::settings::RootSettings;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub id: i32,
-    pub admin: bool,
+    pub permissions: Vec<Permission>,
+    /// Id of the refresh token (session) the token was issued for, `None` for root tokens
+    pub sid: Option<i32>,
+    /// Fingerprint of the root password hash a root token was issued for, `None` for students
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    /// The API key the claims were built for, `None` for tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<ApiKeyClaims>,
+    pub jti: String,
     exp: i64,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ApiKeyClaims {
+    pub id: i32,
+    pub scope: ApiKeyScope,
+}
+
+/// Start of every API key, tells them apart from tokens
+pub const API_KEY_PREFIX: &str = "babi_";
+
 impl Claims {
-    pub fn new(id: i32, admin: bool, expiration_hours: i64) -> Self {
+    pub fn new(
+        id: i32,
+        permissions: Vec<Permission>,
+        sid: Option<i32>,
+        expiration_minutes: i64,
+    ) -> Self {
         Claims {
             id,
-            admin,
-            exp: (Utc::now() + Duration::hours(expiration_hours)).timestamp(),
+            permissions,
+            sid,
+            root: None,
+            api_key: None,
+            jti: generate_random_token(),
+            exp: (Utc::now() + Duration::minutes(expiration_minutes)).timestamp(),
         }
     }
 
-    pub fn root(expiration_minutes: i64) -> Self {
+    pub fn root(root_settings: &RootSettings) -> Self {
         Claims {
             id: 0,
-            admin: true,
-            exp: (Utc::now() + Duration::minutes(expiration_minutes)).timestamp(),
+            permissions: Permission::ALL.to_vec(),
+            sid: None,
+            root: Some(root_fingerprint(root_settings)),
+            api_key: None,
+            jti: generate_random_token(),
+            exp: (Utc::now() + Duration::minutes(root_settings.expiration_minutes)).timestamp(),
+        }
+    }
+
+    /// Claims of a request made with an API key, they are built anew for every request
+    pub fn from_api_key(api_key: &models::api_key::ApiKey) -> Self {
+        Claims {
+            id: api_key.student_id.unwrap_or(0),
+            permissions: match api_key.scope {
+                ApiKeyScope::Admin => Permission::ALL.to_vec(),
+                ApiKeyScope::ReadOnly | ApiKeyScope::Comments => Vec::new(),
+            },
+            sid: None,
+            root: None,
+            api_key: Some(ApiKeyClaims {
+                id: api_key.id,
+                scope: api_key.scope,
+            }),
+            jti: format!("api_key:{}", api_key.id),
+            exp: Utc::now().timestamp(),
+        }
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.root.is_some()
+    }
+
+    /// The student the claims act as, `None` for root and API keys without a student
+    pub fn student_id(&self) -> Option<i32> {
+        if self.is_root() || (self.api_key.is_some() && self.id == 0) {
+            None
+        } else {
+            Some(self.id)
         }
     }
+
+    pub fn exp(&self) -> i64 {
+        self.exp
+    }
+
+    pub fn has(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+    }
 }
 
-pub fn create_jwt(claims: Claims, secret: String) -> Result<String, BabibappError> {
-    let encoding_key = EncodingKey::from_secret(secret.as_bytes());
-    jsonwebtoken::encode(&Header::default(), &claims, &encoding_key).map_err(|e| e.into())
+pub fn create_jwt(claims: &Claims, keys: &TokenKeys) -> Result<String, BabibappError> {
+    let mut header = Header::new(keys.signing_algorithm);
+    header.kid = keys.signing_kid.clone();
+    jsonwebtoken::encode(&header, claims, &keys.encoding_key).map_err(|e| e.into())
 }
 
-pub fn decode_jwt(token: &str, secret: String) -> Result<Claims, BabibappError> {
-    let decoding_key = DecodingKey::from_secret(secret.as_bytes());
-    jsonwebtoken::decode::<Claims>(token, &decoding_key, &Validation::default())
+pub fn decode_jwt(token: &str, keys: &TokenKeys) -> Result<Claims, BabibappError> {
+    let header = jsonwebtoken::decode_header(token)
+        .map_err(|_| BabibappError::unauthorized("Invalid token"))?;
+
+    // tokens signed with a key that was rotated out are rejected here
+    let key = keys
+        .verification_key(header.kid.as_deref())
+        .ok_or_else(|| BabibappError::unauthorized("Invalid token"))?;
+
+    jsonwebtoken::decode::<Claims>(token, &key.key, &Validation::new(key.algorithm))
         .map(|data| data.claims)
-        .map_err(|e| e.into())
+        .map_err(|_| BabibappError::unauthorized("Invalid token"))
+}
+
+/// Fingerprint of the configured root credentials,
+/// changing the root password hash invalidates all root tokens issued before
+pub fn root_fingerprint(root_settings: &RootSettings) -> String {
+    hash_token(&format!(
+        "{}:{}",
+        root_settings.email, root_settings.password_hash
+    ))[..16]
+        .to_string()
 }
 
-pub fn validate_token(token: &str, secret: String) -> Result<Claims, BabibappError> {
-    let claims = decode_jwt(token, secret)?;
+/// Looks up an API key and marks it as used
+async fn validate_api_key(key: &str, context: &RequestContext) -> Result<Claims, BabibappError> {
+    let presented_hash = hash_token(key);
+
+    let api_key = db::blocked_access(&context.pool, move |conn| {
+        use schema::api_keys::dsl::*;
+
+        diesel::update(
+            api_keys
+                .filter(key_hash.eq(presented_hash))
+                .filter(revoked.eq(false)),
+        )
+        .set(last_used.eq(SystemTime::now()))
+        .get_result::<models::api_key::ApiKeyRecord>(conn)
+        .optional()
+    })
+    .await??
+    .ok_or_else(|| BabibappError::unauthorized("Invalid API key"))?;
+
+    Ok(Claims::from_api_key(&api_key.into()))
+}
+
+/// Whether a request may be made with an API key of the scope
+fn scope_allows(scope: ApiKeyScope, req: &HttpRequest) -> bool {
+    match scope {
+        ApiKeyScope::Admin => true,
+        ApiKeyScope::Comments => req.method() == Method::GET || req.path().starts_with("/comment/"),
+        ApiKeyScope::ReadOnly => req.method() == Method::GET,
+    }
+}
+
+/// Validates an access token or an API key
+pub async fn validate_token(
+    token: &str,
+    context: &RequestContext,
+) -> Result<Claims, BabibappError> {
+    if token.starts_with(API_KEY_PREFIX) {
+        return validate_api_key(token, context).await;
+    }
+
+    let claims = decode_jwt(token, &context.keys)?;
     if claims.exp < Utc::now().timestamp() {
-        return Err(BabibappError::from_msg("Token expired!"));
+        return Err(BabibappError::unauthorized("Token expired"));
     }
+
+    if let Some(fingerprint) = &claims.root {
+        if *fingerprint != root_fingerprint(&context.settings.root) {
+            return Err(BabibappError::unauthorized("Token revoked"));
+        }
+    }
+
+    let jti = claims.jti.clone();
+    let sid = claims.sid;
+    let revoked =
+        db::blocked_access(&context.pool, move |conn| is_revoked(conn, &jti, sid)).await??;
+
+    if revoked {
+        return Err(BabibappError::unauthorized("Token revoked"));
+    }
+
     Ok(claims)
 }
 
@@ -58,17 +222,32 @@ pub fn token_from_jwt(jwt: &str) -> TokenWrapper {
     }
 }
 
-pub fn token_from_claims(claims: Claims, secret: String) -> Result<TokenWrapper, BabibappError> {
-    let jwt = create_jwt(claims, secret)?;
+pub fn token_from_claims(claims: Claims, keys: &TokenKeys) -> Result<TokenWrapper, BabibappError> {
+    let jwt = create_jwt(&claims, keys)?;
     Ok(token_from_jwt(&jwt))
 }
 
+pub fn token_pair_from_claims(
+    claims: Claims,
+    refresh_token: Option<String>,
+    keys: &TokenKeys,
+) -> Result<TokenPairWrapper, BabibappError> {
+    let jwt = create_jwt(&claims, keys)?;
+    Ok(TokenPairWrapper {
+        token: jwt,
+        expires_in: claims.exp - Utc::now().timestamp(),
+        refresh_token,
+        totp_required: false,
+    })
+}
+
 pub fn token_from_request(req: HttpRequest) -> Result<TokenWrapper, BabibappError> {
     let mut auth_header = req
         .headers()
         .get(actix_web::http::header::AUTHORIZATION)
-        .ok_or(anyhow::anyhow!("No authorization header"))?
-        .to_str()?;
+        .ok_or_else(|| BabibappError::unauthorized("No authorization header"))?
+        .to_str()
+        .map_err(|_| BabibappError::unauthorized("Invalid authorization header"))?;
 
     if auth_header.starts_with("Bearer ") {
         auth_header = auth_header.strip_prefix("Bearer ").unwrap();
@@ -77,3 +256,282 @@ pub fn token_from_request(req: HttpRequest) -> Result<TokenWrapper, BabibappErro
     let wrapped = token_from_jwt(auth_header);
     Ok(wrapped)
 }
+
+async fn claims_from_request(req: HttpRequest) -> Result<Claims, BabibappError> {
+    let context = req
+        .app_data::<web::Data<RequestContext>>()
+        .expect("RequestContext is not registered as app data")
+        .clone();
+
+    let token = token_from_request(req.clone())?;
+    let claims = validate_token(&token.token, &context).await?;
+
+    if let Some(api_key) = &claims.api_key {
+        if !scope_allows(api_key.scope, &req) {
+            return Err(BabibappError::forbidden(format!(
+                "The API key is limited to the {} scope",
+                api_key.scope
+            )));
+        }
+    }
+
+    Ok(claims)
+}
+
+/// Extractor for the claims of a valid token, requests without one are rejected with 401
+#[derive(Debug)]
+pub struct AuthenticatedUser(pub Claims);
+
+impl FromRequest for AuthenticatedUser {
+    type Error = BabibappError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move {
+            let claims = claims_from_request(req).await?;
+            Ok(AuthenticatedUser(claims))
+        })
+    }
+}
+
+/// Extractor for the claims of a valid student token,
+/// root tokens and API keys without a student are rejected with 403
+#[derive(Debug)]
+pub struct StudentUser(pub Claims);
+
+impl FromRequest for StudentUser {
+    type Error = BabibappError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        Box::pin(async move {
+            let claims = claims_from_request(req).await?;
+            if claims.is_root() {
+                return Err(BabibappError::forbidden(
+                    "The root account is not a student",
+                ));
+            }
+            if claims.student_id().is_none() {
+                return Err(BabibappError::forbidden(
+                    "The API key doesn't act as a student",
+                ));
+            }
+            Ok(StudentUser(claims))
+        })
+    }
+}
+
+/// Defines an extractor for the claims of a valid token that carries the given permission,
+/// requests with a token lacking it are rejected with 403
+macro_rules! permission_extractor {
+    ($(#[$meta:meta])* $name:ident, $permission:expr) => {
+        $(#[$meta])*
+        #[derive(Debug)]
+        pub struct $name(pub Claims);
+
+        impl FromRequest for $name {
+            type Error = BabibappError;
+            type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+            fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+                let req = req.clone();
+                Box::pin(async move {
+                    let claims = claims_from_request(req).await?;
+                    if !claims.has($permission) {
+                        return Err(BabibappError::forbidden(format!(
+                            "Missing permission: {}",
+                            $permission
+                        )));
+                    }
+                    Ok($name(claims))
+                })
+            }
+        }
+    };
+}
+
+permission_extractor!(
+    /// Extractor for users allowed to manage all student accounts
+    StudentManager,
+    Permission::ManageStudents
+);
+permission_extractor!(
+    /// Extractor for users allowed to manage roles
+    RoleManager,
+    Permission::ManageRoles
+);
+permission_extractor!(
+    /// Extractor for users allowed to edit teachers
+    TeacherEditor,
+    Permission::ManageTeachers
+);
+permission_extractor!(
+    /// Extractor for users allowed to moderate comments
+    CommentModerator,
+    Permission::ModerateComments
+);
+
+pub fn generate_random_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(48)
+        .map(char::from)
+        .collect()
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Collects the permissions of all roles of a student
+pub fn load_permissions(conn: &PgConnection, student_id: i32) -> QueryResult<Vec<Permission>> {
+    use schema::{role_permissions, student_roles};
+
+    let permissions = role_permissions::table
+        .inner_join(student_roles::table.on(student_roles::role_id.eq(role_permissions::role_id)))
+        .filter(student_roles::student_id.eq(student_id))
+        .select(role_permissions::permission)
+        .distinct()
+        .load::<String>(conn)?;
+
+    // unknown permissions may be left over by newer versions and are ignored
+    Ok(permissions
+        .iter()
+        .filter_map(|permission| permission.parse().ok())
+        .collect())
+}
+
+/// Rejects managing another student who holds permissions the claims lack,
+/// otherwise a student manager could reset an admin's password and log in as them
+pub fn check_outranks(
+    conn: &PgConnection,
+    claims: &Claims,
+    student_id: i32,
+) -> Result<(), BabibappError> {
+    if claims.student_id() == Some(student_id) {
+        return Ok(());
+    }
+
+    let permissions = load_permissions(conn, student_id)?;
+    if let Some(missing) = permissions.iter().find(|p| !claims.has(**p)) {
+        return Err(BabibappError::forbidden(format!(
+            "The student holds a permission you are missing: {}",
+            missing
+        )));
+    }
+
+    Ok(())
+}
+
+/// Whether any student holds a role that allows managing roles,
+/// such a student can take over everything root is needed for
+pub fn admin_exists(conn: &PgConnection) -> QueryResult<bool> {
+    use diesel::dsl::exists;
+    use schema::{role_permissions, student_roles};
+
+    diesel::select(exists(
+        role_permissions::table
+            .inner_join(
+                student_roles::table.on(student_roles::role_id.eq(role_permissions::role_id)),
+            )
+            .filter(role_permissions::permission.eq(Permission::ManageRoles.as_str())),
+    ))
+    .get_result(conn)
+}
+
+/// Starts a new session for a student and returns it together with the plain refresh token.
+pub fn create_session(
+    conn: &PgConnection,
+    student_id: i32,
+    expiration_days: i64,
+) -> QueryResult<(models::token::RefreshToken, String)> {
+    use schema::refresh_tokens;
+
+    let refresh_token = generate_random_token();
+
+    let new_refresh_token = models::token::NewRefreshToken {
+        student_id,
+        token_hash: hash_token(&refresh_token),
+        expires: SystemTime::now() + StdDuration::from_secs(expiration_days as u64 * 24 * 60 * 60),
+    };
+
+    let session = diesel::insert_into(refresh_tokens::table)
+        .values(&new_refresh_token)
+        .get_result::<models::token::RefreshToken>(conn)?;
+
+    Ok((session, refresh_token))
+}
+
+/// Revokes the access token with the given `jti` and either its own session or every session of the student.
+pub fn revoke_sessions(
+    conn: &PgConnection,
+    claims: &Claims,
+    all_sessions: bool,
+) -> QueryResult<()> {
+    use schema::refresh_tokens::dsl::*;
+    use schema::revoked_tokens;
+
+    let now = SystemTime::now();
+
+    diesel::delete(revoked_tokens::table.filter(revoked_tokens::expires.lt(now))).execute(conn)?;
+
+    let revoked_token = models::token::RevokedToken {
+        jti: claims.jti.clone(),
+        expires: SystemTime::UNIX_EPOCH + StdDuration::from_secs(claims.exp.max(0) as u64),
+    };
+
+    diesel::insert_into(revoked_tokens::table)
+        .values(&revoked_token)
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+
+    if all_sessions {
+        diesel::update(refresh_tokens.filter(student_id.eq(claims.id)))
+            .set(revoked.eq(true))
+            .execute(conn)?;
+    } else if let Some(sid) = claims.sid {
+        diesel::update(refresh_tokens.find(sid))
+            .set(revoked.eq(true))
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
+
+/// Ends every session of a student except `keep`, along with their access tokens
+pub fn end_sessions(conn: &PgConnection, student: i32, keep: Option<i32>) -> QueryResult<()> {
+    use schema::refresh_tokens::dsl::*;
+
+    diesel::update(
+        refresh_tokens
+            .filter(student_id.eq(student))
+            .filter(id.ne(keep.unwrap_or(0))),
+    )
+    .set(revoked.eq(true))
+    .execute(conn)?;
+
+    Ok(())
+}
+
+fn is_revoked(conn: &PgConnection, token_jti: &str, sid: Option<i32>) -> QueryResult<bool> {
+    use diesel::dsl::exists;
+    use schema::refresh_tokens::dsl::*;
+    use schema::revoked_tokens;
+
+    let jti_revoked =
+        diesel::select(exists(revoked_tokens::table.find(token_jti))).get_result::<bool>(conn)?;
+
+    let session_revoked = match sid {
+        Some(sid) => refresh_tokens
+            .find(sid)
+            .select(revoked)
+            .first::<bool>(conn)
+            .optional()?
+            .unwrap_or(true),
+        None => false,
+    };
+
+    Ok(jti_revoked || session_revoked)
+}
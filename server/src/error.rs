@@ -1,25 +1,67 @@
 use std::fmt;
 
 use actix_web::{http::StatusCode, HttpResponse};
-use anyhow::anyhow;
-use serde::Serialize;
+use diesel::result::DatabaseErrorKind;
 
-#[derive(Serialize)]
-struct ApiError {
-    error: &'static str,
-}
+pub use babibapp_models::error::{ErrorCode, ErrorResponse};
 
-pub struct BabibappError {
-    pub msg: Option<&'static str>,
-    pub inner: anyhow::Error,
+pub enum BabibappError {
+    Unauthorized(String),
+    Forbidden(String),
+    Conflict(String),
+    ValidationFailed(String),
+    NotFound(String),
+    TooManyRequests(String),
+    Internal(anyhow::Error),
 }
 
 impl BabibappError {
-    pub fn from_msg(msg: &'static str) -> Self {
-        let inner = anyhow!("{}", msg);
-        BabibappError {
-            msg: Some(msg),
-            inner,
+    pub fn unauthorized(msg: impl Into<String>) -> Self {
+        BabibappError::Unauthorized(msg.into())
+    }
+
+    pub fn forbidden(msg: impl Into<String>) -> Self {
+        BabibappError::Forbidden(msg.into())
+    }
+
+    pub fn conflict(msg: impl Into<String>) -> Self {
+        BabibappError::Conflict(msg.into())
+    }
+
+    pub fn validation_failed(msg: impl Into<String>) -> Self {
+        BabibappError::ValidationFailed(msg.into())
+    }
+
+    pub fn not_found(msg: impl Into<String>) -> Self {
+        BabibappError::NotFound(msg.into())
+    }
+
+    pub fn too_many_requests(msg: impl Into<String>) -> Self {
+        BabibappError::TooManyRequests(msg.into())
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            BabibappError::Unauthorized(_) => ErrorCode::Unauthorized,
+            BabibappError::Forbidden(_) => ErrorCode::Forbidden,
+            BabibappError::Conflict(_) => ErrorCode::Conflict,
+            BabibappError::ValidationFailed(_) => ErrorCode::ValidationFailed,
+            BabibappError::NotFound(_) => ErrorCode::NotFound,
+            BabibappError::TooManyRequests(_) => ErrorCode::TooManyRequests,
+            BabibappError::Internal(_) => ErrorCode::Internal,
+        }
+    }
+
+    /// The message sent to the client, internal errors are not exposed
+    pub fn message(&self) -> String {
+        match self {
+            BabibappError::Unauthorized(msg)
+            | BabibappError::Forbidden(msg)
+            | BabibappError::Conflict(msg)
+            | BabibappError::ValidationFailed(msg)
+            | BabibappError::NotFound(msg)
+            | BabibappError::TooManyRequests(msg) => msg.clone(),
+            BabibappError::Internal(_) => "Internal server error".to_string(),
         }
     }
 }
@@ -29,46 +71,118 @@ where
     T: Into<anyhow::Error>,
 {
     fn from(t: T) -> Self {
-        BabibappError {
-            msg: None,
-            inner: t.into(),
+        use diesel::result::Error as DieselError;
+
+        let inner: anyhow::Error = t.into();
+
+        match inner.downcast_ref::<DieselError>() {
+            Some(DieselError::NotFound) => BabibappError::not_found("Not found"),
+            Some(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, info)) => {
+                log::debug!("Unique violation: {}", info.message());
+                BabibappError::conflict(
+                    info.constraint_name()
+                        .and_then(unique_violation_message)
+                        .unwrap_or("Already exists"),
+                )
+            }
+            Some(DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, info)) => {
+                log::debug!("Foreign key violation: {}", info.message());
+                BabibappError::validation_failed(
+                    info.constraint_name()
+                        .and_then(foreign_key_violation_message)
+                        .unwrap_or("A referenced entity does not exist"),
+                )
+            }
+            _ => BabibappError::Internal(inner),
         }
     }
 }
 
+/// Messages for the unique constraints handlers run into,
+/// the database's own messages would expose table and constraint names
+fn unique_violation_message(constraint: &str) -> Option<&'static str> {
+    let message = match constraint {
+        "students_email_key" => "A student with this email already exists",
+        "students_first_name_last_name_key" => "A student with this name already exists",
+        "teachers_name_prefix_key" => "A teacher with this name and prefix already exists",
+        "roles_name_key" => "A role with this name already exists",
+        "role_permissions_pkey" => "The role already has this permission",
+        "student_roles_pkey" => "The student already has this role",
+        "api_keys_name_key" => "An API key with this name already exists",
+        "comment_votes_comment_id_student_id_key" => "Already voted on this comment",
+        "comment_reports_open_idx" => "Already reported this comment",
+        "comment_reactions_comment_id_student_id_reaction_key" => "Already reacted to this comment",
+        _ => return None,
+    };
+    Some(message)
+}
+
+fn foreign_key_violation_message(constraint: &str) -> Option<&'static str> {
+    let message = match constraint {
+        "comments_student_id_fkey"
+        | "comments_author_id_fkey"
+        | "comment_votes_student_id_fkey"
+        | "comment_reactions_student_id_fkey"
+        | "comment_reports_reporter_id_fkey"
+        | "student_roles_student_id_fkey"
+        | "api_keys_student_id_fkey" => "The student does not exist",
+        "comments_teacher_id_fkey" => "The teacher does not exist",
+        "comments_parent_id_fkey"
+        | "comment_votes_comment_id_fkey"
+        | "comment_reactions_comment_id_fkey"
+        | "comment_reports_comment_id_fkey"
+        | "comment_revisions_comment_id_fkey" => "The comment does not exist",
+        "role_permissions_role_id_fkey" | "student_roles_role_id_fkey" | "invites_role_id_fkey" => {
+            "The role does not exist"
+        }
+        _ => return None,
+    };
+    Some(message)
+}
+
 impl fmt::Debug for BabibappError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("BabibappError")
-            .field("msg", &self.msg)
-            .field("inner", &self.inner)
-            .finish()
+        match self {
+            BabibappError::Internal(inner) => f.debug_tuple("Internal").field(inner).finish(),
+            _ => f
+                .debug_tuple("BabibappError")
+                .field(&self.code())
+                .field(&self.message())
+                .finish(),
+        }
     }
 }
 
 impl fmt::Display for BabibappError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let Some(msg) = self.msg {
-            write!(f, "{}: ", msg)?;
+        match self {
+            BabibappError::Internal(inner) => writeln!(f, "{}", inner),
+            _ => writeln!(f, "{}", self.message()),
         }
-        writeln!(f, "{}", self.inner)
     }
 }
 
 impl actix_web::error::ResponseError for BabibappError {
-    fn status_code(&self) -> actix_web::http::StatusCode {
-        match self.inner.downcast_ref::<diesel::result::Error>() {
-            Some(diesel::result::Error::NotFound) => StatusCode::NOT_FOUND,
-            _ => StatusCode::BAD_REQUEST,
+    fn status_code(&self) -> StatusCode {
+        match self {
+            BabibappError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            BabibappError::Forbidden(_) => StatusCode::FORBIDDEN,
+            BabibappError::Conflict(_) => StatusCode::CONFLICT,
+            BabibappError::ValidationFailed(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            BabibappError::NotFound(_) => StatusCode::NOT_FOUND,
+            BabibappError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            BabibappError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
     fn error_response(&self) -> HttpResponse<actix_web::body::BoxBody> {
-        if let Some(msg) = &self.msg {
-            HttpResponse::build(self.status_code()).json(ApiError { error: msg })
-        } else {
-            HttpResponse::build(self.status_code())
-                .content_type("text/plain")
-                .body(self.inner.to_string())
+        if let BabibappError::Internal(inner) = self {
+            log::error!("Internal error: {:?}", inner);
         }
+
+        HttpResponse::build(self.status_code()).json(ErrorResponse {
+            code: self.code(),
+            message: self.message(),
+        })
     }
 }
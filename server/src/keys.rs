@@ -0,0 +1,148 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use openssl::pkey::PKey;
+
+use babibapp_models::token::{Jwk, JwkSet};
+
+use crate::settings::{KeyAlgorithm, KeySettings, TokenSettings};
+
+pub struct VerificationKey {
+    pub kid: Option<String>,
+    pub algorithm: Algorithm,
+    pub key: DecodingKey,
+}
+
+/// Keys for signing and verifying access tokens, loaded once at startup
+pub struct TokenKeys {
+    pub signing_kid: Option<String>,
+    pub signing_algorithm: Algorithm,
+    pub encoding_key: EncodingKey,
+    pub verification_keys: Vec<VerificationKey>,
+    /// Public keys for other services, empty when a shared secret is used
+    pub jwks: JwkSet,
+}
+
+impl TokenKeys {
+    pub fn from_settings(settings: &TokenSettings) -> anyhow::Result<TokenKeys> {
+        if settings.keys.is_empty() {
+            let secret = settings
+                .secret
+                .as_ref()
+                .ok_or_else(|| anyhow!("Either `token.secret` or `token.keys` must be set"))?;
+
+            return Ok(TokenKeys {
+                signing_kid: None,
+                signing_algorithm: Algorithm::HS256,
+                encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+                verification_keys: vec![VerificationKey {
+                    kid: None,
+                    algorithm: Algorithm::HS256,
+                    key: DecodingKey::from_secret(secret.as_bytes()),
+                }],
+                jwks: JwkSet::default(),
+            });
+        }
+
+        let signing_kid = settings
+            .signing_key
+            .as_ref()
+            .ok_or_else(|| anyhow!("`token.signing_key` must be set if `token.keys` are"))?;
+        let signing_settings = settings
+            .keys
+            .iter()
+            .find(|key| key.kid == *signing_kid)
+            .ok_or_else(|| anyhow!("No key configured with kid `{}`", signing_kid))?;
+        let private_key_path = signing_settings
+            .private_key
+            .as_ref()
+            .ok_or_else(|| anyhow!("The signing key `{}` has no private key", signing_kid))?;
+
+        let private_pem = read_pem(private_key_path)?;
+        let encoding_key = match signing_settings.algorithm {
+            KeyAlgorithm::RS256 => EncodingKey::from_rsa_pem(&private_pem)?,
+            KeyAlgorithm::EdDSA => EncodingKey::from_ed_pem(&private_pem)?,
+        };
+
+        let mut verification_keys = Vec::new();
+        let mut jwks = JwkSet::default();
+
+        for key_settings in &settings.keys {
+            let public_pem = read_pem(&key_settings.public_key)?;
+
+            let key = match key_settings.algorithm {
+                KeyAlgorithm::RS256 => DecodingKey::from_rsa_pem(&public_pem)?,
+                KeyAlgorithm::EdDSA => DecodingKey::from_ed_pem(&public_pem)?,
+            };
+
+            verification_keys.push(VerificationKey {
+                kid: Some(key_settings.kid.clone()),
+                algorithm: algorithm(key_settings.algorithm),
+                key,
+            });
+            jwks.keys.push(jwk(key_settings, &public_pem)?);
+        }
+
+        Ok(TokenKeys {
+            signing_kid: Some(signing_kid.clone()),
+            signing_algorithm: algorithm(signing_settings.algorithm),
+            encoding_key,
+            verification_keys,
+            jwks,
+        })
+    }
+
+    /// The key a token with the given `kid` header has to be verified with
+    pub fn verification_key(&self, kid: Option<&str>) -> Option<&VerificationKey> {
+        self.verification_keys
+            .iter()
+            .find(|key| key.kid.as_deref() == kid)
+    }
+}
+
+fn algorithm(algorithm: KeyAlgorithm) -> Algorithm {
+    match algorithm {
+        KeyAlgorithm::RS256 => Algorithm::RS256,
+        KeyAlgorithm::EdDSA => Algorithm::EdDSA,
+    }
+}
+
+fn read_pem(path: &Path) -> anyhow::Result<Vec<u8>> {
+    fs::read(path).context(format!("Couldn't load key file: `{}`", path.display()))
+}
+
+fn jwk(key_settings: &KeySettings, public_pem: &[u8]) -> anyhow::Result<Jwk> {
+    let public_key = PKey::public_key_from_pem(public_pem)?;
+    let encode = |bytes: &[u8]| base64::encode_config(bytes, base64::URL_SAFE_NO_PAD);
+
+    let mut jwk = Jwk {
+        kty: String::new(),
+        kid: key_settings.kid.clone(),
+        alg: String::new(),
+        key_use: "sig".to_string(),
+        n: None,
+        e: None,
+        crv: None,
+        x: None,
+    };
+
+    match key_settings.algorithm {
+        KeyAlgorithm::RS256 => {
+            let rsa = public_key.rsa()?;
+            jwk.kty = "RSA".to_string();
+            jwk.alg = "RS256".to_string();
+            jwk.n = Some(encode(&rsa.n().to_vec()));
+            jwk.e = Some(encode(&rsa.e().to_vec()));
+        }
+        KeyAlgorithm::EdDSA => {
+            jwk.kty = "OKP".to_string();
+            jwk.alg = "EdDSA".to_string();
+            jwk.crv = Some("Ed25519".to_string());
+            jwk.x = Some(encode(&public_key.raw_public_key()?));
+        }
+    }
+
+    Ok(jwk)
+}
@@ -4,7 +4,12 @@ use diesel::PgConnection;
 pub mod auth;
 pub mod db;
 pub mod error;
+pub mod keys;
+pub mod lockout;
+pub mod mail;
+pub mod password;
 pub mod request;
 pub mod settings;
+pub mod totp;
 
 pub type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
@@ -0,0 +1,164 @@
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime};
+
+use actix_web::HttpRequest;
+use diesel::prelude::*;
+use diesel::PgConnection;
+
+use babibapp_models as models;
+use babibapp_schema::schema;
+
+use crate::settings::LoginSettings;
+
+const KIND_EMAIL: &str = "email";
+const KIND_IP: &str = "ip";
+
+/// Address of the client that sent the request
+pub fn client_ip(req: &HttpRequest, settings: &LoginSettings) -> String {
+    if settings.trust_forwarded_for {
+        if let Some(addr) = req.connection_info().realip_remote_addr() {
+            return addr
+                .parse::<SocketAddr>()
+                .map(|addr| addr.ip().to_string())
+                .unwrap_or_else(|_| addr.to_string());
+        }
+    }
+
+    req.peer_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Returns until when logins for the email or from the address are locked, if they are
+pub fn locked_until(
+    conn: &PgConnection,
+    login_email: &str,
+    login_ip: &str,
+) -> QueryResult<Option<SystemTime>> {
+    use schema::login_lockouts::dsl::*;
+
+    let now = SystemTime::now();
+
+    let lockouts = login_lockouts
+        .filter(
+            kind.eq(KIND_EMAIL)
+                .and(value.eq(login_email))
+                .or(kind.eq(KIND_IP).and(value.eq(login_ip))),
+        )
+        .filter(locked_until.gt(now))
+        .select(locked_until)
+        .load::<Option<SystemTime>>(conn)?;
+
+    Ok(lockouts.into_iter().flatten().max())
+}
+
+/// Logs a login attempt and updates the failure counters of the email and the address.
+/// A successful login only resets the counter of the email, so logging into an
+/// own account doesn't lift the lock of an address.
+pub fn record_attempt(
+    conn: &PgConnection,
+    settings: &LoginSettings,
+    login_email: &str,
+    login_ip: &str,
+    login_success: bool,
+) -> QueryResult<()> {
+    conn.transaction(|| {
+        let now = SystemTime::now();
+
+        {
+            use schema::login_attempts::dsl::*;
+
+            let log_limit =
+                now - Duration::from_secs(settings.attempt_log_days as u64 * 24 * 60 * 60);
+            diesel::delete(login_attempts.filter(attempted.lt(log_limit))).execute(conn)?;
+
+            diesel::insert_into(login_attempts)
+                .values(&models::login::NewLoginAttempt {
+                    email: login_email.to_string(),
+                    ip: login_ip.to_string(),
+                    success: login_success,
+                })
+                .execute(conn)?;
+        }
+
+        use schema::login_lockouts::dsl::*;
+
+        let reset_limit = now - Duration::from_secs(settings.max_lockout_seconds as u64);
+        diesel::delete(
+            login_lockouts
+                .filter(last_failure.lt(reset_limit))
+                .filter(locked_until.is_null().or(locked_until.lt(now))),
+        )
+        .execute(conn)?;
+
+        if login_success {
+            diesel::delete(
+                login_lockouts
+                    .filter(kind.eq(KIND_EMAIL))
+                    .filter(value.eq(login_email)),
+            )
+            .execute(conn)?;
+            return Ok(());
+        }
+
+        count_failure(
+            conn,
+            settings,
+            KIND_EMAIL,
+            login_email,
+            settings.max_email_failures,
+        )?;
+        count_failure(conn, settings, KIND_IP, login_ip, settings.max_ip_failures)?;
+
+        Ok(())
+    })
+}
+
+fn count_failure(
+    conn: &PgConnection,
+    settings: &LoginSettings,
+    counted_kind: &str,
+    counted_value: &str,
+    max_failures: i32,
+) -> QueryResult<()> {
+    use schema::login_lockouts::dsl::*;
+
+    diesel::insert_into(login_lockouts)
+        .values(&models::login::NewLoginLockout {
+            kind: counted_kind.to_string(),
+            value: counted_value.to_string(),
+        })
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+
+    let lockout = login_lockouts
+        .filter(kind.eq(counted_kind))
+        .filter(value.eq(counted_value))
+        .for_update()
+        .first::<models::login::LoginLockout>(conn)?;
+
+    let now = SystemTime::now();
+    let new_failures = lockout.failures + 1;
+
+    // every failure beyond the limit doubles the lockout
+    let new_locked_until = if new_failures >= max_failures {
+        let exponent = (new_failures - max_failures).min(30) as u32;
+        let seconds = settings
+            .lockout_seconds
+            .saturating_mul(1 << exponent)
+            .min(settings.max_lockout_seconds);
+        Some(now + Duration::from_secs(seconds.max(0) as u64))
+    } else {
+        None
+    };
+
+    diesel::update(login_lockouts.find(lockout.id))
+        .set((
+            failures.eq(new_failures),
+            last_failure.eq(now),
+            locked_until.eq(new_locked_until),
+        ))
+        .execute(conn)?;
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::Context;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+
+use crate::settings::{MailSettings, MailTransport};
+
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers mails, the transport is chosen in the mail settings
+pub trait Mailer: Send + Sync {
+    fn send(&self, mail: &Mail) -> anyhow::Result<()>;
+}
+
+pub fn mailer_from_settings(settings: &MailSettings) -> anyhow::Result<Arc<dyn Mailer>> {
+    let mailer: Arc<dyn Mailer> = match &settings.transport {
+        MailTransport::Smtp {
+            host,
+            port,
+            username,
+            password,
+        } => {
+            let mut builder = SmtpTransport::starttls_relay(host)?;
+            if let Some(port) = port {
+                builder = builder.port(*port);
+            }
+            if let (Some(username), Some(password)) = (username, password) {
+                builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+            }
+
+            Arc::new(SmtpMailer {
+                transport: builder.build(),
+                from: settings
+                    .from
+                    .parse()
+                    .context(format!("Invalid sender address: `{}`", settings.from))?,
+            })
+        }
+        MailTransport::File { dir } => {
+            fs::create_dir_all(dir).context(format!(
+                "Couldn't create mail directory: `{}`",
+                dir.display()
+            ))?;
+            Arc::new(FileMailer { dir: dir.clone() })
+        }
+        MailTransport::Log => Arc::new(LogMailer),
+        MailTransport::Disabled => Arc::new(DisabledMailer),
+    };
+
+    Ok(mailer)
+}
+
+pub struct SmtpMailer {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, mail: &Mail) -> anyhow::Result<()> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(mail.to.parse()?)
+            .subject(mail.subject.clone())
+            .body(mail.body.clone())?;
+
+        self.transport.send(&message)?;
+        Ok(())
+    }
+}
+
+/// Writes each mail to its own file
+pub struct FileMailer {
+    dir: PathBuf,
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, mail: &Mail) -> anyhow::Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_nanos();
+        let path = self.dir.join(format!("{}.txt", timestamp));
+
+        fs::write(
+            path,
+            format!(
+                "To: {}\nSubject: {}\n\n{}\n",
+                mail.to, mail.subject, mail.body
+            ),
+        )?;
+        Ok(())
+    }
+}
+
+pub struct LogMailer;
+
+impl Mailer for LogMailer {
+    fn send(&self, mail: &Mail) -> anyhow::Result<()> {
+        log::info!(
+            "Mail to {}, subject: {}\n{}",
+            mail.to,
+            mail.subject,
+            mail.body
+        );
+        Ok(())
+    }
+}
+
+/// Used without a configured transport, nothing can be delivered
+pub struct DisabledMailer;
+
+impl Mailer for DisabledMailer {
+    fn send(&self, _: &Mail) -> anyhow::Result<()> {
+        anyhow::bail!("No mail transport is configured")
+    }
+}
@@ -4,6 +4,8 @@ extern crate openssl;
 extern crate diesel_migrations;
 
 use std::env;
+use std::io;
+use std::sync::Arc;
 
 use actix_web::{middleware, web, App, HttpServer};
 use babibapp::request::RequestContext;
@@ -11,24 +13,40 @@ use diesel::r2d2::{ConnectionManager, Pool};
 use diesel::PgConnection;
 
 use babibapp::error::BabibappError;
+use babibapp::keys::TokenKeys;
 use babibapp::settings::Settings;
 use babibapp::DbPool;
-use babibapp::{db, request};
+use babibapp::{db, mail, request};
 use env_logger::Env;
+use pwhash::bcrypt;
 
 embed_migrations!();
 
 #[actix_web::main]
 async fn main() -> actix_web::Result<(), BabibappError> {
     let mut args = env::args().skip(1);
-    let settings_path = args
-        .next()
-        .unwrap_or("/etc/babibapp/server.toml".to_string());
-    let settings = Settings::from_toml(&settings_path).expect("Loading settings file failed");
+    let first_arg = args.next();
+
+    // `babibapp hash-password [password]` prints the hash for `root.password_hash`
+    if first_arg.as_deref() == Some("hash-password") {
+        let password = match args.next() {
+            Some(password) => password,
+            None => {
+                let mut password = String::new();
+                io::stdin().read_line(&mut password)?;
+                password.trim_end_matches(&['\r', '\n'][..]).to_string()
+            }
+        };
+        println!("{}", bcrypt::hash(password)?);
+        return Ok(());
+    }
 
-    // init logging
+    // init logging, before the settings so their deprecation warnings are shown
     env_logger::init_from_env(Env::new().default_filter_or("info"));
 
+    let settings_path = first_arg.unwrap_or("/etc/babibapp/server.toml".to_string());
+    let settings = Settings::from_toml(&settings_path).expect("Loading settings file failed");
+
     // set up database pool
     let db_url = format!(
         "postgres://{}:{}@{}/{}",
@@ -56,9 +74,15 @@ async fn main() -> actix_web::Result<(), BabibappError> {
         settings.http.port
     );
 
+    let mailer = mail::mailer_from_settings(&settings.mail).expect("Setting up the mailer failed");
+
+    let keys = TokenKeys::from_settings(&settings.token).expect("Loading token keys failed");
+
     let context = RequestContext {
         pool,
         settings: settings.clone(),
+        mailer,
+        keys: Arc::new(keys),
     };
 
     // start HTTP server
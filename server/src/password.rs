@@ -0,0 +1,70 @@
+use crate::error::BabibappError;
+use crate::settings::PasswordSettings;
+
+/// Passwords that are refused because they are among the first ones to be guessed
+const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+/// Checks a new password against the password policy
+pub fn check_policy(
+    password: &str,
+    email: &str,
+    settings: &PasswordSettings,
+) -> Result<(), BabibappError> {
+    if password.chars().count() < settings.min_length {
+        return Err(BabibappError::validation_failed(format!(
+            "The password must be at least {} characters long",
+            settings.min_length
+        )));
+    }
+
+    let lowercase = password.to_lowercase();
+
+    if lowercase == email.to_lowercase() {
+        return Err(BabibappError::validation_failed(
+            "The password must not be the email address",
+        ));
+    }
+
+    if COMMON_PASSWORDS.lines().any(|common| common == lowercase) {
+        return Err(BabibappError::validation_failed(
+            "The password is too common",
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> PasswordSettings {
+        PasswordSettings { min_length: 8 }
+    }
+
+    #[test]
+    fn accepts_long_uncommon_password() {
+        assert!(check_policy("correct horse battery", "a@example.org", &settings()).is_ok());
+    }
+
+    #[test]
+    fn rejects_short_password() {
+        assert!(check_policy("k3#xP9q", "a@example.org", &settings()).is_err());
+        assert!(check_policy("k3#xP9qz", "a@example.org", &settings()).is_ok());
+    }
+
+    #[test]
+    fn counts_characters_not_bytes() {
+        assert!(check_policy("ääääääää", "a@example.org", &settings()).is_ok());
+    }
+
+    #[test]
+    fn rejects_email_in_any_case() {
+        assert!(check_policy("Anna@Example.org", "anna@example.org", &settings()).is_err());
+    }
+
+    #[test]
+    fn rejects_common_password_in_any_case() {
+        assert!(check_policy("QwertyUIOP", "a@example.org", &settings()).is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use actix_web::{delete, get, post, web, HttpResponse};
+use diesel::prelude::*;
+
+use babibapp_models as models;
+use babibapp_schema::schema;
+use models::api_key::ApiKeyScope;
+
+use crate::auth::{self, RoleManager};
+use crate::db;
+use crate::error::BabibappError;
+use crate::request::{RequestContext, RequestResult};
+
+/// API keys can carry every permission, so only role managers may manage them
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_all).service(create).service(revoke);
+}
+
+#[get("/get_all")]
+async fn get_all(context: web::Data<RequestContext>, _: RoleManager) -> RequestResult {
+    let keys = db::blocked_access(&context.pool, |conn| {
+        use schema::api_keys::dsl::*;
+
+        api_keys
+            .order(id)
+            .load::<models::api_key::ApiKeyRecord>(conn)
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", keys);
+
+    let keys: Vec<models::api_key::ApiKey> = keys.into_iter().map(|k| k.into()).collect();
+
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+#[post("/create")]
+async fn create(
+    context: web::Data<RequestContext>,
+    RoleManager(claims): RoleManager,
+    form: web::Json<models::api_key::CreateApiKey>,
+) -> RequestResult {
+    if form.name.trim().is_empty() {
+        return Err(BabibappError::validation_failed("An API key needs a name"));
+    }
+
+    if form.scope == ApiKeyScope::Comments && form.student_id.is_none() {
+        return Err(BabibappError::validation_failed(
+            "An API key with the comments scope has to act as a student",
+        ));
+    }
+
+    let key = format!("{}{}", auth::API_KEY_PREFIX, auth::generate_random_token());
+    let new_api_key = models::api_key::NewApiKey {
+        name: form.name.trim().to_string(),
+        key_hash: auth::hash_token(&key),
+        prefix: key[..auth::API_KEY_PREFIX.len() + 6].to_string(),
+        scope: form.scope.to_string(),
+        student_id: form.student_id,
+        created_by: claims.student_id(),
+    };
+
+    let api_key = db::blocked_access(&context.pool, move |conn| {
+        use schema::api_keys::dsl::*;
+
+        diesel::insert_into(api_keys)
+            .values(&new_api_key)
+            .get_result::<models::api_key::ApiKeyRecord>(conn)
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", api_key);
+
+    Ok(HttpResponse::Ok().json(models::api_key::CreatedApiKey {
+        key,
+        api_key: api_key.into(),
+    }))
+}
+
+#[delete("/revoke/{api_key_id}")]
+async fn revoke(
+    context: web::Data<RequestContext>,
+    _: RoleManager,
+    api_key_id: web::Path<i32>,
+) -> RequestResult {
+    let api_key_id = api_key_id.into_inner();
+
+    let api_key = db::blocked_access(&context.pool, move |conn| {
+        use schema::api_keys::dsl::*;
+
+        diesel::update(api_keys.find(api_key_id))
+            .set(revoked.eq(true))
+            .get_result::<models::api_key::ApiKeyRecord>(conn)
+            .optional()
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", api_key);
+
+    if let Some(api_key) = api_key {
+        Ok(HttpResponse::Ok().json(models::api_key::ApiKey::from(api_key)))
+    } else {
+        Err(BabibappError::not_found(format!(
+            "No API key found with api_key_id: {}",
+            api_key_id
+        )))
+    }
+}
@@ -0,0 +1,1614 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::{delete, get, post, put, web, HttpResponse};
+use diesel::expression::BoxableExpression;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+use diesel::PgConnection;
+
+use crate::auth::{AuthenticatedUser, Claims, CommentModerator, Permission, StudentUser};
+use crate::db;
+use crate::error::BabibappError;
+use crate::request::page::{self, PublishedCursor};
+use crate::request::{RequestContext, RequestResult};
+use crate::settings::AnonymityPolicy;
+
+use super::{score, TargetScope, DOWNVOTES, UPVOTES};
+
+use babibapp_models as models;
+use babibapp_schema::schema;
+use models::comment::{
+    ApprovalStatus, CommentSort, CommentTarget, CommentVotes, ModerationAction, ReactionCount,
+};
+use models::page::Page;
+use models::wrappers::{
+    BodyWrapper, CommentFilter, ModerationWrapper, PageQuery, ReasonWrapper, SortQuery, TargetQuery,
+};
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(get)
+        .service(get_all)
+        .service(get_vote)
+        .service(thread)
+        .service(inbox)
+        .service(approve)
+        .service(reject)
+        .service(create)
+        .service(reply)
+        .service(edit)
+        .service(history)
+        .service(do_upvote)
+        .service(do_downvote)
+        .service(do_unvote)
+        .service(reaction_set)
+        .service(get_reactions)
+        .service(react)
+        .service(unreact)
+        .service(delete)
+        .service(report)
+        .service(reports)
+        .service(moderate)
+        .service(moderation_log)
+        .service(reveal_author);
+}
+
+/// Authors and moderators may learn who wrote a comment and still see it once it is hidden
+fn is_privileged(claims: &Claims, comment: &models::comment::Comment) -> bool {
+    claims.id == comment.author_id || claims.has(Permission::ModerateComments)
+}
+
+/// Moderators only learn the author of an anonymous comment through `reveal_author`
+fn knows_author(claims: &Claims, comment: &models::comment::Comment) -> bool {
+    claims.id == comment.author_id
+        || (claims.has(Permission::ModerateComments) && !comment.anonymous)
+}
+
+/// Only students can log in, so the receivers of other targets never act on their comments
+fn is_receiver(student: i32, comment: &models::comment::Comment) -> bool {
+    comment.target.receiver_is_student() && student == comment.receiver_id
+}
+
+/// Comments waiting for approval or rejected by their receiver are only shown to the author,
+/// the receiver and moderators
+fn is_approved_for(claims: &Claims, comment: &models::comment::Comment) -> bool {
+    comment.status == ApprovalStatus::Approved.as_str()
+        || is_receiver(claims.id, comment)
+        || is_privileged(claims, comment)
+}
+
+pub fn is_visible(claims: &Claims, comment: &models::comment::Comment) -> bool {
+    is_approved_for(claims, comment) && (!comment.hidden || is_privileged(claims, comment))
+}
+
+/// `is_visible` for queries of comments by anyone but moderators,
+/// so comments the viewer can't see don't count against page sizes
+pub fn visible_to(
+    viewer: i32,
+) -> Box<dyn BoxableExpression<schema::comments::table, Pg, SqlType = Bool>> {
+    use schema::comments::dsl::*;
+
+    Box::new(
+        status
+            .eq(ApprovalStatus::Approved.as_str())
+            .or(author_id.eq(viewer))
+            .or(target
+                .eq(CommentTarget::Student)
+                .and(receiver_id.eq(viewer)))
+            .and(hidden.eq(false).or(author_id.eq(viewer))),
+    )
+}
+
+/// Comments about oneself and about receivers that can't approve them never wait for approval
+fn initial_status(
+    require_approval: bool,
+    target: CommentTarget,
+    author: i32,
+    receiver: i32,
+) -> ApprovalStatus {
+    if require_approval && target.receiver_is_student() && author != receiver {
+        ApprovalStatus::Pending
+    } else {
+        ApprovalStatus::Approved
+    }
+}
+
+/// Authors and moderators see the whole comment, everyone else doesn't learn the author.
+/// Tombstones of deleted comments are limited for everyone, hidden comments also lose their body.
+/// Anonymous comments are limited for moderators too.
+pub fn comment_view(
+    claims: &Claims,
+    comment: models::comment::Comment,
+) -> models::comment::CommentView {
+    let privileged = is_privileged(claims, &comment);
+
+    if !comment.deleted && knows_author(claims, &comment) {
+        models::comment::CommentView::Full(comment)
+    } else {
+        let limited = models::comment::LimitedViewComment {
+            id: comment.id,
+            target: comment.target,
+            receiver_id: comment.receiver_id,
+            body: if comment.hidden && !privileged {
+                String::new()
+            } else {
+                comment.body
+            },
+            published: comment.published,
+            edited: comment.edited,
+            parent_id: comment.parent_id,
+            depth: comment.depth,
+            deleted: comment.deleted,
+            hidden: comment.hidden,
+            status: comment.status,
+            anonymous: comment.anonymous,
+        };
+        models::comment::CommentView::Limited(limited)
+    }
+}
+
+/// Whether a new comment is anonymous, `requested` is what the author asked for
+fn is_anonymous(policy: AnonymityPolicy, requested: Option<bool>) -> Result<bool, BabibappError> {
+    match (policy, requested) {
+        (AnonymityPolicy::Always, Some(false)) => Err(BabibappError::validation_failed(
+            "Comments are always anonymous",
+        )),
+        (AnonymityPolicy::Never, Some(true)) => Err(BabibappError::validation_failed(
+            "Anonymous comments are disabled",
+        )),
+        (AnonymityPolicy::Always, _) => Ok(true),
+        (AnonymityPolicy::Never, _) => Ok(false),
+        (AnonymityPolicy::Optional, requested) => Ok(requested.unwrap_or(false)),
+    }
+}
+
+/// Loads a comment, the routes of a single target don't find comments of other targets
+fn find_comment(
+    conn: &PgConnection,
+    scope: TargetScope,
+    comment_id: i32,
+) -> QueryResult<models::comment::Comment> {
+    let comment = schema::comments::table
+        .find(comment_id)
+        .first::<models::comment::CommentRecord>(conn)?;
+
+    scope.check(comment.into())
+}
+
+/// Like `find_comment`, but locks the comment until the end of the transaction
+fn lock_comment(
+    conn: &PgConnection,
+    scope: TargetScope,
+    comment_id: i32,
+) -> QueryResult<models::comment::Comment> {
+    let comment = schema::comments::table
+        .find(comment_id)
+        .for_update()
+        .first::<models::comment::CommentRecord>(conn)?;
+
+    scope.check(comment.into())
+}
+
+/// Votes, reactions and reports only go to comments the student can see, others are treated as
+/// missing like deleted ones
+fn check_rateable(
+    claims: &Claims,
+    comment: models::comment::Comment,
+) -> QueryResult<models::comment::Comment> {
+    if comment.deleted || !is_visible(claims, &comment) {
+        return Err(diesel::NotFound);
+    }
+
+    Ok(comment)
+}
+
+/// Loads the replies below a comment, level by level
+fn load_replies(conn: &PgConnection, root: i32) -> QueryResult<Vec<models::comment::Comment>> {
+    use schema::comments::dsl::*;
+
+    let mut replies = Vec::new();
+    let mut level = vec![root];
+
+    while !level.is_empty() {
+        let children = comments
+            .filter(parent_id.eq_any(&level))
+            .order(id)
+            .load::<models::comment::CommentRecord>(conn)?;
+        level = children.iter().map(|c| c.id).collect();
+        replies.extend(children.into_iter().map(models::comment::Comment::from));
+    }
+
+    Ok(replies)
+}
+
+/// Orders a comment and its replies so that every reply follows its parent
+fn flatten_thread(
+    claims: &Claims,
+    root: models::comment::Comment,
+    replies: Vec<models::comment::Comment>,
+) -> Vec<models::comment::ThreadEntry> {
+    let mut children: HashMap<i32, Vec<models::comment::Comment>> = HashMap::new();
+    for comment in replies {
+        if let Some(parent) = comment.parent_id {
+            children.entry(parent).or_default().push(comment);
+        }
+    }
+
+    let root_depth = root.depth;
+    let mut entries = Vec::new();
+    let mut stack = vec![root];
+
+    while let Some(comment) = stack.pop() {
+        // replies below an unapproved reply are left out with it
+        if !is_approved_for(claims, &comment) {
+            continue;
+        }
+        if let Some(mut replies) = children.remove(&comment.id) {
+            // reversed, so the oldest reply is taken from the stack first
+            replies.reverse();
+            stack.extend(replies);
+        }
+        entries.push(models::comment::ThreadEntry {
+            depth: comment.depth - root_depth,
+            comment: comment_view(claims, comment),
+        });
+    }
+
+    entries
+}
+
+#[get("/get/{comment_id}")]
+async fn get(
+    context: web::Data<RequestContext>,
+    AuthenticatedUser(claims): AuthenticatedUser,
+    scope: TargetScope,
+    comment_id: web::Path<i32>,
+) -> RequestResult {
+    let comment_id = comment_id.into_inner();
+
+    let comment = db::blocked_access(&context.pool, move |conn| {
+        let comment_id = scope.resolve(conn, comment_id)?;
+        find_comment(conn, scope, comment_id).optional()
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", comment);
+
+    if let Some(comment) = comment.filter(|c| is_visible(&claims, c)) {
+        return Ok(HttpResponse::Ok().json(comment_view(&claims, comment)));
+    }
+
+    Err(BabibappError::not_found(format!(
+        "No comment found with comment_id: {}",
+        comment_id
+    )))
+}
+
+/// Comments with their votes, one page at a time. `sort` defaults to the newest first.
+#[get("/get_all")]
+async fn get_all(
+    context: web::Data<RequestContext>,
+    AuthenticatedUser(claims): AuthenticatedUser,
+    scope: TargetScope,
+    sort: web::Query<SortQuery>,
+    filter: web::Query<CommentFilter>,
+    page_query: web::Query<PageQuery>,
+) -> RequestResult {
+    let mut filter = filter.into_inner();
+    if scope.0.is_some() {
+        filter.target = scope.0;
+    }
+
+    if filter.receiver_id.is_some() && filter.target.is_none() {
+        return Err(BabibappError::validation_failed(
+            "A receiver_id filter needs a target",
+        ));
+    }
+
+    // the author is only known to the author and moderators
+    if filter.author_id.is_some_and(|author| author != claims.id)
+        && !claims.has(Permission::ModerateComments)
+    {
+        return Err(BabibappError::forbidden(
+            "Only moderators may list the comments of other authors",
+        ));
+    }
+
+    let listing = Listing {
+        published_after: filter_time(filter.published_after, "published_after")?,
+        published_before: filter_time(filter.published_before, "published_before")?,
+        viewer: Some(claims.id).filter(|_| !claims.has(Permission::ModerateComments)),
+        // moderators would otherwise learn the authors of anonymous comments
+        hide_anonymous: filter.author_id.is_some_and(|author| author != claims.id),
+        filter,
+    };
+
+    let limit = page::limit(&page_query)?;
+    let start = match sort.sort {
+        CommentSort::New => PageStart::After(PublishedCursor::from_query(&page_query)?),
+        ranked => PageStart::Offset(ranked, page::cursor::<i64>(&page_query)?),
+    };
+
+    let voter = claims.id;
+
+    let (comments, mut reactions_by_comment) = db::blocked_access(&context.pool, move |conn| {
+        let comments = load_with_votes(conn, &listing, voter, start, limit)?;
+        let ids: Vec<i32> = comments.items.iter().map(|(c, _)| c.id).collect();
+        let reactions_by_comment = load_reactions(conn, ids, voter)?;
+        Ok::<_, BabibappError>((comments, reactions_by_comment))
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", comments);
+
+    let comment_views: Page<models::comment::ScoredComment> =
+        comments.map(|(c, votes)| models::comment::ScoredComment {
+            reactions: reactions_by_comment.remove(&c.id).unwrap_or_default(),
+            comment: comment_view(&claims, c),
+            votes,
+        });
+
+    Ok(HttpResponse::Ok().json(comment_views))
+}
+
+/// Latest timestamp accepted by filters, 9999-12-31T23:59:59Z, the database can't store much later ones
+const MAX_FILTER_SECONDS: u64 = 253_402_300_799;
+
+/// Time of a timestamp filter, `name` is the query parameter it came from
+fn filter_time(seconds: Option<u64>, name: &str) -> Result<Option<SystemTime>, BabibappError> {
+    seconds
+        .map(|seconds| {
+            Some(seconds)
+                .filter(|seconds| *seconds <= MAX_FILTER_SECONDS)
+                .and_then(|seconds| UNIX_EPOCH.checked_add(Duration::from_secs(seconds)))
+                .ok_or_else(|| {
+                    BabibappError::validation_failed(format!("{} is out of range", name))
+                })
+        })
+        .transpose()
+}
+
+/// Comments a `get_all` request may list
+struct Listing {
+    filter: CommentFilter,
+    published_after: Option<SystemTime>,
+    published_before: Option<SystemTime>,
+    /// the requesting user, `None` for moderators, who see every comment
+    viewer: Option<i32>,
+    hide_anonymous: bool,
+}
+
+impl Listing {
+    fn query(&self) -> schema::comments::BoxedQuery<'static, Pg> {
+        use schema::comments::dsl::*;
+
+        let mut query = comments.into_boxed();
+
+        if let Some(viewer) = self.viewer {
+            query = query.filter(visible_to(viewer));
+        }
+        if self.hide_anonymous {
+            query = query.filter(anonymous.eq(false));
+        }
+        if let Some(filter_target) = self.filter.target {
+            query = query.filter(target.eq(filter_target));
+        }
+        if let Some(receiver) = self.filter.receiver_id {
+            query = query.filter(receiver_id.eq(receiver));
+        }
+        if let Some(author) = self.filter.author_id {
+            query = query.filter(author_id.eq(author));
+        }
+        if let Some(after) = self.published_after {
+            query = query.filter(published.gt(after));
+        }
+        if let Some(before) = self.published_before {
+            query = query.filter(published.lt(before));
+        }
+
+        query
+    }
+}
+
+/// Where a page of comments starts, the newest comments are paged by the last comment of the previous page,
+/// ranked ones by the number of comments already handed out, as the ranks change with every vote
+#[derive(Debug, Clone, Copy)]
+enum PageStart {
+    After(Option<PublishedCursor>),
+    Offset(CommentSort, i64),
+}
+
+/// A page of comments with their vote counts and the vote of `voter`, in a single query
+fn load_with_votes(
+    conn: &PgConnection,
+    listing: &Listing,
+    voter: i32,
+    start: PageStart,
+    limit: i64,
+) -> Result<Page<(models::comment::Comment, CommentVotes)>, BabibappError> {
+    use diesel::dsl::sql;
+    use diesel::sql_types::{BigInt, Double, Integer, Nullable};
+    use schema::comments;
+
+    let total = listing.query().count().get_result::<i64>(conn)?;
+
+    let mut query = listing
+        .query()
+        .select((
+            comments::all_columns,
+            sql::<BigInt>(UPVOTES),
+            sql::<BigInt>(DOWNVOTES),
+            sql::<Nullable<Bool>>(
+                "(SELECT upvote FROM comment_votes \
+                 WHERE comment_votes.comment_id = comments.id AND comment_votes.student_id = ",
+            )
+            .bind::<Integer, _>(voter)
+            .sql(")"),
+        ))
+        .limit(limit + 1);
+
+    match start {
+        PageStart::After(Some(cursor)) => {
+            query = query.filter(
+                comments::published
+                    .lt(cursor.published)
+                    .or(comments::published
+                        .eq(cursor.published)
+                        .and(comments::id.lt(cursor.id))),
+            );
+        }
+        PageStart::After(None) => {}
+        PageStart::Offset(_, offset) if offset > total => {
+            return Err(BabibappError::validation_failed("Invalid cursor"));
+        }
+        PageStart::Offset(sort, offset) => {
+            if let Some(score) = score(sort) {
+                query = query.order(sql::<Double>(&score).desc());
+            }
+            query = query.offset(offset);
+        }
+    }
+    query = query.then_order_by((comments::published.desc(), comments::id.desc()));
+
+    let rows = query
+        .load::<(models::comment::CommentRecord, i64, i64, Option<bool>)>(conn)?
+        .into_iter()
+        .map(|(comment, upvotes, downvotes, own_vote)| {
+            (
+                models::comment::Comment::from(comment),
+                CommentVotes::new(upvotes, downvotes, own_vote),
+            )
+        })
+        .collect();
+
+    Ok(match start {
+        PageStart::After(_) => page::keyset_page(rows, limit, total, |(c, _)| PublishedCursor {
+            published: c.published,
+            id: c.id,
+        }),
+        PageStart::Offset(_, offset) => page::offset_page(rows, offset, limit, total),
+    })
+}
+
+/// Reaction counts of comments and whether `reactor` gave them, most frequent reaction first.
+/// Comments without reactions are missing.
+fn load_reactions(
+    conn: &PgConnection,
+    reacted_comments: Vec<i32>,
+    reactor: i32,
+) -> QueryResult<HashMap<i32, Vec<ReactionCount>>> {
+    use diesel::dsl::sql;
+    use diesel::query_dsl::GroupByDsl;
+    use diesel::sql_types::{BigInt, Bool, Integer};
+    use schema::comment_reactions::dsl::*;
+
+    let rows = comment_reactions
+        .filter(comment_id.eq_any(reacted_comments))
+        .group_by((comment_id, reaction))
+        .select((
+            comment_id,
+            reaction,
+            sql::<BigInt>("COUNT(*)"),
+            sql::<Bool>("BOOL_OR(student_id = ")
+                .bind::<Integer, _>(reactor)
+                .sql(")"),
+        ))
+        .load::<(i32, String, i64, bool)>(conn)?;
+
+    let mut reactions_by_comment: HashMap<i32, Vec<ReactionCount>> = HashMap::new();
+    for (reacted_comment, given_reaction, count, own) in rows {
+        reactions_by_comment
+            .entry(reacted_comment)
+            .or_default()
+            .push(ReactionCount {
+                reaction: given_reaction,
+                count,
+                own,
+            });
+    }
+    for counts in reactions_by_comment.values_mut() {
+        counts.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.reaction.cmp(&b.reaction))
+        });
+    }
+
+    Ok(reactions_by_comment)
+}
+
+fn count_reactions(
+    conn: &PgConnection,
+    reacted_comment: i32,
+    reactor: i32,
+) -> QueryResult<Vec<ReactionCount>> {
+    let mut reactions_by_comment = load_reactions(conn, vec![reacted_comment], reactor)?;
+    Ok(reactions_by_comment
+        .remove(&reacted_comment)
+        .unwrap_or_default())
+}
+
+/// A comment followed by all of its replies, each reply right after its parent
+#[get("/thread/{comment_id}")]
+async fn thread(
+    context: web::Data<RequestContext>,
+    AuthenticatedUser(claims): AuthenticatedUser,
+    scope: TargetScope,
+    comment_id: web::Path<i32>,
+) -> RequestResult {
+    let comment_id = comment_id.into_inner();
+
+    let thread = db::blocked_access(&context.pool, move |conn| {
+        let comment_id = scope.resolve(conn, comment_id)?;
+        let root = match find_comment(conn, scope, comment_id).optional()? {
+            Some(root) => root,
+            None => return Ok(None),
+        };
+
+        let replies = load_replies(conn, root.id)?;
+        QueryResult::Ok(Some((root, replies)))
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", thread);
+
+    if let Some((root, replies)) = thread.filter(|(root, _)| is_approved_for(&claims, root)) {
+        return Ok(HttpResponse::Ok().json(flatten_thread(&claims, root, replies)));
+    }
+
+    Err(BabibappError::not_found(format!(
+        "No comment found with comment_id: {}",
+        comment_id
+    )))
+}
+
+/// Comments about the student that wait for their approval
+#[get("/inbox")]
+async fn inbox(
+    context: web::Data<RequestContext>,
+    StudentUser(claims): StudentUser,
+    scope: TargetScope,
+) -> RequestResult {
+    let comments = db::blocked_access(&context.pool, move |conn| {
+        use schema::comments::dsl::*;
+
+        let student_targets: Vec<CommentTarget> = CommentTarget::ALL
+            .into_iter()
+            .filter(|t| t.receiver_is_student() && scope.0.is_none_or(|s| s == *t))
+            .collect();
+
+        comments
+            .filter(target.eq_any(student_targets))
+            .filter(receiver_id.eq(claims.id))
+            .filter(status.eq(ApprovalStatus::Pending.as_str()))
+            .filter(deleted.eq(false))
+            .order(id)
+            .load::<models::comment::CommentRecord>(conn)
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", comments);
+
+    let comment_views: Vec<models::comment::CommentView> = comments
+        .into_iter()
+        .map(|c| comment_view(&claims, c.into()))
+        .collect();
+
+    Ok(HttpResponse::Ok().json(comment_views))
+}
+
+#[post("/approve/{comment_id}")]
+async fn approve(
+    context: web::Data<RequestContext>,
+    StudentUser(claims): StudentUser,
+    scope: TargetScope,
+    comment_id: web::Path<i32>,
+) -> RequestResult {
+    review(
+        context,
+        claims,
+        scope,
+        comment_id.into_inner(),
+        ApprovalStatus::Approved,
+    )
+    .await
+}
+
+#[post("/reject/{comment_id}")]
+async fn reject(
+    context: web::Data<RequestContext>,
+    StudentUser(claims): StudentUser,
+    scope: TargetScope,
+    comment_id: web::Path<i32>,
+) -> RequestResult {
+    review(
+        context,
+        claims,
+        scope,
+        comment_id.into_inner(),
+        ApprovalStatus::Rejected,
+    )
+    .await
+}
+
+/// The receiver decides whether a comment about them is shown, they may change their mind later
+async fn review(
+    context: web::Data<RequestContext>,
+    claims: Claims,
+    scope: TargetScope,
+    reviewed_comment: i32,
+    new_status: ApprovalStatus,
+) -> RequestResult {
+    let reviewer = claims.id;
+
+    let comment = db::blocked_access(&context.pool, move |conn| {
+        let reviewed_comment = scope.resolve(conn, reviewed_comment)?;
+        conn.transaction::<_, BabibappError, _>(|| {
+            let comment = lock_comment(conn, scope, reviewed_comment)?;
+
+            if !is_receiver(reviewer, &comment) {
+                return Err(BabibappError::forbidden(
+                    "Only the receiver may approve or reject a comment",
+                ));
+            }
+
+            if comment.deleted {
+                return Err(BabibappError::conflict(
+                    "A deleted comment can't be approved or rejected",
+                ));
+            }
+
+            use schema::comments::dsl::*;
+
+            let comment = diesel::update(comments.find(reviewed_comment))
+                .set(status.eq(new_status.as_str()))
+                .get_result::<models::comment::CommentRecord>(conn)?;
+
+            Ok(comment.into())
+        })
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", comment);
+
+    Ok(HttpResponse::Ok().json(comment_view(&claims, comment)))
+}
+
+#[get("/get_vote/{comment_id}")]
+async fn get_vote(
+    context: web::Data<RequestContext>,
+    AuthenticatedUser(claims): AuthenticatedUser,
+    scope: TargetScope,
+    comment_id: web::Path<i32>,
+) -> RequestResult {
+    let query_comment_id = comment_id.into_inner();
+
+    let votes = db::blocked_access(&context.pool, move |conn| {
+        let query_comment_id = scope.resolve(conn, query_comment_id)?;
+        check_rateable(&claims, find_comment(conn, scope, query_comment_id)?)?;
+        count_votes(conn, query_comment_id, claims.id)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(votes.score))
+}
+
+/// Vote counts of a single comment and the vote of `voter`
+fn count_votes(conn: &PgConnection, voted_comment: i32, voter: i32) -> QueryResult<CommentVotes> {
+    use diesel::dsl::sql;
+    use diesel::sql_types::{BigInt, Bool, Integer, Nullable};
+    use schema::comment_votes::dsl::*;
+
+    let (upvotes, downvotes, own_vote) = comment_votes
+        .filter(comment_id.eq(voted_comment))
+        .select((
+            sql::<BigInt>("COUNT(*) FILTER (WHERE upvote)"),
+            sql::<BigInt>("COUNT(*) FILTER (WHERE NOT upvote)"),
+            sql::<Nullable<Bool>>("BOOL_OR(upvote) FILTER (WHERE student_id = ")
+                .bind::<Integer, _>(voter)
+                .sql(")"),
+        ))
+        .get_result::<(i64, i64, Option<bool>)>(conn)?;
+
+    Ok(CommentVotes::new(upvotes, downvotes, own_vote))
+}
+
+#[post("/create")]
+async fn create(
+    context: web::Data<RequestContext>,
+    StudentUser(claims): StudentUser,
+    scope: TargetScope,
+    form: web::Json<models::comment::CreateComment>,
+) -> RequestResult {
+    let require_approval = context.settings.comment.require_approval;
+    let comment_anonymous = is_anonymous(context.settings.comment.anonymity, form.anonymous)?;
+
+    // the routes of a single target imply it
+    let comment_target = scope
+        .0
+        .or(form.target)
+        .ok_or_else(|| BabibappError::validation_failed("A comment needs a target"))?;
+
+    let comment = db::blocked_access(&context.pool, move |conn| {
+        use schema::comments::dsl::*;
+
+        let (receiver_student, receiver_teacher) =
+            comment_target.receiver_columns(form.receiver_id);
+
+        let new_comment = models::comment::NewComment {
+            target: comment_target,
+            student_id: receiver_student,
+            teacher_id: receiver_teacher,
+            author_id: claims.id,
+            body: form.body.clone(),
+            published: Some(SystemTime::now()),
+            parent_id: None,
+            depth: 0,
+            status: initial_status(
+                require_approval,
+                comment_target,
+                claims.id,
+                form.receiver_id,
+            )
+            .to_string(),
+            anonymous: comment_anonymous,
+        };
+
+        diesel::insert_into(comments)
+            .values(&new_comment)
+            .get_result::<models::comment::CommentRecord>(conn)
+            .map(models::comment::Comment::from)
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", comment);
+
+    Ok(HttpResponse::Ok().json(comment))
+}
+
+/// Replies to a comment, the reply is about the same receiver
+#[post("/reply/{comment_id}")]
+async fn reply(
+    context: web::Data<RequestContext>,
+    StudentUser(claims): StudentUser,
+    scope: TargetScope,
+    comment_id: web::Path<i32>,
+    form: web::Json<models::comment::CreateReply>,
+) -> RequestResult {
+    let parent_comment_id = comment_id.into_inner();
+    let max_depth = context.settings.comment.max_depth;
+    let require_approval = context.settings.comment.require_approval;
+    let reply_anonymous = is_anonymous(context.settings.comment.anonymity, form.anonymous)?;
+
+    let comment = db::blocked_access(&context.pool, move |conn| {
+        let parent_comment_id = scope.resolve(conn, parent_comment_id)?;
+        let parent = find_comment(conn, scope, parent_comment_id)?;
+
+        if parent.deleted {
+            return Err(BabibappError::conflict(
+                "A deleted comment can't be replied to",
+            ));
+        }
+
+        if parent.hidden {
+            return Err(BabibappError::conflict(
+                "A hidden comment can't be replied to",
+            ));
+        }
+
+        if parent.status != ApprovalStatus::Approved.as_str() {
+            return Err(BabibappError::conflict(
+                "Only approved comments can be replied to",
+            ));
+        }
+
+        if parent.depth >= max_depth {
+            return Err(BabibappError::validation_failed(format!(
+                "Replies can't be nested deeper than {} levels",
+                max_depth
+            )));
+        }
+
+        let (receiver_student, receiver_teacher) =
+            parent.target.receiver_columns(parent.receiver_id);
+
+        let new_comment = models::comment::NewComment {
+            target: parent.target,
+            student_id: receiver_student,
+            teacher_id: receiver_teacher,
+            author_id: claims.id,
+            body: form.body.clone(),
+            published: Some(SystemTime::now()),
+            parent_id: Some(parent.id),
+            depth: parent.depth + 1,
+            status: initial_status(
+                require_approval,
+                parent.target,
+                claims.id,
+                parent.receiver_id,
+            )
+            .to_string(),
+            anonymous: reply_anonymous,
+        };
+
+        let comment = diesel::insert_into(schema::comments::table)
+            .values(&new_comment)
+            .get_result::<models::comment::CommentRecord>(conn)?;
+
+        Ok(models::comment::Comment::from(comment))
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", comment);
+
+    Ok(HttpResponse::Ok().json(comment))
+}
+
+#[put("/edit/{comment_id}")]
+async fn edit(
+    context: web::Data<RequestContext>,
+    AuthenticatedUser(claims): AuthenticatedUser,
+    scope: TargetScope,
+    comment_id: web::Path<i32>,
+    form: web::Json<BodyWrapper>,
+) -> RequestResult {
+    let comment_id = comment_id.into_inner();
+    let require_approval = context.settings.comment.require_approval;
+    let editor = claims.id;
+    let editor_student = claims.student_id();
+    let moderator = claims.has(Permission::ModerateComments);
+
+    let comment = db::blocked_access(&context.pool, move |conn| {
+        let comment_id = scope.resolve(conn, comment_id)?;
+        conn.transaction::<_, BabibappError, _>(|| {
+            let comment = lock_comment(conn, scope, comment_id)?;
+
+            if !moderator && comment.author_id != editor {
+                return Err(BabibappError::forbidden(
+                    "Only the author or a moderator may edit a comment",
+                ));
+            }
+
+            if comment.deleted {
+                return Err(BabibappError::conflict("A deleted comment can't be edited"));
+            }
+
+            // the replaced body is kept, so votes can't be collected for something else
+            diesel::insert_into(schema::comment_revisions::table)
+                .values(&models::comment::NewCommentRevision {
+                    comment_id: comment.id,
+                    editor_id: editor_student,
+                    body: comment.body,
+                    written: comment.edited.unwrap_or(comment.published),
+                })
+                .execute(conn)?;
+
+            // the receiver has to approve the new body again
+            let new_status = if editor == comment.author_id
+                && initial_status(
+                    require_approval,
+                    comment.target,
+                    comment.author_id,
+                    comment.receiver_id,
+                ) == ApprovalStatus::Pending
+            {
+                ApprovalStatus::Pending.to_string()
+            } else {
+                comment.status
+            };
+
+            use schema::comments::dsl::*;
+
+            let comment = diesel::update(comments.find(comment_id))
+                .set((
+                    body.eq(&form.body),
+                    edited.eq(SystemTime::now()),
+                    status.eq(new_status),
+                ))
+                .get_result::<models::comment::CommentRecord>(conn)?;
+
+            Ok(models::comment::Comment::from(comment))
+        })
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", comment);
+
+    Ok(HttpResponse::Ok().json(comment_view(&claims, comment)))
+}
+
+/// Previous bodies of a comment, oldest first
+#[get("/history/{comment_id}")]
+async fn history(
+    context: web::Data<RequestContext>,
+    AuthenticatedUser(claims): AuthenticatedUser,
+    scope: TargetScope,
+    comment_id: web::Path<i32>,
+) -> RequestResult {
+    let query_comment_id = comment_id.into_inner();
+
+    let revisions = db::blocked_access(&context.pool, move |conn| {
+        let query_comment_id = scope.resolve(conn, query_comment_id)?;
+        let comment = find_comment(conn, scope, query_comment_id)?;
+
+        if !claims.has(Permission::ModerateComments) && comment.author_id != claims.id {
+            return Err(BabibappError::forbidden(
+                "Only the author or a moderator may see the history of a comment",
+            ));
+        }
+
+        use schema::comment_revisions::dsl::*;
+
+        let mut list = comment_revisions
+            .filter(comment_id.eq(query_comment_id))
+            .order(id)
+            .load::<models::comment::CommentRevision>(conn)?;
+
+        // the edits of the author would give them away
+        if !knows_author(&claims, &comment) {
+            for revision in &mut list {
+                if revision.editor_id == Some(comment.author_id) {
+                    revision.editor_id = None;
+                }
+            }
+        }
+
+        Ok(list)
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", revisions);
+
+    Ok(HttpResponse::Ok().json(revisions))
+}
+
+#[post("/upvote/{comment_id}")]
+async fn do_upvote(
+    context: web::Data<RequestContext>,
+    StudentUser(claims): StudentUser,
+    scope: TargetScope,
+    comment_id: web::Path<i32>,
+) -> RequestResult {
+    vote(context, claims, scope, comment_id.into_inner(), true).await
+}
+
+#[post("/downvote/{comment_id}")]
+async fn do_downvote(
+    context: web::Data<RequestContext>,
+    StudentUser(claims): StudentUser,
+    scope: TargetScope,
+    comment_id: web::Path<i32>,
+) -> RequestResult {
+    vote(context, claims, scope, comment_id.into_inner(), false).await
+}
+
+/// Votes on a comment and answers with its new vote counts, a second vote replaces the first
+async fn vote(
+    context: web::Data<RequestContext>,
+    claims: Claims,
+    scope: TargetScope,
+    voted_comment: i32,
+    new_upvote: bool,
+) -> RequestResult {
+    let allow_self_votes = context.settings.comment.allow_self_votes;
+
+    let votes = db::blocked_access(&context.pool, move |conn| {
+        let voted_comment = scope.resolve(conn, voted_comment)?;
+        conn.transaction::<_, BabibappError, _>(|| {
+            // keeps the comment from being deleted before the vote is in, without blocking other votes
+            let comment = schema::comments::table
+                .find(voted_comment)
+                .for_key_share()
+                .first::<models::comment::CommentRecord>(conn)?;
+            let comment = check_rateable(&claims, scope.check(comment.into())?)?;
+
+            if !allow_self_votes && comment.author_id == claims.id {
+                return Err(BabibappError::forbidden(
+                    "Voting on your own comments is disabled",
+                ));
+            }
+
+            use schema::comment_votes::dsl::*;
+
+            diesel::insert_into(comment_votes)
+                .values(&models::comment::NewCommentVote {
+                    comment_id: voted_comment,
+                    student_id: claims.id,
+                    upvote: new_upvote,
+                })
+                .on_conflict((comment_id, student_id))
+                .do_update()
+                .set(upvote.eq(new_upvote))
+                .execute(conn)?;
+
+            Ok(count_votes(conn, voted_comment, claims.id)?)
+        })
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", votes);
+
+    Ok(HttpResponse::Ok().json(votes))
+}
+
+#[delete("/unvote/{comment_id}")]
+async fn do_unvote(
+    context: web::Data<RequestContext>,
+    StudentUser(claims): StudentUser,
+    scope: TargetScope,
+    comment_id: web::Path<i32>,
+) -> RequestResult {
+    let query_comment_id = comment_id.into_inner();
+
+    let votes = db::blocked_access(&context.pool, move |conn| {
+        let query_comment_id = scope.resolve(conn, query_comment_id)?;
+        check_rateable(&claims, find_comment(conn, scope, query_comment_id)?)?;
+
+        use schema::comment_votes::dsl::*;
+
+        let removed = diesel::delete(
+            comment_votes
+                .filter(student_id.eq(claims.id))
+                .filter(comment_id.eq(query_comment_id)),
+        )
+        .execute(conn)?;
+
+        if removed == 0 {
+            return Err(BabibappError::not_found(format!(
+                "No vote on comment: {}",
+                query_comment_id
+            )));
+        }
+
+        Ok(count_votes(conn, query_comment_id, claims.id)?)
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", votes);
+
+    Ok(HttpResponse::Ok().json(votes))
+}
+
+/// Reactions students may give, in the configured order
+#[get("/reaction_set")]
+async fn reaction_set(context: web::Data<RequestContext>, _: AuthenticatedUser) -> RequestResult {
+    Ok(HttpResponse::Ok().json(&context.settings.comment.reactions))
+}
+
+#[get("/get_reactions/{comment_id}")]
+async fn get_reactions(
+    context: web::Data<RequestContext>,
+    AuthenticatedUser(claims): AuthenticatedUser,
+    scope: TargetScope,
+    comment_id: web::Path<i32>,
+) -> RequestResult {
+    let query_comment_id = comment_id.into_inner();
+
+    let reactions = db::blocked_access(&context.pool, move |conn| {
+        let query_comment_id = scope.resolve(conn, query_comment_id)?;
+        check_rateable(&claims, find_comment(conn, scope, query_comment_id)?)?;
+        count_reactions(conn, query_comment_id, claims.id)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(reactions))
+}
+
+/// Reacts to a comment and answers with its new reaction counts, reacting twice the same way
+/// changes nothing
+#[post("/react/{comment_id}/{reaction}")]
+async fn react(
+    context: web::Data<RequestContext>,
+    StudentUser(claims): StudentUser,
+    scope: TargetScope,
+    path: web::Path<(i32, String)>,
+) -> RequestResult {
+    let (reacted_comment, new_reaction) = path.into_inner();
+
+    if !context.settings.comment.reactions.contains(&new_reaction) {
+        return Err(BabibappError::validation_failed(format!(
+            "Unknown reaction: {}",
+            new_reaction
+        )));
+    }
+
+    let reactions = db::blocked_access(&context.pool, move |conn| {
+        let reacted_comment = scope.resolve(conn, reacted_comment)?;
+        conn.transaction::<_, BabibappError, _>(|| {
+            // keeps the comment from being deleted before the reaction is in
+            let comment = schema::comments::table
+                .find(reacted_comment)
+                .for_key_share()
+                .first::<models::comment::CommentRecord>(conn)?;
+            check_rateable(&claims, scope.check(comment.into())?)?;
+
+            diesel::insert_into(schema::comment_reactions::table)
+                .values(&models::comment::NewCommentReaction {
+                    comment_id: reacted_comment,
+                    student_id: claims.id,
+                    reaction: new_reaction,
+                })
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            Ok(count_reactions(conn, reacted_comment, claims.id)?)
+        })
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", reactions);
+
+    Ok(HttpResponse::Ok().json(reactions))
+}
+
+#[delete("/unreact/{comment_id}/{reaction}")]
+async fn unreact(
+    context: web::Data<RequestContext>,
+    StudentUser(claims): StudentUser,
+    scope: TargetScope,
+    path: web::Path<(i32, String)>,
+) -> RequestResult {
+    let (query_comment_id, old_reaction) = path.into_inner();
+
+    let reactions = db::blocked_access(&context.pool, move |conn| {
+        let query_comment_id = scope.resolve(conn, query_comment_id)?;
+        check_rateable(&claims, find_comment(conn, scope, query_comment_id)?)?;
+
+        use schema::comment_reactions::dsl::*;
+
+        let removed = diesel::delete(
+            comment_reactions
+                .filter(student_id.eq(claims.id))
+                .filter(comment_id.eq(query_comment_id))
+                .filter(reaction.eq(&old_reaction)),
+        )
+        .execute(conn)?;
+
+        if removed == 0 {
+            return Err(BabibappError::not_found(format!(
+                "No reaction {} on comment: {}",
+                old_reaction, query_comment_id
+            )));
+        }
+
+        Ok(count_reactions(conn, query_comment_id, claims.id)?)
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", reactions);
+
+    Ok(HttpResponse::Ok().json(reactions))
+}
+
+#[delete("/delete/{comment_id}")]
+async fn delete(
+    context: web::Data<RequestContext>,
+    AuthenticatedUser(claims): AuthenticatedUser,
+    scope: TargetScope,
+    comment_id: web::Path<i32>,
+) -> RequestResult {
+    let comment_id = comment_id.into_inner();
+
+    let deleter = claims.id;
+    let deleter_actor = ModerationActor::of(&claims);
+    let moderator = claims.has(Permission::ModerateComments);
+
+    let comment = db::blocked_access(&context.pool, move |conn| {
+        let comment_id = scope.resolve(conn, comment_id)?;
+        conn.transaction::<_, BabibappError, _>(|| {
+            let comment = lock_comment(conn, scope, comment_id)?;
+
+            if !moderator && comment.author_id != deleter {
+                return Err(BabibappError::forbidden(
+                    "Only the author or a moderator may delete a comment",
+                ));
+            }
+
+            if comment.author_id != deleter {
+                record_moderation(conn, comment_id, deleter_actor, ModerationAction::Delete)?;
+            }
+
+            Ok(delete_comment(conn, comment_id)?)
+        })
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", comment);
+
+    Ok(HttpResponse::Ok().json(comment_view(&claims, comment)))
+}
+
+/// Deletes a comment, or turns it into a tombstone as long as it has replies
+fn delete_comment(conn: &PgConnection, comment_id: i32) -> QueryResult<models::comment::Comment> {
+    use diesel::dsl::exists;
+    use schema::comments::dsl::*;
+
+    let has_replies = diesel::select(exists(comments.filter(parent_id.eq(comment_id))))
+        .get_result::<bool>(conn)?;
+
+    // the replies stay readable below a tombstone without the deleted body
+    if has_replies {
+        diesel::delete(
+            schema::comment_revisions::table
+                .filter(schema::comment_revisions::comment_id.eq(comment_id)),
+        )
+        .execute(conn)?;
+
+        return diesel::update(comments.find(comment_id))
+            .set((body.eq(""), deleted.eq(true)))
+            .get_result::<models::comment::CommentRecord>(conn)
+            .map(models::comment::Comment::from);
+    }
+
+    let comment = diesel::delete(comments.find(comment_id))
+        .get_result::<models::comment::CommentRecord>(conn)?;
+
+    // tombstones are only kept as long as they have replies
+    let mut next_parent = comment.parent_id;
+    while let Some(parent) = next_parent {
+        let has_replies = diesel::select(exists(comments.filter(parent_id.eq(parent))))
+            .get_result::<bool>(conn)?;
+        if has_replies {
+            break;
+        }
+
+        let removed = diesel::delete(comments.find(parent).filter(deleted.eq(true)))
+            .get_result::<models::comment::CommentRecord>(conn)
+            .optional()?;
+
+        next_parent = removed.and_then(|removed| removed.parent_id);
+    }
+
+    Ok(comment.into())
+}
+
+/// Who took a moderation action, nobody for automatic ones
+#[derive(Debug, Clone, Copy, Default)]
+struct ModerationActor {
+    student_id: Option<i32>,
+    api_key_id: Option<i32>,
+    root: bool,
+}
+
+impl ModerationActor {
+    fn of(claims: &Claims) -> Self {
+        ModerationActor {
+            student_id: claims.student_id(),
+            api_key_id: claims.api_key.map(|api_key| api_key.id),
+            root: claims.is_root(),
+        }
+    }
+
+    /// Whether the moderation log can tell who took the action
+    fn is_known(&self) -> bool {
+        self.student_id.is_some() || self.api_key_id.is_some() || self.root
+    }
+}
+
+fn record_moderation(
+    conn: &PgConnection,
+    moderated_comment: i32,
+    actor: ModerationActor,
+    moderation_action: ModerationAction,
+) -> QueryResult<()> {
+    diesel::insert_into(schema::comment_moderations::table)
+        .values(&models::comment::NewCommentModeration {
+            comment_id: moderated_comment,
+            moderator_id: actor.student_id,
+            action: moderation_action.to_string(),
+            api_key_id: actor.api_key_id,
+            root: actor.root,
+        })
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Reports a comment to the moderators, enough open reports hide it until a moderator looked at it
+#[post("/report/{comment_id}")]
+async fn report(
+    context: web::Data<RequestContext>,
+    StudentUser(claims): StudentUser,
+    scope: TargetScope,
+    comment_id: web::Path<i32>,
+    form: web::Json<ReasonWrapper>,
+) -> RequestResult {
+    let reported_comment = comment_id.into_inner();
+    let auto_hide_reports = context.settings.comment.auto_hide_reports;
+
+    if form.reason.trim().is_empty() {
+        return Err(BabibappError::validation_failed("A report needs a reason"));
+    }
+
+    let new_report = db::blocked_access(&context.pool, move |conn| {
+        let reported_comment = scope.resolve(conn, reported_comment)?;
+        conn.transaction::<_, BabibappError, _>(|| {
+            let comment = check_rateable(&claims, lock_comment(conn, scope, reported_comment)?)?;
+
+            use diesel::dsl::{count_star, exists};
+            use schema::comment_reports::dsl::*;
+
+            let open_reports = comment_reports
+                .filter(comment_id.eq(reported_comment))
+                .filter(resolved.eq(false));
+
+            let already_reported =
+                diesel::select(exists(open_reports.filter(reporter_id.eq(claims.id))))
+                    .get_result::<bool>(conn)?;
+            if already_reported {
+                return Err(BabibappError::conflict("You already reported this comment"));
+            }
+
+            let new_report = diesel::insert_into(comment_reports)
+                .values(&models::comment::NewCommentReport {
+                    comment_id: reported_comment,
+                    reporter_id: claims.id,
+                    reason: form.reason.clone(),
+                })
+                .get_result::<models::comment::CommentReport>(conn)?;
+
+            if auto_hide_reports > 0 && !comment.hidden {
+                let count = open_reports.select(count_star()).get_result::<i64>(conn)?;
+
+                if count >= auto_hide_reports {
+                    diesel::update(schema::comments::table.find(reported_comment))
+                        .set(schema::comments::hidden.eq(true))
+                        .execute(conn)?;
+                    record_moderation(
+                        conn,
+                        reported_comment,
+                        ModerationActor::default(),
+                        ModerationAction::AutoHide,
+                    )?;
+                }
+            }
+
+            Ok(new_report)
+        })
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", new_report);
+
+    Ok(HttpResponse::Ok().json(new_report))
+}
+
+/// The moderation queue, comments with the most open reports first
+#[get("/reports")]
+async fn reports(
+    context: web::Data<RequestContext>,
+    CommentModerator(claims): CommentModerator,
+    scope: TargetScope,
+    query: web::Query<TargetQuery>,
+) -> RequestResult {
+    let queue_target = scope.0.or(query.target);
+
+    let queue = db::blocked_access(&context.pool, move |conn| {
+        use schema::comment_reports::dsl::*;
+
+        let open_reports = comment_reports
+            .filter(resolved.eq(false))
+            .order(id)
+            .load::<models::comment::CommentReport>(conn)?;
+
+        let mut reports_by_comment: HashMap<i32, Vec<models::comment::CommentReport>> =
+            HashMap::new();
+        for open_report in open_reports {
+            reports_by_comment
+                .entry(open_report.comment_id)
+                .or_default()
+                .push(open_report);
+        }
+
+        let mut reported_comments = schema::comments::table
+            .filter(schema::comments::id.eq_any(reports_by_comment.keys().collect::<Vec<_>>()))
+            .into_boxed();
+        if let Some(queue_target) = queue_target {
+            reported_comments = reported_comments.filter(schema::comments::target.eq(queue_target));
+        }
+        let reported_comments = reported_comments.load::<models::comment::CommentRecord>(conn)?;
+
+        let mut queue: Vec<(
+            models::comment::Comment,
+            Vec<models::comment::CommentReport>,
+        )> = reported_comments
+            .into_iter()
+            .filter_map(|comment| {
+                let open_reports = reports_by_comment.remove(&comment.id)?;
+                Some((comment.into(), open_reports))
+            })
+            .collect();
+
+        // equally reported comments stay in the order they were first reported
+        queue.sort_by_key(|(_, reports)| (std::cmp::Reverse(reports.len()), reports[0].id));
+
+        QueryResult::Ok(queue)
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", queue);
+
+    let queue: Vec<models::comment::ReportedComment> = queue
+        .into_iter()
+        .map(|(comment, reports)| models::comment::ReportedComment {
+            comment: comment_view(&claims, comment),
+            reports,
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(queue))
+}
+
+/// Acts on a reported comment, which closes its open reports
+#[post("/moderate/{comment_id}")]
+async fn moderate(
+    context: web::Data<RequestContext>,
+    CommentModerator(claims): CommentModerator,
+    scope: TargetScope,
+    comment_id: web::Path<i32>,
+    form: web::Json<ModerationWrapper>,
+) -> RequestResult {
+    let moderated_comment = comment_id.into_inner();
+    let moderation_action = form.action;
+
+    if moderation_action == ModerationAction::AutoHide {
+        return Err(BabibappError::validation_failed(
+            "Comments are only hidden automatically because of reports",
+        ));
+    }
+
+    if moderation_action == ModerationAction::RevealAuthor {
+        return Err(BabibappError::validation_failed(
+            "Authors are only revealed through /reveal_author",
+        ));
+    }
+
+    let moderator = ModerationActor::of(&claims);
+
+    let comment = db::blocked_access(&context.pool, move |conn| {
+        let moderated_comment = scope.resolve(conn, moderated_comment)?;
+        conn.transaction::<_, BabibappError, _>(|| {
+            let comment = lock_comment(conn, scope, moderated_comment)?;
+
+            {
+                use schema::comment_reports::dsl::*;
+
+                diesel::update(
+                    comment_reports
+                        .filter(comment_id.eq(moderated_comment))
+                        .filter(resolved.eq(false)),
+                )
+                .set(resolved.eq(true))
+                .execute(conn)?;
+            }
+
+            record_moderation(conn, moderated_comment, moderator, moderation_action)?;
+
+            use schema::comments::dsl::*;
+
+            let comment = match moderation_action {
+                ModerationAction::Hide | ModerationAction::Restore => {
+                    diesel::update(comments.find(moderated_comment))
+                        .set(hidden.eq(moderation_action == ModerationAction::Hide))
+                        .get_result::<models::comment::CommentRecord>(conn)?
+                        .into()
+                }
+                ModerationAction::Delete => delete_comment(conn, moderated_comment)?,
+                ModerationAction::Dismiss
+                | ModerationAction::AutoHide
+                | ModerationAction::RevealAuthor => comment,
+            };
+
+            Ok(comment)
+        })
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", comment);
+
+    Ok(HttpResponse::Ok().json(comment_view(&claims, comment)))
+}
+
+/// Moderator actions on a comment, oldest first
+#[get("/moderation_log/{comment_id}")]
+async fn moderation_log(
+    context: web::Data<RequestContext>,
+    _: CommentModerator,
+    scope: TargetScope,
+    comment_id: web::Path<i32>,
+) -> RequestResult {
+    let query_comment_id = comment_id.into_inner();
+
+    let log = db::blocked_access(&context.pool, move |conn| {
+        let query_comment_id = scope.resolve(conn, query_comment_id)?;
+        // the log outlives its comment, so only comments that still exist are checked
+        if let Some(scope_target) = scope.0 {
+            use diesel::dsl::exists;
+            use schema::comments;
+
+            let of_other_target = diesel::select(exists(
+                comments::table
+                    .find(query_comment_id)
+                    .filter(comments::target.ne(scope_target)),
+            ))
+            .get_result::<bool>(conn)?;
+            if of_other_target {
+                return Err(diesel::NotFound);
+            }
+        }
+
+        use schema::comment_moderations::dsl::*;
+
+        comment_moderations
+            .filter(comment_id.eq(query_comment_id))
+            .order(id)
+            .load::<models::comment::CommentModeration>(conn)
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", log);
+
+    Ok(HttpResponse::Ok().json(log))
+}
+
+/// The whole comment including its author, even if it is anonymous.
+/// Every call is recorded in the moderation log.
+#[post("/reveal_author/{comment_id}")]
+async fn reveal_author(
+    context: web::Data<RequestContext>,
+    CommentModerator(claims): CommentModerator,
+    scope: TargetScope,
+    comment_id: web::Path<i32>,
+) -> RequestResult {
+    let revealed_comment = comment_id.into_inner();
+    let moderator = ModerationActor::of(&claims);
+
+    // a reveal nobody can be held accountable for is not allowed
+    if !moderator.is_known() {
+        return Err(BabibappError::forbidden(
+            "Authors are only revealed to callers the moderation log can name",
+        ));
+    }
+
+    let comment = db::blocked_access(&context.pool, move |conn| {
+        let revealed_comment = scope.resolve(conn, revealed_comment)?;
+        conn.transaction::<_, BabibappError, _>(|| {
+            let comment = find_comment(conn, scope, revealed_comment)?;
+
+            record_moderation(
+                conn,
+                revealed_comment,
+                moderator,
+                ModerationAction::RevealAuthor,
+            )?;
+
+            Ok(comment)
+        })
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", comment);
+
+    Ok(HttpResponse::Ok().json(comment))
+}
@@ -1,9 +1,107 @@
-use actix_web::web;
+use std::future::{ready, Ready};
 
-mod student_comment;
-mod teacher_comment;
+use actix_web::dev::Payload;
+use actix_web::{web, FromRequest, HttpRequest};
+use diesel::prelude::*;
+use diesel::PgConnection;
 
+use babibapp_models::comment::{Comment, CommentSort, CommentTarget};
+use babibapp_schema::schema;
+
+mod handlers;
+
+pub use handlers::{comment_view, is_visible, visible_to};
+
+/// Comments of all targets share the routes below `/comment`. Each target also gets them below
+/// `/comment/{target}`, where only its own comments are found, as the routes of the separate
+/// student and teacher comments used to be. Teacher comments got new ids when both were moved
+/// into one table, below `/comment/teacher` their old ids still find them.
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("/student").configure(student_comment::config))
-        .service(web::scope("/teacher").configure(teacher_comment::config));
+    for target in CommentTarget::ALL {
+        cfg.service(
+            web::scope(&format!("/{}", target))
+                .app_data(TargetScope(Some(target)))
+                .configure(handlers::config),
+        );
+    }
+    cfg.configure(handlers::config);
+}
+
+/// Extractor for the target of the routes a request came in on, `None` for the shared routes
+#[derive(Debug, Clone, Copy, Default)]
+struct TargetScope(Option<CommentTarget>);
+
+impl TargetScope {
+    /// Comments of other targets are treated as missing
+    fn check(self, comment: Comment) -> QueryResult<Comment> {
+        match self.0 {
+            Some(target) if target != comment.target => Err(diesel::NotFound),
+            _ => Ok(comment),
+        }
+    }
+
+    /// Id of the comment `comment_id` names on these routes. The teacher routes take the old id of
+    /// a teacher comment first, current ids of teacher comments are all higher than the old ones.
+    fn resolve(self, conn: &PgConnection, comment_id: i32) -> QueryResult<i32> {
+        use schema::comments::dsl::*;
+
+        if self.0 != Some(CommentTarget::Teacher) {
+            return Ok(comment_id);
+        }
+
+        let current_id = comments
+            .filter(legacy_teacher_id.eq(comment_id))
+            .select(id)
+            .first::<i32>(conn)
+            .optional()?;
+
+        Ok(current_id.unwrap_or(comment_id))
+    }
+}
+
+impl FromRequest for TargetScope {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(req
+            .app_data::<TargetScope>()
+            .copied()
+            .unwrap_or_default()))
+    }
+}
+
+/// Upvotes of the comment in the current row of `comments`
+const UPVOTES: &str = "(SELECT COUNT(*) FROM comment_votes \
+     WHERE comment_votes.comment_id = comments.id AND comment_votes.upvote)";
+/// Downvotes of the comment in the current row of `comments`
+const DOWNVOTES: &str = "(SELECT COUNT(*) FROM comment_votes \
+     WHERE comment_votes.comment_id = comments.id AND NOT comment_votes.upvote)";
+
+/// SQL expression of the score comments are ranked by with `sort`, highest first.
+/// `new` only goes by the publication, which also breaks ties of the others.
+fn score(sort: CommentSort) -> Option<String> {
+    let score = match sort {
+        CommentSort::New => return None,
+        CommentSort::Top => "up - down",
+        // grows with the number of votes as long as they are split evenly
+        CommentSort::Controversial => {
+            "CASE WHEN up = 0 OR down = 0 THEN 0 \
+             ELSE POWER(up + down, LEAST(up, down) / GREATEST(up, down)) END"
+        }
+        // lower bound of the Wilson score interval for the share of upvotes, at 95% confidence
+        CommentSort::Wilson => {
+            "CASE WHEN up + down = 0 THEN 0 \
+             ELSE (up / (up + down) + 1.96 ^ 2 / (2 * (up + down)) \
+             - 1.96 * SQRT((up * down / (up + down) ^ 2 + 1.96 ^ 2 / (4 * (up + down))) / (up + down))) \
+             / (1 + 1.96 ^ 2 / (up + down)) END"
+        }
+    };
+
+    Some(format!(
+        "(SELECT {} FROM (SELECT \
+         COUNT(*) FILTER (WHERE upvote)::float8 AS up, COUNT(*) FILTER (WHERE NOT upvote)::float8 AS down \
+         FROM comment_votes WHERE comment_votes.comment_id = comments.id) AS votes)",
+        score
+    ))
 }
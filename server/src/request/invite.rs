@@ -0,0 +1,134 @@
+use std::time::{Duration, SystemTime};
+
+use actix_web::{delete, get, post, web, HttpResponse};
+use diesel::prelude::*;
+
+use babibapp_models as models;
+use babibapp_schema::schema;
+
+use crate::auth::{self, Permission, StudentManager};
+use crate::db;
+use crate::error::BabibappError;
+use crate::request::{RequestContext, RequestResult};
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_all).service(create).service(revoke);
+}
+
+#[get("/get_all")]
+async fn get_all(context: web::Data<RequestContext>, _: StudentManager) -> RequestResult {
+    let invites = db::blocked_access(&context.pool, |conn| {
+        use schema::invites::dsl::*;
+
+        invites
+            .order(id.desc())
+            .load::<models::invite::InviteRecord>(conn)
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", invites);
+
+    let invites: Vec<models::invite::Invite> = invites.into_iter().map(|i| i.into()).collect();
+
+    Ok(HttpResponse::Ok().json(invites))
+}
+
+#[post("/create")]
+async fn create(
+    context: web::Data<RequestContext>,
+    StudentManager(claims): StudentManager,
+    form: web::Json<models::invite::CreateInvite>,
+) -> RequestResult {
+    if form.role.is_some() && !claims.has(Permission::ManageRoles) {
+        return Err(BabibappError::forbidden(
+            "Only role managers may create invites that grant a role",
+        ));
+    }
+
+    let invite_max_uses = form.max_uses.unwrap_or(1);
+    if invite_max_uses < 1 {
+        return Err(BabibappError::validation_failed(
+            "An invite must allow at least one use",
+        ));
+    }
+
+    let expiration_days = form.expiration_days.unwrap_or(7).max(0) as u64;
+    let invite_created_by = claims.student_id();
+    let code = auth::generate_random_token();
+    let invite_code_hash = auth::hash_token(&code);
+
+    let invite = db::blocked_access(&context.pool, move |conn| {
+        let invite_role_id = match &form.role {
+            Some(role_name) => {
+                use schema::roles::dsl::*;
+
+                let role = roles
+                    .filter(name.eq(role_name))
+                    .first::<models::role::Role>(conn)
+                    .optional()?
+                    .ok_or_else(|| {
+                        BabibappError::not_found(format!("No role found with name: {}", role_name))
+                    })?;
+                Some(role.id)
+            }
+            None => None,
+        };
+
+        use schema::invites::dsl::*;
+
+        let new_invite = models::invite::NewInvite {
+            code_hash: invite_code_hash,
+            created_by: invite_created_by,
+            expires: SystemTime::now() + Duration::from_secs(expiration_days * 24 * 60 * 60),
+            max_uses: invite_max_uses,
+            email: form.email.clone(),
+            first_name: form.first_name.clone(),
+            last_name: form.last_name.clone(),
+            role_id: invite_role_id,
+        };
+
+        let invite = diesel::insert_into(invites)
+            .values(&new_invite)
+            .get_result::<models::invite::InviteRecord>(conn)?;
+
+        Ok(invite) as Result<models::invite::InviteRecord, BabibappError>
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", invite);
+
+    Ok(HttpResponse::Ok().json(models::invite::CreatedInvite {
+        code,
+        invite: invite.into(),
+    }))
+}
+
+#[delete("/revoke/{invite_id}")]
+async fn revoke(
+    context: web::Data<RequestContext>,
+    _: StudentManager,
+    invite_id: web::Path<i32>,
+) -> RequestResult {
+    let invite_id = invite_id.into_inner();
+
+    let invite = db::blocked_access(&context.pool, move |conn| {
+        use schema::invites::dsl::*;
+
+        diesel::update(invites.find(invite_id))
+            .set(revoked.eq(true))
+            .get_result::<models::invite::InviteRecord>(conn)
+            .optional()
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", invite);
+
+    if let Some(invite) = invite {
+        Ok(HttpResponse::Ok().json(models::invite::Invite::from(invite)))
+    } else {
+        Err(BabibappError::not_found(format!(
+            "No invite found with invite_id: {}",
+            invite_id
+        )))
+    }
+}
@@ -0,0 +1,62 @@
+use std::time::SystemTime;
+
+use actix_web::{delete, get, web, HttpResponse};
+use diesel::prelude::*;
+
+use babibapp_models as models;
+use babibapp_schema::schema;
+
+use crate::auth::StudentManager;
+use crate::db;
+use crate::error::BabibappError;
+use crate::request::{RequestContext, RequestResult};
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_all).service(clear);
+}
+
+#[get("/get_all")]
+async fn get_all(context: web::Data<RequestContext>, _: StudentManager) -> RequestResult {
+    let lockouts = db::blocked_access(&context.pool, |conn| {
+        use schema::login_lockouts::dsl::*;
+
+        login_lockouts
+            .filter(locked_until.gt(SystemTime::now()))
+            .order(locked_until.desc())
+            .load::<models::login::LoginLockout>(conn)
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", lockouts);
+
+    Ok(HttpResponse::Ok().json(lockouts))
+}
+
+#[delete("/clear/{lockout_id}")]
+async fn clear(
+    context: web::Data<RequestContext>,
+    _: StudentManager,
+    lockout_id: web::Path<i32>,
+) -> RequestResult {
+    let lockout_id = lockout_id.into_inner();
+
+    let lockout = db::blocked_access(&context.pool, move |conn| {
+        use schema::login_lockouts::dsl::*;
+
+        diesel::delete(login_lockouts.find(lockout_id))
+            .get_result::<models::login::LoginLockout>(conn)
+            .optional()
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", lockout);
+
+    if let Some(lockout) = lockout {
+        Ok(HttpResponse::Ok().json(lockout))
+    } else {
+        Err(BabibappError::not_found(format!(
+            "No lockout found with lockout_id: {}",
+            lockout_id
+        )))
+    }
+}
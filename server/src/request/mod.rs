@@ -1,13 +1,24 @@
+use std::sync::Arc;
+
 use actix_web::{web, HttpResponse};
 
 use crate::error::BabibappError;
+use crate::keys::TokenKeys;
+use crate::mail::Mailer;
 use crate::settings::Settings;
 use crate::DbPool;
 
+mod api_key;
 mod comment;
+mod invite;
+mod lockout;
+mod page;
+mod role;
+mod search;
 mod student;
 mod teacher;
 mod token;
+mod totp;
 
 type RequestResult = Result<HttpResponse, BabibappError>;
 
@@ -15,11 +26,32 @@ type RequestResult = Result<HttpResponse, BabibappError>;
 pub struct RequestContext {
     pub pool: DbPool,
     pub settings: Settings,
+    pub mailer: Arc<dyn Mailer>,
+    pub keys: Arc<TokenKeys>,
 }
 
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::scope("/token").configure(token::config))
-        .service(web::scope("/student").configure(student::config))
-        .service(web::scope("/teacher").configure(teacher::config))
-        .service(web::scope("/comment").configure(comment::config));
+    // malformed bodies, paths and queries are answered with the same JSON errors as everything else
+    cfg.app_data(
+        web::JsonConfig::default()
+            .error_handler(|err, _| BabibappError::validation_failed(err.to_string()).into()),
+    )
+    .app_data(
+        web::PathConfig::default()
+            .error_handler(|err, _| BabibappError::validation_failed(err.to_string()).into()),
+    )
+    .app_data(
+        web::QueryConfig::default()
+            .error_handler(|err, _| BabibappError::validation_failed(err.to_string()).into()),
+    )
+    .service(web::scope("/token").configure(token::config))
+    .service(web::scope("/student").configure(student::config))
+    .service(web::scope("/teacher").configure(teacher::config))
+    .service(web::scope("/comment").configure(comment::config))
+    .service(web::scope("/role").configure(role::config))
+    .service(web::scope("/lockout").configure(lockout::config))
+    .service(web::scope("/invite").configure(invite::config))
+    .service(web::scope("/totp").configure(totp::config))
+    .service(web::scope("/api_key").configure(api_key::config))
+    .service(web::scope("/search").configure(search::config));
 }
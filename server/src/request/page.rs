@@ -0,0 +1,216 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use babibapp_models::page::Page;
+use babibapp_models::wrappers::PageQuery;
+
+use crate::error::BabibappError;
+
+/// Page size of requests without `limit`
+const DEFAULT_LIMIT: i64 = 50;
+const MAX_LIMIT: i64 = 200;
+
+pub fn limit(query: &PageQuery) -> Result<i64, BabibappError> {
+    match query.limit {
+        None => Ok(DEFAULT_LIMIT),
+        Some(limit) if (1..=MAX_LIMIT).contains(&limit) => Ok(limit),
+        Some(_) => Err(BabibappError::validation_failed(format!(
+            "limit has to be between 1 and {}",
+            MAX_LIMIT
+        ))),
+    }
+}
+
+/// Cursors are opaque to clients, listings by id use the last id of the previous page,
+/// listings by publication a `PublishedCursor` and ranked listings the number of items already handed out
+pub fn cursor<C: FromStr + Default + PartialOrd>(query: &PageQuery) -> Result<C, BabibappError> {
+    match query.cursor.as_deref().map(str::parse::<C>) {
+        None => Ok(C::default()),
+        Some(Ok(cursor)) if cursor >= C::default() => Ok(cursor),
+        Some(_) => Err(BabibappError::validation_failed("Invalid cursor")),
+    }
+}
+
+/// Latest publication a cursor may name, the end of the year 9999 the database can still store
+const MAX_CURSOR_MICROS: u64 = 253_402_300_799_999_999;
+
+/// Last item of the previous page of a listing ordered by publication and id, newest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublishedCursor {
+    pub published: SystemTime,
+    pub id: i32,
+}
+
+impl PublishedCursor {
+    /// `None` for the first page
+    pub fn from_query(query: &PageQuery) -> Result<Option<Self>, BabibappError> {
+        query
+            .cursor
+            .as_deref()
+            .map(|cursor| {
+                cursor
+                    .parse()
+                    .map_err(|_| BabibappError::validation_failed("Invalid cursor"))
+            })
+            .transpose()
+    }
+}
+
+/// Microseconds since the unix epoch and the id, the database doesn't keep anything finer
+impl fmt::Display for PublishedCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let micros = self
+            .published
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros();
+        write!(f, "{}_{}", micros, self.id)
+    }
+}
+
+impl FromStr for PublishedCursor {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (micros, id) = s.split_once('_').ok_or(())?;
+        let micros: u64 = micros.parse().map_err(|_| ())?;
+        if micros > MAX_CURSOR_MICROS {
+            return Err(());
+        }
+        let published = UNIX_EPOCH + Duration::from_micros(micros);
+
+        Ok(PublishedCursor {
+            published,
+            id: id.parse().map_err(|_| ())?,
+        })
+    }
+}
+
+/// Page of rows in the order of a cursor, loaded with one row more than `limit` to tell whether another page follows
+pub fn keyset_page<T, C: ToString>(
+    mut rows: Vec<T>,
+    limit: i64,
+    total: i64,
+    cursor: impl Fn(&T) -> C,
+) -> Page<T> {
+    let next_cursor = if rows.len() as i64 > limit {
+        rows.truncate(limit as usize);
+        rows.last().map(|row| cursor(row).to_string())
+    } else {
+        None
+    };
+
+    Page {
+        items: rows,
+        next_cursor,
+        total,
+    }
+}
+
+/// Page of rows starting after the first `offset` ones, loaded with one row more than `limit`
+pub fn offset_page<T>(mut rows: Vec<T>, offset: i64, limit: i64, total: i64) -> Page<T> {
+    let next_cursor = if rows.len() as i64 > limit {
+        rows.truncate(limit as usize);
+        Some(offset.saturating_add(limit).to_string())
+    } else {
+        None
+    };
+
+    Page {
+        items: rows,
+        next_cursor,
+        total,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(limit: Option<i64>, cursor: Option<&str>) -> PageQuery {
+        PageQuery {
+            limit,
+            cursor: cursor.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn limit_defaults_and_is_bounded() {
+        assert_eq!(limit(&query(None, None)).unwrap(), DEFAULT_LIMIT);
+        assert_eq!(limit(&query(Some(1), None)).unwrap(), 1);
+        assert_eq!(limit(&query(Some(MAX_LIMIT), None)).unwrap(), MAX_LIMIT);
+        assert!(limit(&query(Some(0), None)).is_err());
+        assert!(limit(&query(Some(MAX_LIMIT + 1), None)).is_err());
+    }
+
+    #[test]
+    fn cursor_rejects_negative_and_malformed_values() {
+        assert_eq!(cursor::<i32>(&query(None, None)).unwrap(), 0);
+        assert_eq!(cursor::<i32>(&query(None, Some("17"))).unwrap(), 17);
+        assert!(cursor::<i32>(&query(None, Some("-1"))).is_err());
+        assert!(cursor::<i32>(&query(None, Some("abc"))).is_err());
+        assert!(cursor::<i64>(&query(None, Some("18446744073709551615"))).is_err());
+    }
+
+    #[test]
+    fn published_cursor_round_trips() {
+        let cursor = PublishedCursor {
+            published: UNIX_EPOCH + Duration::from_micros(1_651_234_567_123_456),
+            id: 42,
+        };
+        let parsed = PublishedCursor::from_query(&query(None, Some(&cursor.to_string())));
+        assert_eq!(parsed.unwrap(), Some(cursor));
+    }
+
+    #[test]
+    fn published_cursor_rejects_malformed_values() {
+        assert_eq!(
+            PublishedCursor::from_query(&query(None, None)).unwrap(),
+            None
+        );
+        for malformed in [
+            "",
+            "12",
+            "12_",
+            "_12",
+            "-1_2",
+            "1_-",
+            "1_2_3",
+            "253402300800000000_1",
+        ] {
+            assert!(PublishedCursor::from_query(&query(None, Some(malformed))).is_err());
+        }
+    }
+
+    #[test]
+    fn keyset_page_continues_after_last_row() {
+        let page = keyset_page(vec![1, 2, 3], 2, 10, |row| *row);
+        assert_eq!(page.items, vec![1, 2]);
+        assert_eq!(page.next_cursor.as_deref(), Some("2"));
+        assert_eq!(page.total, 10);
+
+        let last = keyset_page(vec![1, 2], 2, 10, |row| *row);
+        assert_eq!(last.items, vec![1, 2]);
+        assert_eq!(last.next_cursor, None);
+    }
+
+    #[test]
+    fn offset_page_continues_after_handed_out_rows() {
+        let page = offset_page(vec![5, 6, 7], 4, 2, 10);
+        assert_eq!(page.items, vec![5, 6]);
+        assert_eq!(page.next_cursor.as_deref(), Some("6"));
+
+        assert_eq!(offset_page(vec![9], 8, 2, 10).next_cursor, None);
+        assert_eq!(
+            offset_page(Vec::<i32>::new(), 20, 2, 10).items,
+            Vec::<i32>::new()
+        );
+    }
+
+    #[test]
+    fn offset_page_does_not_overflow() {
+        let page = offset_page(vec![1, 2, 3], i64::MAX - 1, 2, 10);
+        assert_eq!(page.next_cursor, Some(i64::MAX.to_string()));
+    }
+}
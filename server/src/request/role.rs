@@ -0,0 +1,266 @@
+use actix_web::{delete, get, post, put, web, HttpResponse};
+use diesel::prelude::*;
+use diesel::PgConnection;
+
+use babibapp_models as models;
+use babibapp_schema::schema;
+use models::wrappers::RoleWrapper;
+
+use crate::auth::{self, AuthenticatedUser, Permission, RoleManager};
+use crate::db;
+use crate::error::BabibappError;
+use crate::request::{RequestContext, RequestResult};
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_all)
+        .service(get_student)
+        .service(create)
+        .service(delete)
+        .service(grant)
+        .service(revoke);
+}
+
+/// Attaches the permissions to each of the given roles
+fn role_views(
+    conn: &PgConnection,
+    roles: Vec<models::role::Role>,
+) -> QueryResult<Vec<models::role::RoleView>> {
+    use schema::role_permissions::dsl::*;
+
+    let role_ids: Vec<i32> = roles.iter().map(|role| role.id).collect();
+    let permissions = role_permissions
+        .filter(role_id.eq_any(role_ids))
+        .load::<models::role::RolePermission>(conn)?;
+
+    Ok(roles
+        .into_iter()
+        .map(|role| models::role::RoleView {
+            permissions: permissions
+                .iter()
+                .filter(|p| p.role_id == role.id)
+                .filter_map(|p| p.permission.parse().ok())
+                .collect(),
+            id: role.id,
+            name: role.name,
+        })
+        .collect())
+}
+
+fn student_role_views(
+    conn: &PgConnection,
+    student: i32,
+) -> QueryResult<Vec<models::role::RoleView>> {
+    use schema::{roles, student_roles};
+
+    let roles = roles::table
+        .inner_join(student_roles::table)
+        .filter(student_roles::student_id.eq(student))
+        .select((roles::id, roles::name))
+        .order(roles::id)
+        .load::<models::role::Role>(conn)?;
+
+    role_views(conn, roles)
+}
+
+fn find_role(conn: &PgConnection, role_name: String) -> Result<models::role::Role, BabibappError> {
+    use schema::roles::dsl::*;
+
+    roles
+        .filter(name.eq(&role_name))
+        .first::<models::role::Role>(conn)
+        .optional()?
+        .ok_or_else(|| BabibappError::not_found(format!("No role found with name: {}", role_name)))
+}
+
+#[get("/get_all")]
+async fn get_all(context: web::Data<RequestContext>, _: RoleManager) -> RequestResult {
+    let roles = db::blocked_access(&context.pool, |conn| {
+        use schema::roles::dsl::*;
+
+        let list = roles.order(id).load::<models::role::Role>(conn)?;
+        role_views(conn, list)
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", roles);
+
+    Ok(HttpResponse::Ok().json(roles))
+}
+
+#[get("/get_student/{student_id}")]
+async fn get_student(
+    context: web::Data<RequestContext>,
+    AuthenticatedUser(claims): AuthenticatedUser,
+    student_id: web::Path<i32>,
+) -> RequestResult {
+    let student_id = student_id.into_inner();
+
+    if !claims.has(Permission::ManageRoles) && student_id != claims.id {
+        return Err(BabibappError::forbidden(
+            "Only the student or a role manager may do this",
+        ));
+    }
+
+    let roles = db::blocked_access(&context.pool, move |conn| {
+        student_role_views(conn, student_id)
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", roles);
+
+    Ok(HttpResponse::Ok().json(roles))
+}
+
+#[post("/create")]
+async fn create(
+    context: web::Data<RequestContext>,
+    _: RoleManager,
+    form: web::Json<models::role::CreateRole>,
+) -> RequestResult {
+    let role = db::blocked_access(&context.pool, move |conn| {
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            use schema::{role_permissions, roles};
+
+            let new_role = models::role::NewRole {
+                name: form.name.clone(),
+            };
+
+            let role = diesel::insert_into(roles::table)
+                .values(&new_role)
+                .get_result::<models::role::Role>(conn)?;
+
+            let permissions: Vec<models::role::RolePermission> = form
+                .permissions
+                .iter()
+                .map(|permission| models::role::RolePermission {
+                    role_id: role.id,
+                    permission: permission.to_string(),
+                })
+                .collect();
+
+            diesel::insert_into(role_permissions::table)
+                .values(&permissions)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            role_views(conn, vec![role])
+        })
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", role);
+
+    Ok(HttpResponse::Ok().json(&role[0]))
+}
+
+#[delete("/delete/{role_id}")]
+async fn delete(
+    context: web::Data<RequestContext>,
+    _: RoleManager,
+    role_id: web::Path<i32>,
+) -> RequestResult {
+    let role_id = role_id.into_inner();
+
+    let role = db::blocked_access(&context.pool, move |conn| {
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            use schema::roles::dsl::*;
+
+            let holders = schema::student_roles::table
+                .filter(schema::student_roles::role_id.eq(role_id))
+                .select(schema::student_roles::student_id)
+                .load::<i32>(conn)?;
+
+            let role = diesel::delete(roles.find(role_id))
+                .get_result::<models::role::Role>(conn)
+                .optional()?;
+
+            // the permissions of the role are in the tokens of its holders until they log in again
+            for holder in holders {
+                auth::end_sessions(conn, holder, None)?;
+            }
+
+            Ok(role)
+        })
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", role);
+
+    if let Some(role) = role {
+        Ok(HttpResponse::Ok().json(role))
+    } else {
+        Err(BabibappError::not_found(format!(
+            "No role found with role_id: {}",
+            role_id
+        )))
+    }
+}
+
+#[put("/grant/{student_id}")]
+async fn grant(
+    context: web::Data<RequestContext>,
+    _: RoleManager,
+    student_id: web::Path<i32>,
+    form: web::Json<RoleWrapper>,
+) -> RequestResult {
+    let student_id = student_id.into_inner();
+
+    let roles = db::blocked_access(&context.pool, move |conn| {
+        use schema::student_roles;
+
+        let role = find_role(conn, form.role.clone())?;
+
+        diesel::insert_into(student_roles::table)
+            .values(&models::role::StudentRole {
+                student_id,
+                role_id: role.id,
+            })
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        Ok(student_role_views(conn, student_id)?)
+            as Result<Vec<models::role::RoleView>, BabibappError>
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", roles);
+
+    Ok(HttpResponse::Ok().json(roles))
+}
+
+#[put("/revoke/{student_id}")]
+async fn revoke(
+    context: web::Data<RequestContext>,
+    _: RoleManager,
+    student_id: web::Path<i32>,
+    form: web::Json<RoleWrapper>,
+) -> RequestResult {
+    let student_id = student_id.into_inner();
+
+    let roles = db::blocked_access(&context.pool, move |conn| {
+        conn.transaction::<_, BabibappError, _>(|| {
+            use schema::student_roles::dsl;
+
+            let role = find_role(conn, form.role.clone())?;
+
+            let removed = diesel::delete(
+                dsl::student_roles
+                    .filter(dsl::student_id.eq(student_id))
+                    .filter(dsl::role_id.eq(role.id)),
+            )
+            .execute(conn)?;
+
+            // the tokens of the student still carry the permissions of the role
+            if removed > 0 {
+                auth::end_sessions(conn, student_id, None)?;
+            }
+
+            Ok(student_role_views(conn, student_id)?)
+        })
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", roles);
+
+    Ok(HttpResponse::Ok().json(roles))
+}
@@ -0,0 +1,217 @@
+use actix_web::{get, web, HttpResponse};
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Float, Text};
+use diesel::PgConnection;
+
+use babibapp_models as models;
+use babibapp_schema::schema;
+use models::wrappers::{PageQuery, SearchQuery};
+
+use crate::auth::{AuthenticatedUser, Permission};
+use crate::db;
+use crate::error::BabibappError;
+use crate::request::comment::{comment_view, is_visible, visible_to};
+use crate::request::student::student_view;
+use crate::request::{page, RequestContext, RequestResult};
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(search);
+}
+
+/// The searched text of each kind, these have to stay the expressions of the GIN indexes
+const COMMENT_DOCUMENT: &str = "comments.body";
+const STUDENT_DOCUMENT: &str = "students.first_name || ' ' || students.last_name";
+const TEACHER_DOCUMENT: &str = "teachers.prefix || ' ' || teachers.name";
+
+/// Closes a `headline` fragment, matched words are wrapped in `**`
+const HEADLINE_OPTIONS: &str = "), 'StartSel=**, StopSel=**')";
+
+// Each fragment below ends where the search query has to be bound.
+// `websearch_to_tsquery` accepts any input, so users can't cause syntax errors.
+
+fn matches(document: &str) -> String {
+    format!(
+        "to_tsvector('simple', {}) @@ websearch_to_tsquery('simple', ",
+        document
+    )
+}
+
+fn rank(document: &str) -> String {
+    format!(
+        "ts_rank(to_tsvector('simple', {}), websearch_to_tsquery('simple', ",
+        document
+    )
+}
+
+fn headline(document: &str) -> String {
+    format!(
+        "ts_headline('simple', {}, websearch_to_tsquery('simple', ",
+        document
+    )
+}
+
+/// Comments, students and teachers matching `q`, each kind ranked on its own.
+/// Comments follow the same visibility rules as `/comment/get`.
+#[get("")]
+async fn search(
+    context: web::Data<RequestContext>,
+    AuthenticatedUser(claims): AuthenticatedUser,
+    query: web::Query<SearchQuery>,
+) -> RequestResult {
+    let query = query.into_inner();
+    let limit = page::limit(&PageQuery {
+        limit: query.limit,
+        cursor: None,
+    })?;
+
+    if query.q.trim().is_empty() {
+        return Err(BabibappError::validation_failed(
+            "The search query is empty",
+        ));
+    }
+
+    let searcher = claims.id;
+    let moderator = claims.has(Permission::ModerateComments);
+
+    let (comments, students, teachers) = db::blocked_access(&context.pool, move |conn| {
+        Ok::<_, diesel::result::Error>((
+            search_comments(conn, searcher, moderator, &query.q, limit)?,
+            search_students(conn, &query.q, limit)?,
+            search_teachers(conn, &query.q, limit)?,
+        ))
+    })
+    .await??;
+
+    log::debug!(
+        "Database response: {:?}, {:?}, {:?}",
+        comments,
+        students,
+        teachers
+    );
+
+    let results = models::search::SearchResults {
+        comments: comments
+            .into_iter()
+            .filter(|(c, _)| is_visible(&claims, c))
+            .map(|(c, snippet)| models::search::CommentHit {
+                comment: comment_view(&claims, c),
+                snippet,
+            })
+            .collect(),
+        students: students
+            .into_iter()
+            .map(|(s, snippet)| models::search::StudentHit {
+                student: student_view(&claims, s),
+                snippet,
+            })
+            .collect(),
+        teachers: teachers
+            .into_iter()
+            .map(|(teacher, snippet)| models::search::TeacherHit { teacher, snippet })
+            .collect(),
+    };
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
+fn search_comments(
+    conn: &PgConnection,
+    searcher: i32,
+    moderator: bool,
+    q: &str,
+    limit: i64,
+) -> QueryResult<Vec<(models::comment::Comment, String)>> {
+    use schema::comments::dsl::*;
+
+    let mut query = comments
+        .select((
+            schema::comments::all_columns,
+            sql::<Text>(&headline(COMMENT_DOCUMENT))
+                .bind::<Text, _>(q.to_owned())
+                .sql(HEADLINE_OPTIONS),
+        ))
+        .filter(
+            sql::<Bool>(&matches(COMMENT_DOCUMENT))
+                .bind::<Text, _>(q.to_owned())
+                .sql(")"),
+        )
+        .filter(deleted.eq(false))
+        .order(
+            sql::<Float>(&rank(COMMENT_DOCUMENT))
+                .bind::<Text, _>(q.to_owned())
+                .sql(")) DESC"),
+        )
+        .then_order_by(id.desc())
+        .limit(limit)
+        .into_boxed();
+
+    if !moderator {
+        query = query.filter(visible_to(searcher));
+    }
+
+    let rows = query.load::<(models::comment::CommentRecord, String)>(conn)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(comment, snippet)| (comment.into(), snippet))
+        .collect())
+}
+
+fn search_students(
+    conn: &PgConnection,
+    q: &str,
+    limit: i64,
+) -> QueryResult<Vec<(models::student::StudentRecord, String)>> {
+    use schema::students::dsl::*;
+
+    students
+        .select((
+            schema::students::all_columns,
+            sql::<Text>(&headline(STUDENT_DOCUMENT))
+                .bind::<Text, _>(q.to_owned())
+                .sql(HEADLINE_OPTIONS),
+        ))
+        .filter(
+            sql::<Bool>(&matches(STUDENT_DOCUMENT))
+                .bind::<Text, _>(q.to_owned())
+                .sql(")"),
+        )
+        .order(
+            sql::<Float>(&rank(STUDENT_DOCUMENT))
+                .bind::<Text, _>(q.to_owned())
+                .sql(")) DESC"),
+        )
+        .then_order_by(id)
+        .limit(limit)
+        .load(conn)
+}
+
+fn search_teachers(
+    conn: &PgConnection,
+    q: &str,
+    limit: i64,
+) -> QueryResult<Vec<(models::teacher::Teacher, String)>> {
+    use schema::teachers::dsl::*;
+
+    teachers
+        .select((
+            schema::teachers::all_columns,
+            sql::<Text>(&headline(TEACHER_DOCUMENT))
+                .bind::<Text, _>(q.to_owned())
+                .sql(HEADLINE_OPTIONS),
+        ))
+        .filter(
+            sql::<Bool>(&matches(TEACHER_DOCUMENT))
+                .bind::<Text, _>(q.to_owned())
+                .sql(")"),
+        )
+        .order(
+            sql::<Float>(&rank(TEACHER_DOCUMENT))
+                .bind::<Text, _>(q.to_owned())
+                .sql(")) DESC"),
+        )
+        .then_order_by(id)
+        .limit(limit)
+        .load(conn)
+}
@@ -1,25 +1,31 @@
+use std::time::SystemTime;
+
 use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
 use diesel::prelude::*;
 use pwhash::bcrypt;
 
 use babibapp_models as models;
 use babibapp_schema::schema;
+use models::page::Page;
 use models::wrappers::*;
 
-use crate::auth;
+use crate::auth::{self, AuthenticatedUser, Claims, Permission, StudentManager, StudentUser};
 use crate::db;
 use crate::error::BabibappError;
-use crate::request::{RequestContext, RequestResult};
+use crate::lockout;
+use crate::password;
+use crate::request::{page, RequestContext, RequestResult};
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(get)
         .service(get_self)
         .service(get_all)
         .service(register)
+        .service(signup)
         .service(reset_email)
         .service(reset_password)
+        .service(change_password)
         .service(reset_name)
-        .service(make_admin)
         .service(reset_full)
         .service(delete);
 }
@@ -27,14 +33,9 @@ pub fn config(cfg: &mut web::ServiceConfig) {
 #[get("/get/{student_id}")]
 async fn get(
     context: web::Data<RequestContext>,
-    req: HttpRequest,
+    AuthenticatedUser(claims): AuthenticatedUser,
     student_id: web::Path<i32>,
 ) -> RequestResult {
-    let token_settings = &context.settings.token;
-
-    let token = auth::token_from_request(req.clone())?;
-    let claims = auth::validate_token(&token.token, token_settings.secret.clone())?;
-
     let student_id = student_id.into_inner();
 
     let student = db::blocked_access(&context.pool, move |conn| {
@@ -42,7 +43,7 @@ async fn get(
 
         students
             .find(student_id)
-            .first::<models::student::Student>(conn)
+            .first::<models::student::StudentRecord>(conn)
             .optional()
     })
     .await??;
@@ -50,30 +51,37 @@ async fn get(
     log::debug!("Database response: {:?}", student);
 
     if let Some(student) = student {
-        let student_view = if claims.id == student.id || claims.admin {
-            models::student::StudentView::Full(student)
-        } else {
-            let limited = models::student::LimitedViewStudent {
-                id: student.id,
-                first_name: student.first_name,
-                last_name: student.last_name,
-            };
-            models::student::StudentView::Limited(limited)
-        };
-
-        return Ok(HttpResponse::Ok().json(student_view));
+        return Ok(HttpResponse::Ok().json(student_view(&claims, student)));
     }
 
-    Ok(HttpResponse::NotFound().body(format!("No student found with student_id: {}", student_id)))
+    Err(BabibappError::not_found(format!(
+        "No student found with student_id: {}",
+        student_id
+    )))
 }
 
-#[get("/get_self")]
-async fn get_self(context: web::Data<RequestContext>, req: HttpRequest) -> RequestResult {
-    let token_settings = &context.settings.token;
-
-    let token = auth::token_from_request(req.clone())?;
-    let claims = auth::validate_token(&token.token, token_settings.secret.clone())?;
+/// The student and student managers see the whole profile, everyone else only the name
+pub fn student_view(
+    claims: &Claims,
+    student: models::student::StudentRecord,
+) -> models::student::StudentView {
+    if claims.id == student.id || claims.has(Permission::ManageStudents) {
+        models::student::StudentView::Full(student.into())
+    } else {
+        let limited = models::student::LimitedViewStudent {
+            id: student.id,
+            first_name: student.first_name,
+            last_name: student.last_name,
+        };
+        models::student::StudentView::Limited(limited)
+    }
+}
 
+#[get("/get_self")]
+async fn get_self(
+    context: web::Data<RequestContext>,
+    StudentUser(claims): StudentUser,
+) -> RequestResult {
     let student_id = claims.id;
 
     let student = db::blocked_access(&context.pool, move |conn| {
@@ -81,7 +89,7 @@ async fn get_self(context: web::Data<RequestContext>, req: HttpRequest) -> Reque
 
         students
             .find(student_id)
-            .first::<models::student::Student>(conn)
+            .first::<models::student::StudentRecord>(conn)
             .optional()
     })
     .await??;
@@ -89,44 +97,44 @@ async fn get_self(context: web::Data<RequestContext>, req: HttpRequest) -> Reque
     log::debug!("Database response: {:?}", student);
 
     if let Some(student) = student {
-        Ok(HttpResponse::Ok().json(student))
+        Ok(HttpResponse::Ok().json(models::student::StudentProfile::from(student)))
     } else {
-        Ok(HttpResponse::NotFound()
-            .body(format!("No student found with student_id: {}", student_id)))
+        Err(BabibappError::not_found(format!(
+            "No student found with student_id: {}",
+            student_id
+        )))
     }
 }
 
+/// Students ordered by id, one page at a time
 #[get("/get_all")]
-async fn get_all(context: web::Data<RequestContext>, req: HttpRequest) -> RequestResult {
-    let token_settings = &context.settings.token;
+async fn get_all(
+    context: web::Data<RequestContext>,
+    AuthenticatedUser(claims): AuthenticatedUser,
+    query: web::Query<PageQuery>,
+) -> RequestResult {
+    let limit = page::limit(&query)?;
+    let after = page::cursor::<i32>(&query)?;
+
+    let students = db::blocked_access(&context.pool, move |conn| {
+        use diesel::dsl::count_star;
+        use schema::students::dsl::*;
 
-    let token = auth::token_from_request(req.clone())?;
-    let claims = auth::validate_token(&token.token, token_settings.secret.clone())?;
+        let total = students.select(count_star()).get_result::<i64>(conn)?;
+        let list = students
+            .filter(id.gt(after))
+            .order(id)
+            .limit(limit + 1)
+            .load::<models::student::StudentRecord>(conn)?;
 
-    let students = db::blocked_access(&context.pool, |conn| {
-        use schema::students::table;
-        let list = table.load::<models::student::Student>(conn)?;
-        Ok(list) as Result<Vec<models::student::Student>, BabibappError>
+        QueryResult::Ok(page::keyset_page(list, limit, total, |s| s.id))
     })
     .await??;
 
     log::debug!("Database response: {:?}", students);
 
-    let student_views: Vec<models::student::StudentView> = students
-        .into_iter()
-        .map(|s| {
-            if claims.id == s.id || claims.admin {
-                models::student::StudentView::Full(s)
-            } else {
-                let limited = models::student::LimitedViewStudent {
-                    id: s.id,
-                    first_name: s.first_name,
-                    last_name: s.last_name,
-                };
-                models::student::StudentView::Limited(limited)
-            }
-        })
-        .collect();
+    let student_views: Page<models::student::StudentView> =
+        students.map(|s| student_view(&claims, s));
 
     Ok(HttpResponse::Ok().json(student_views))
 }
@@ -134,18 +142,10 @@ async fn get_all(context: web::Data<RequestContext>, req: HttpRequest) -> Reques
 #[post("/register")]
 async fn register(
     context: web::Data<RequestContext>,
-    req: HttpRequest,
+    _: StudentManager,
     form: web::Json<models::student::RegisterStudent>,
 ) -> RequestResult {
-    let token_settings = &context.settings.token;
-
-    let token = auth::token_from_request(req.clone())?;
-    let claims = auth::validate_token(&token.token, token_settings.secret.clone())?;
-
-    if !claims.admin {
-        return Ok(HttpResponse::Unauthorized().body("Access only for admins"));
-    }
-
+    password::check_policy(&form.password, &form.email, &context.settings.password)?;
     let hashed_password = bcrypt::hash(form.password.clone())?;
 
     let student = db::blocked_access(&context.pool, move |conn| {
@@ -156,36 +156,101 @@ async fn register(
             first_name: form.first_name.clone(),
             last_name: form.last_name.clone(),
             password_hash: hashed_password,
-            admin: form.admin,
         };
 
         diesel::insert_into(students)
             .values(&new_student)
-            .get_result::<models::student::Student>(conn)
+            .get_result::<models::student::StudentRecord>(conn)
     })
     .await??;
 
     log::debug!("Database response: {:?}", student);
 
-    Ok(HttpResponse::Ok().json(student))
+    Ok(HttpResponse::Ok().json(models::student::StudentProfile::from(student)))
+}
+
+/// Public registration with an invite code
+#[post("/signup")]
+async fn signup(
+    context: web::Data<RequestContext>,
+    form: web::Json<models::invite::SignupStudent>,
+) -> RequestResult {
+    let password_settings = context.settings.password.clone();
+    let presented_hash = auth::hash_token(&form.code);
+
+    let student = db::blocked_access(&context.pool, move |conn| {
+        conn.transaction::<_, BabibappError, _>(|| {
+            use schema::invites::dsl::*;
+
+            let invite = invites
+                .filter(code_hash.eq(presented_hash))
+                .filter(revoked.eq(false))
+                .filter(expires.gt(SystemTime::now()))
+                .for_update()
+                .first::<models::invite::InviteRecord>(conn)
+                .optional()?
+                .filter(|invite| invite.uses < invite.max_uses)
+                .ok_or_else(|| BabibappError::unauthorized("Invalid or expired invite code"))?;
+
+            // pre-filled values of the invite can't be changed
+            let required = |prefilled: &Option<String>, given: &Option<String>, field: &str| {
+                prefilled
+                    .clone()
+                    .or_else(|| given.clone())
+                    .ok_or_else(|| BabibappError::validation_failed(format!("Missing {}", field)))
+            };
+            let new_email = required(&invite.email, &form.email, "email")?;
+            let new_first_name = required(&invite.first_name, &form.first_name, "first name")?;
+            let new_last_name = required(&invite.last_name, &form.last_name, "last name")?;
+
+            password::check_policy(&form.password, &new_email, &password_settings)?;
+            let hashed_password = bcrypt::hash(form.password.clone())?;
+
+            let student = diesel::insert_into(schema::students::table)
+                .values(&models::student::NewStudent {
+                    email: new_email,
+                    first_name: new_first_name,
+                    last_name: new_last_name,
+                    password_hash: hashed_password,
+                })
+                .get_result::<models::student::StudentRecord>(conn)?;
+
+            diesel::update(invites.find(invite.id))
+                .set(uses.eq(uses + 1))
+                .execute(conn)?;
+
+            if let Some(invite_role_id) = invite.role_id {
+                diesel::insert_into(schema::student_roles::table)
+                    .values(&models::role::StudentRole {
+                        student_id: student.id,
+                        role_id: invite_role_id,
+                    })
+                    .execute(conn)?;
+            }
+
+            Ok(student)
+        })
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", student);
+
+    Ok(HttpResponse::Ok().json(models::student::StudentProfile::from(student)))
 }
 
 #[put("/reset_email/{student_id}")]
 async fn reset_email(
     context: web::Data<RequestContext>,
-    req: HttpRequest,
+    AuthenticatedUser(claims): AuthenticatedUser,
     student_id: web::Path<i32>,
     form: web::Json<EmailWrapper>,
 ) -> RequestResult {
-    let token_settings = &context.settings.token;
-
-    let token = auth::token_from_request(req.clone())?;
-    let claims = auth::validate_token(&token.token, token_settings.secret.clone())?;
-
     let student_id = student_id.into_inner();
 
-    if !claims.admin && student_id != claims.id {
-        return Ok(HttpResponse::Unauthorized().body("Access only for admins"));
+    if !claims.has(Permission::ManageStudents) && student_id != claims.id {
+        return Err(BabibappError::forbidden(
+            "Only the student or a student manager may do this",
+        ));
     }
 
     let new_email = form.email.clone();
@@ -193,210 +258,255 @@ async fn reset_email(
     let student = db::blocked_access(&context.pool, move |conn| {
         use schema::students::dsl::*;
 
+        auth::check_outranks(conn, &claims, student_id)?;
+
         diesel::update(students.find(student_id))
             .set(email.eq(new_email))
-            .get_result::<models::student::Student>(conn)
+            .get_result::<models::student::StudentRecord>(conn)
             .optional()
+            .map_err(BabibappError::from)
     })
     .await??;
 
     if let Some(student) = student {
-        Ok(HttpResponse::Ok().json(student))
+        Ok(HttpResponse::Ok().json(models::student::StudentProfile::from(student)))
     } else {
-        Ok(HttpResponse::NotFound()
-            .body(format!("No student found with student_id: {}", student_id)))
+        Err(BabibappError::not_found(format!(
+            "No student found with student_id: {}",
+            student_id
+        )))
     }
 }
 
 #[put("/reset_password/{student_id}")]
 async fn reset_password(
     context: web::Data<RequestContext>,
-    req: HttpRequest,
+    StudentManager(claims): StudentManager,
     student_id: web::Path<i32>,
     form: web::Json<PasswordWrapper>,
 ) -> RequestResult {
-    let token_settings = &context.settings.token;
+    let student_id = student_id.into_inner();
+    let password_settings = context.settings.password.clone();
 
-    let token = auth::token_from_request(req.clone())?;
-    let claims = auth::validate_token(&token.token, token_settings.secret.clone())?;
+    let student = db::blocked_access(&context.pool, move |conn| {
+        use schema::students::dsl::*;
 
-    let student_id = student_id.into_inner();
+        auth::check_outranks(conn, &claims, student_id)?;
 
-    if !claims.admin && student_id != claims.id {
-        return Ok(HttpResponse::Unauthorized().body("Access only for admins"));
-    }
+        let student = students
+            .find(student_id)
+            .first::<models::student::StudentRecord>(conn)
+            .optional()?;
 
-    let hashed_password = bcrypt::hash(form.password.clone())?;
+        let student = match student {
+            Some(student) => student,
+            None => return Ok(None),
+        };
 
-    let student = db::blocked_access(&context.pool, move |conn| {
-        use schema::students::dsl::*;
+        password::check_policy(&form.password, &student.email, &password_settings)?;
+        let hashed_password = bcrypt::hash(form.password.clone())?;
 
-        diesel::update(students.find(student_id))
+        let student = diesel::update(students.find(student_id))
             .set(password_hash.eq(hashed_password))
-            .get_result::<models::student::Student>(conn)
-            .optional()
+            .get_result::<models::student::StudentRecord>(conn)?;
+        auth::end_sessions(conn, student_id, None)?;
+
+        Ok(Some(student)) as Result<Option<models::student::StudentRecord>, BabibappError>
     })
     .await??;
 
     if let Some(student) = student {
-        Ok(HttpResponse::Ok().json(student))
+        Ok(HttpResponse::Ok().json(models::student::StudentProfile::from(student)))
     } else {
-        Ok(HttpResponse::NotFound()
-            .body(format!("No student found with student_id: {}", student_id)))
+        Err(BabibappError::not_found(format!(
+            "No student found with student_id: {}",
+            student_id
+        )))
     }
 }
 
-#[put("/reset_name/{student_id}")]
-async fn reset_name(
+#[put("/change_password")]
+async fn change_password(
     context: web::Data<RequestContext>,
     req: HttpRequest,
-    student_id: web::Path<i32>,
-    form: web::Json<NameWrapper>,
+    StudentUser(claims): StudentUser,
+    form: web::Json<ChangePasswordWrapper>,
 ) -> RequestResult {
-    let token_settings = &context.settings.token;
+    let student_id = claims.id;
+    let attempt_ip = lockout::client_ip(&req, &context.settings.login);
 
-    let token = auth::token_from_request(req.clone())?;
-    let claims = auth::validate_token(&token.token, token_settings.secret.clone())?;
+    let check_ip = attempt_ip.clone();
+    let (student, locked_until) = db::blocked_access(&context.pool, move |conn| {
+        use schema::students::dsl::*;
 
-    let student_id = student_id.into_inner();
+        let student = students
+            .find(student_id)
+            .first::<models::student::StudentRecord>(conn)?;
+        let locked_until = lockout::locked_until(conn, &student.email.to_lowercase(), &check_ip)?;
+
+        QueryResult::Ok((student, locked_until))
+    })
+    .await??;
+
+    if locked_until.is_some() {
+        return Err(BabibappError::too_many_requests(
+            "Too many failed password attempts, try again later",
+        ));
+    }
 
-    if !claims.admin {
-        return Ok(HttpResponse::Unauthorized().body("Access only for admins"));
+    // a stolen access token must not allow guessing the password any faster than logging in
+    if !bcrypt::verify(&form.old_password, &student.password_hash) {
+        let attempt_email = student.email.to_lowercase();
+        let login_settings = context.settings.login.clone();
+        db::blocked_access(&context.pool, move |conn| {
+            lockout::record_attempt(conn, &login_settings, &attempt_email, &attempt_ip, false)
+        })
+        .await??;
+
+        return Err(BabibappError::forbidden("Wrong password"));
     }
 
+    password::check_policy(
+        &form.new_password,
+        &student.email,
+        &context.settings.password,
+    )?;
+    let hashed_password = bcrypt::hash(form.new_password.clone())?;
+
     let student = db::blocked_access(&context.pool, move |conn| {
-        use schema::students::dsl::*;
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            use schema::students::dsl::*;
 
-        diesel::update(students.find(student_id))
-            .set((
-                first_name.eq(form.first_name.clone()),
-                last_name.eq(form.last_name.clone()),
-            ))
-            .get_result::<models::student::Student>(conn)
-            .optional()
+            let student = diesel::update(students.find(student_id))
+                .set(password_hash.eq(hashed_password))
+                .get_result::<models::student::StudentRecord>(conn)?;
+
+            // whoever else knew the old password is logged out, this session stays
+            auth::end_sessions(conn, student_id, claims.sid)?;
+
+            Ok(student)
+        })
     })
     .await??;
 
-    if let Some(student) = student {
-        Ok(HttpResponse::Ok().json(student))
-    } else {
-        Ok(HttpResponse::NotFound()
-            .body(format!("No student found with student_id: {}", student_id)))
-    }
+    log::debug!("Database response: {:?}", student);
+
+    Ok(HttpResponse::Ok().json(models::student::StudentProfile::from(student)))
 }
 
-#[put("/make_admin/{student_id}")]
-async fn make_admin(
+#[put("/reset_name/{student_id}")]
+async fn reset_name(
     context: web::Data<RequestContext>,
-    req: HttpRequest,
+    StudentManager(claims): StudentManager,
     student_id: web::Path<i32>,
+    form: web::Json<NameWrapper>,
 ) -> RequestResult {
-    let token_settings = &context.settings.token;
-
-    let token = auth::token_from_request(req.clone())?;
-    let claims = auth::validate_token(&token.token, token_settings.secret.clone())?;
-
     let student_id = student_id.into_inner();
 
-    if !claims.admin {
-        return Ok(HttpResponse::Unauthorized().body("Access only for admins"));
-    }
-
     let student = db::blocked_access(&context.pool, move |conn| {
         use schema::students::dsl::*;
 
+        auth::check_outranks(conn, &claims, student_id)?;
+
         diesel::update(students.find(student_id))
-            .set(admin.eq(true))
-            .get_result::<models::student::Student>(conn)
+            .set((
+                first_name.eq(form.first_name.clone()),
+                last_name.eq(form.last_name.clone()),
+            ))
+            .get_result::<models::student::StudentRecord>(conn)
             .optional()
+            .map_err(BabibappError::from)
     })
     .await??;
 
     if let Some(student) = student {
-        Ok(HttpResponse::Ok().json(student))
+        Ok(HttpResponse::Ok().json(models::student::StudentProfile::from(student)))
     } else {
-        Ok(HttpResponse::NotFound()
-            .body(format!("No student found with student_id: {}", student_id)))
+        Err(BabibappError::not_found(format!(
+            "No student found with student_id: {}",
+            student_id
+        )))
     }
 }
 
 #[put("/reset_full/{student_id}")]
 async fn reset_full(
     context: web::Data<RequestContext>,
-    req: HttpRequest,
+    StudentManager(claims): StudentManager,
     student_id: web::Path<i32>,
     form: web::Json<models::student::RegisterStudent>,
 ) -> RequestResult {
-    let token_settings = &context.settings.token;
-
-    let token = auth::token_from_request(req.clone())?;
-    let claims = auth::validate_token(&token.token, token_settings.secret.clone())?;
-
     let student_id = student_id.into_inner();
 
-    if !claims.admin {
-        return Ok(HttpResponse::Unauthorized().body("Access only for admins"));
-    }
-
-    let new_admin_status = form.admin.unwrap_or(false);
+    password::check_policy(&form.password, &form.email, &context.settings.password)?;
     let hashed_password = bcrypt::hash(form.password.clone())?;
 
     let student = db::blocked_access(&context.pool, move |conn| {
-        use schema::students::dsl::*;
-
-        diesel::update(students.find(student_id))
-            .set((
-                email.eq(form.email.clone()),
-                first_name.eq(form.first_name.clone()),
-                last_name.eq(form.last_name.clone()),
-                password_hash.eq(hashed_password),
-                admin.eq(new_admin_status),
-            ))
-            .get_result::<models::student::Student>(conn)
-            .optional()
+        conn.transaction::<_, BabibappError, _>(|| {
+            use schema::students::dsl::*;
+
+            auth::check_outranks(conn, &claims, student_id)?;
+
+            let student = diesel::update(students.find(student_id))
+                .set((
+                    email.eq(form.email.clone()),
+                    first_name.eq(form.first_name.clone()),
+                    last_name.eq(form.last_name.clone()),
+                    password_hash.eq(hashed_password),
+                ))
+                .get_result::<models::student::StudentRecord>(conn)
+                .optional()?;
+            auth::end_sessions(conn, student_id, None)?;
+
+            Ok(student)
+        })
     })
     .await??;
 
     if let Some(student) = student {
-        Ok(HttpResponse::Ok().json(student))
+        Ok(HttpResponse::Ok().json(models::student::StudentProfile::from(student)))
     } else {
-        Ok(HttpResponse::NotFound()
-            .body(format!("No student found with student_id: {}", student_id)))
+        Err(BabibappError::not_found(format!(
+            "No student found with student_id: {}",
+            student_id
+        )))
     }
 }
 
 #[delete("/delete/{student_id}")]
 async fn delete(
     context: web::Data<RequestContext>,
-    req: HttpRequest,
+    AuthenticatedUser(claims): AuthenticatedUser,
     student_id: web::Path<i32>,
 ) -> RequestResult {
-    let token_settings = &context.settings.token;
-
-    let token = auth::token_from_request(req.clone())?;
-    let claims = auth::validate_token(&token.token, token_settings.secret.clone())?;
-
     let student_id = student_id.into_inner();
 
-    if !claims.admin && student_id != claims.id {
-        return Ok(HttpResponse::Unauthorized().body("Access only for admins"));
+    if !claims.has(Permission::ManageStudents) && student_id != claims.id {
+        return Err(BabibappError::forbidden(
+            "Only the student or a student manager may do this",
+        ));
     }
 
     let student = db::blocked_access(&context.pool, move |conn| {
         use schema::students::dsl::*;
 
+        auth::check_outranks(conn, &claims, student_id)?;
+
         diesel::delete(students.filter(id.eq(student_id)))
-            .get_result::<models::student::Student>(conn)
+            .get_result::<models::student::StudentRecord>(conn)
             .optional()
+            .map_err(BabibappError::from)
     })
     .await??;
 
     log::debug!("Database response: {:?}", student);
 
     if let Some(student) = student {
-        Ok(HttpResponse::Ok().json(student))
+        Ok(HttpResponse::Ok().json(models::student::StudentProfile::from(student)))
     } else {
-        Ok(HttpResponse::NotFound()
-            .body(format!("No student found with student_id: {}", student_id)))
+        Err(BabibappError::not_found(format!(
+            "No student found with student_id: {}",
+            student_id
+        )))
     }
 }
@@ -1,13 +1,14 @@
-use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
+use actix_web::{delete, get, post, put, web, HttpResponse};
 use diesel::prelude::*;
 
 use babibapp_models as models;
 use babibapp_schema::schema;
+use models::wrappers::PageQuery;
 
-use crate::auth;
+use crate::auth::{AuthenticatedUser, TeacherEditor};
 use crate::db;
 use crate::error::BabibappError;
-use crate::request::{RequestContext, RequestResult};
+use crate::request::{page, RequestContext, RequestResult};
 
 pub fn config(cfg: &mut web::ServiceConfig) {
     cfg.service(get)
@@ -20,14 +21,9 @@ pub fn config(cfg: &mut web::ServiceConfig) {
 #[get("/get/{teacher_id}")]
 async fn get(
     context: web::Data<RequestContext>,
-    req: HttpRequest,
+    _: AuthenticatedUser,
     teacher_id: web::Path<i32>,
 ) -> RequestResult {
-    let token_settings = &context.settings.token;
-
-    let token = auth::token_from_request(req.clone())?;
-    let _ = auth::validate_token(&token.token, token_settings.secret.clone())?;
-
     let teacher_id = teacher_id.into_inner();
 
     let teacher = db::blocked_access(&context.pool, move |conn| {
@@ -45,22 +41,35 @@ async fn get(
     if let Some(teacher) = teacher {
         Ok(HttpResponse::Ok().json(teacher))
     } else {
-        Ok(HttpResponse::NotFound()
-            .body(format!("No teacher found with teacher_id: {}", teacher_id)))
+        Err(BabibappError::not_found(format!(
+            "No teacher found with teacher_id: {}",
+            teacher_id
+        )))
     }
 }
 
+/// Teachers ordered by id, one page at a time
 #[get("/get_all")]
-async fn get_all(context: web::Data<RequestContext>, req: HttpRequest) -> RequestResult {
-    let token_settings = &context.settings.token;
+async fn get_all(
+    context: web::Data<RequestContext>,
+    _: AuthenticatedUser,
+    query: web::Query<PageQuery>,
+) -> RequestResult {
+    let limit = page::limit(&query)?;
+    let after = page::cursor::<i32>(&query)?;
 
-    let token = auth::token_from_request(req.clone())?;
-    let _ = auth::validate_token(&token.token, token_settings.secret.clone())?;
+    let teachers = db::blocked_access(&context.pool, move |conn| {
+        use diesel::dsl::count_star;
+        use schema::teachers::dsl::*;
 
-    let teachers = db::blocked_access(&context.pool, |conn| {
-        use schema::teachers::table;
-        let list = table.load::<models::teacher::Teacher>(conn)?;
-        Ok(list) as Result<Vec<models::teacher::Teacher>, BabibappError>
+        let total = teachers.select(count_star()).get_result::<i64>(conn)?;
+        let list = teachers
+            .filter(id.gt(after))
+            .order(id)
+            .limit(limit + 1)
+            .load::<models::teacher::Teacher>(conn)?;
+
+        QueryResult::Ok(page::keyset_page(list, limit, total, |t| t.id))
     })
     .await??;
 
@@ -72,18 +81,9 @@ async fn get_all(context: web::Data<RequestContext>, req: HttpRequest) -> Reques
 #[post("/add")]
 async fn add(
     context: web::Data<RequestContext>,
-    req: HttpRequest,
+    _: TeacherEditor,
     form: web::Json<models::teacher::NewTeacher>,
 ) -> RequestResult {
-    let token_settings = &context.settings.token;
-
-    let token = auth::token_from_request(req.clone())?;
-    let claims = auth::validate_token(&token.token, token_settings.secret.clone())?;
-
-    if !claims.admin {
-        return Ok(HttpResponse::Unauthorized().body("Access only for admins"));
-    }
-
     let teacher = db::blocked_access(&context.pool, move |conn| {
         use schema::teachers::dsl::*;
 
@@ -106,19 +106,10 @@ async fn add(
 #[put("/reset/{teacher_id}")]
 async fn reset(
     context: web::Data<RequestContext>,
-    req: HttpRequest,
+    _: TeacherEditor,
     teacher_id: web::Path<i32>,
     form: web::Json<models::teacher::NewTeacher>,
 ) -> RequestResult {
-    let token_settings = &context.settings.token;
-
-    let token = auth::token_from_request(req.clone())?;
-    let claims = auth::validate_token(&token.token, token_settings.secret.clone())?;
-
-    if !claims.admin {
-        return Ok(HttpResponse::Unauthorized().body("Access only for admins"));
-    }
-
     let teacher_id = teacher_id.into_inner();
 
     let teacher = db::blocked_access(&context.pool, move |conn| {
@@ -136,26 +127,19 @@ async fn reset(
     if let Some(teacher) = teacher {
         Ok(HttpResponse::Ok().json(teacher))
     } else {
-        Ok(HttpResponse::NotFound()
-            .body(format!("No teacher found with teacher_id: {}", teacher_id)))
+        Err(BabibappError::not_found(format!(
+            "No teacher found with teacher_id: {}",
+            teacher_id
+        )))
     }
 }
 
 #[delete("/delete/{teacher_id}")]
 async fn delete(
     context: web::Data<RequestContext>,
-    req: HttpRequest,
+    _: TeacherEditor,
     teacher_id: web::Path<i32>,
 ) -> RequestResult {
-    let token_settings = &context.settings.token;
-
-    let token = auth::token_from_request(req.clone())?;
-    let claims = auth::validate_token(&token.token, token_settings.secret.clone())?;
-
-    if !claims.admin {
-        return Ok(HttpResponse::Unauthorized().body("Access only for admins"));
-    }
-
     let teacher_id = teacher_id.into_inner();
 
     let teacher = db::blocked_access(&context.pool, move |conn| {
@@ -172,7 +156,9 @@ async fn delete(
     if let Some(teacher) = teacher {
         Ok(HttpResponse::Ok().json(teacher))
     } else {
-        Ok(HttpResponse::NotFound()
-            .body(format!("No teacher found with teacher_id: {}", teacher_id)))
+        Err(BabibappError::not_found(format!(
+            "No teacher found with teacher_id: {}",
+            teacher_id
+        )))
     }
 }
@@ -1,34 +1,145 @@
-use actix_web::{post, web, HttpResponse};
+use std::time::{Duration, SystemTime};
+
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
 use diesel::prelude::*;
 
 use babibapp_models as models;
 use babibapp_schema::schema;
+use diesel::PgConnection;
+use models::wrappers::{
+    EmailWrapper, RefreshTokenWrapper, ResetPasswordWrapper, RevokeWrapper, TotpChallengeWrapper,
+    TotpLoginWrapper,
+};
 use pwhash::bcrypt;
 
 use crate::auth;
+use crate::auth::AuthenticatedUser;
 use crate::db;
+use crate::error::BabibappError;
+use crate::lockout;
+use crate::mail::Mail;
+use crate::password;
 use crate::request::{RequestContext, RequestResult};
+use crate::settings::{MailTransport, TotpSettings};
+use crate::totp;
 
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.service(generate).service(validate);
+    cfg.service(generate)
+        .service(verify_totp)
+        .service(refresh)
+        .service(revoke)
+        .service(validate)
+        .service(jwks)
+        .service(forgot)
+        .service(reset);
+}
+
+/// bcrypt hash checked for unknown emails, so the response time doesn't reveal which emails exist
+const DUMMY_PASSWORD_HASH: &str = "$2b$10$fw.jelbe2dPD1i4ynYxJs.yKNPOJCbR0/sU3R/ho.pZGDeu8lxpkC";
+
+/// Wrong codes after which a login challenge is given up and the password has to be entered again
+const MAX_CHALLENGE_FAILURES: i32 = 5;
+
+/// Permissions a new token of the student carries, and whether they were withheld because
+/// the student is required to enable two-factor authentication first
+fn session_permissions(
+    conn: &PgConnection,
+    totp_settings: &TotpSettings,
+    student: i32,
+) -> QueryResult<(Vec<auth::Permission>, bool)> {
+    let permissions = auth::load_permissions(conn, student)?;
+
+    if totp_settings.require_for_admins
+        && !permissions.is_empty()
+        && !totp::is_enabled(conn, student)?
+    {
+        return Ok((Vec::new(), true));
+    }
+
+    Ok((permissions, false))
+}
+
+/// Starts a session for a student who passed every login step
+async fn issue_tokens(context: &RequestContext, student: i32) -> RequestResult {
+    let token_settings = &context.settings.token;
+    let totp_settings = context.settings.totp.clone();
+    let refresh_expiration_days = token_settings.refresh_expiration_days;
+
+    let (session, refresh_token, (permissions, totp_required)) =
+        db::blocked_access(&context.pool, move |conn| {
+            let (session, refresh_token) =
+                auth::create_session(conn, student, refresh_expiration_days)?;
+            let permissions = session_permissions(conn, &totp_settings, student)?;
+            QueryResult::Ok((session, refresh_token, permissions))
+        })
+        .await??;
+
+    let claims = auth::Claims::new(
+        student,
+        permissions,
+        Some(session.id),
+        token_settings.expiration_minutes,
+    );
+    let mut token_pair = auth::token_pair_from_claims(claims, Some(refresh_token), &context.keys)?;
+    token_pair.totp_required = totp_required;
+
+    Ok(HttpResponse::Ok().json(token_pair))
 }
 
 #[post("/generate")]
 async fn generate(
+    req: HttpRequest,
     context: web::Data<RequestContext>,
     form: web::Json<models::student::LoginStudent>,
 ) -> RequestResult {
-    let token_settings = &context.settings.token;
     let root_settings = &context.settings.root;
+    let login_settings = context.settings.login.clone();
 
     let login_email = form.email.clone();
     let login_email_move = form.email.clone();
     let login_password = form.password.clone();
 
-    if login_email == root_settings.email && login_password == root_settings.password {
-        return Ok(HttpResponse::Ok().json(auth::token_from_claims(
-            auth::Claims::root(root_settings.expiration_minutes),
-            token_settings.secret.clone(),
+    let attempt_email = login_email.to_lowercase();
+    let attempt_ip = lockout::client_ip(&req, &login_settings);
+
+    let (check_email, check_ip) = (attempt_email.clone(), attempt_ip.clone());
+    let locked_until = db::blocked_access(&context.pool, move |conn| {
+        lockout::locked_until(conn, &check_email, &check_ip)
+    })
+    .await??;
+
+    if locked_until.is_some() {
+        return Err(BabibappError::too_many_requests(
+            "Too many failed login attempts, try again later",
+        ));
+    }
+
+    let record_attempt = |success: bool| {
+        let (attempt_email, attempt_ip) = (attempt_email.clone(), attempt_ip.clone());
+        let login_settings = login_settings.clone();
+        db::blocked_access(&context.pool, move |conn| {
+            lockout::record_attempt(conn, &login_settings, &attempt_email, &attempt_ip, success)
+        })
+    };
+
+    if login_email == root_settings.email
+        && bcrypt::verify(&login_password, &root_settings.password_hash)
+    {
+        if root_settings.disable_when_admin_exists {
+            let admin_exists = db::blocked_access(&context.pool, auth::admin_exists).await??;
+            if admin_exists {
+                log::warn!("Refused root login because an admin exists");
+                record_attempt(false).await??;
+                return Err(BabibappError::unauthorized("Invalid credentials"));
+            }
+        }
+
+        record_attempt(true).await??;
+
+        return Ok(HttpResponse::Ok().json(auth::token_pair_from_claims(
+            auth::Claims::root(root_settings),
+            None,
+            &context.keys,
         )?));
     }
 
@@ -37,36 +148,362 @@ async fn generate(
 
         students
             .filter(email.eq(login_email_move))
-            .first::<models::student::Student>(conn)
+            .first::<models::student::StudentRecord>(conn)
             .optional()
     })
     .await??;
 
-    if let Some(student) = student {
-        if bcrypt::verify(&login_password, &student.password_hash) {
-            let claims =
-                auth::Claims::new(student.id, student.admin, token_settings.expiration_hours);
-            Ok(HttpResponse::Ok().json(auth::token_from_claims(
-                claims,
-                token_settings.secret.clone(),
-            )?))
-        } else {
-            Ok(HttpResponse::Unauthorized().body(format!("Wrong password: {}", login_password)))
+    let student = match student {
+        Some(student) if bcrypt::verify(&login_password, &student.password_hash) => student,
+        Some(_) => {
+            record_attempt(false).await??;
+            return Err(BabibappError::unauthorized("Invalid credentials"));
+        }
+        None => {
+            bcrypt::verify(&login_password, DUMMY_PASSWORD_HASH);
+            record_attempt(false).await??;
+            return Err(BabibappError::unauthorized("Invalid credentials"));
         }
+    };
+
+    let challenged_student = student.id;
+    let challenge_minutes = context.settings.totp.challenge_minutes;
+
+    let challenge = db::blocked_access(&context.pool, move |conn| {
+        if !totp::is_enabled(conn, challenged_student)? {
+            return Ok(None);
+        }
+
+        use schema::login_challenges::dsl::*;
+
+        diesel::delete(login_challenges.filter(expires.lt(SystemTime::now()))).execute(conn)?;
+
+        let challenge = auth::generate_random_token();
+        diesel::insert_into(login_challenges)
+            .values(&models::totp::NewLoginChallenge {
+                student_id: challenged_student,
+                token_hash: auth::hash_token(&challenge),
+                expires: SystemTime::now() + Duration::from_secs(challenge_minutes as u64 * 60),
+            })
+            .execute(conn)?;
+
+        QueryResult::Ok(Some(challenge))
+    })
+    .await??;
+
+    // with two-factor authentication the login only succeeds with the code, until then
+    // the failures of the email keep counting
+    if let Some(challenge) = challenge {
+        return Ok(HttpResponse::Ok().json(TotpChallengeWrapper {
+            totp_challenge: challenge,
+        }));
+    }
+
+    record_attempt(true).await??;
+
+    issue_tokens(&context, student.id).await
+}
+
+/// Second login step for students with two-factor authentication
+#[post("/totp")]
+async fn verify_totp(
+    context: web::Data<RequestContext>,
+    req: HttpRequest,
+    form: web::Json<TotpLoginWrapper>,
+) -> RequestResult {
+    let presented_hash = auth::hash_token(&form.totp_challenge);
+    let code = form.code.clone();
+    let login_settings = context.settings.login.clone();
+    let attempt_ip = lockout::client_ip(&req, &login_settings);
+
+    let verified = db::blocked_access(&context.pool, move |conn| {
+        conn.transaction::<_, BabibappError, _>(|| {
+            use schema::login_challenges::dsl::*;
+
+            let challenge = login_challenges
+                .filter(token_hash.eq(presented_hash))
+                .filter(expires.gt(SystemTime::now()))
+                .filter(failures.lt(MAX_CHALLENGE_FAILURES))
+                .for_update()
+                .first::<models::totp::LoginChallenge>(conn)
+                .optional()?
+                .ok_or_else(|| BabibappError::unauthorized("Invalid or expired login challenge"))?;
+
+            let attempt_email = schema::students::table
+                .find(challenge.student_id)
+                .select(schema::students::email)
+                .first::<String>(conn)?
+                .to_lowercase();
+
+            if lockout::locked_until(conn, &attempt_email, &attempt_ip)?.is_some() {
+                return Err(BabibappError::too_many_requests(
+                    "Too many failed login attempts, try again later",
+                ));
+            }
+
+            // codes count against the lockout like passwords, so new challenges don't bring new guesses
+            let code_valid = totp::check_code_or_recovery_code(conn, challenge.student_id, &code)?;
+            lockout::record_attempt(
+                conn,
+                &login_settings,
+                &attempt_email,
+                &attempt_ip,
+                code_valid,
+            )?;
+
+            if !code_valid {
+                diesel::update(login_challenges.find(challenge.id))
+                    .set(failures.eq(failures + 1))
+                    .execute(conn)?;
+                return Ok(None);
+            }
+
+            diesel::delete(login_challenges.find(challenge.id)).execute(conn)?;
+
+            Ok(Some(challenge.student_id))
+        })
+    })
+    .await??;
+
+    match verified {
+        Some(student_id) => issue_tokens(&context, student_id).await,
+        None => Err(BabibappError::unauthorized("Invalid code")),
+    }
+}
+
+#[post("/refresh")]
+async fn refresh(
+    context: web::Data<RequestContext>,
+    form: web::Json<RefreshTokenWrapper>,
+) -> RequestResult {
+    let token_settings = &context.settings.token;
+
+    let presented_hash = auth::hash_token(&form.refresh_token);
+    let refresh_expiration_days = token_settings.refresh_expiration_days;
+    let totp_settings = context.settings.totp.clone();
+
+    let rotated = db::blocked_access(&context.pool, move |conn| {
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            use schema::refresh_tokens::dsl::*;
+
+            let session = refresh_tokens
+                .filter(token_hash.eq(presented_hash))
+                .first::<models::token::RefreshToken>(conn)
+                .optional()?;
+
+            let session = match session {
+                Some(session) => session,
+                None => return Ok(None),
+            };
+
+            if session.revoked {
+                // a refresh token was used twice, so it has probably been stolen:
+                // end every session of the student to lock out both parties
+                diesel::update(refresh_tokens.filter(student_id.eq(session.student_id)))
+                    .set(revoked.eq(true))
+                    .execute(conn)?;
+                return Ok(None);
+            }
+
+            if session.expires < SystemTime::now() {
+                return Ok(None);
+            }
+
+            diesel::update(refresh_tokens.find(session.id))
+                .set(revoked.eq(true))
+                .execute(conn)?;
+
+            let student = schema::students::table
+                .find(session.student_id)
+                .first::<models::student::StudentRecord>(conn)?;
+
+            let (new_session, new_refresh_token) =
+                auth::create_session(conn, student.id, refresh_expiration_days)?;
+            let permissions = session_permissions(conn, &totp_settings, student.id)?;
+
+            Ok(Some((student, new_session, new_refresh_token, permissions)))
+        })
+    })
+    .await??;
+
+    if let Some((student, session, refresh_token, (permissions, totp_required))) = rotated {
+        let claims = auth::Claims::new(
+            student.id,
+            permissions,
+            Some(session.id),
+            token_settings.expiration_minutes,
+        );
+        let mut token_pair =
+            auth::token_pair_from_claims(claims, Some(refresh_token), &context.keys)?;
+        token_pair.totp_required = totp_required;
+
+        Ok(HttpResponse::Ok().json(token_pair))
     } else {
-        Ok(HttpResponse::NotFound().body(format!("No student found with email: {}", login_email)))
+        Err(BabibappError::unauthorized("Invalid refresh token"))
     }
 }
 
+#[post("/revoke")]
+async fn revoke(
+    context: web::Data<RequestContext>,
+    AuthenticatedUser(claims): AuthenticatedUser,
+    form: web::Json<RevokeWrapper>,
+) -> RequestResult {
+    if claims.api_key.is_some() {
+        return Err(BabibappError::forbidden(
+            "API keys can't be revoked with themselves, ask an admin to revoke them",
+        ));
+    }
+
+    let all_sessions = form.all;
+
+    db::blocked_access(&context.pool, move |conn| {
+        auth::revoke_sessions(conn, &claims, all_sessions)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().body("Token revoked"))
+}
+
 #[post("/validate")]
 async fn validate(
     context: web::Data<RequestContext>,
     token: web::Json<auth::TokenWrapper>,
 ) -> RequestResult {
-    let token_settings = &context.settings.token;
+    auth::validate_token(&token.into_inner().token, &context).await?;
+
+    Ok(HttpResponse::Ok().body("Valid token"))
+}
+
+/// Public keys for verifying access tokens without asking the server
+#[get("/jwks")]
+async fn jwks(context: web::Data<RequestContext>) -> RequestResult {
+    Ok(HttpResponse::Ok().json(&context.keys.jwks))
+}
+
+#[post("/forgot")]
+async fn forgot(
+    context: web::Data<RequestContext>,
+    form: web::Json<EmailWrapper>,
+) -> RequestResult {
+    let mail_settings = context.settings.mail.clone();
+    let forgot_email = form.email.clone();
+
+    // no reset token is issued that can't be delivered
+    if let MailTransport::Disabled = mail_settings.transport {
+        return Err(BabibappError::forbidden(
+            "Password resets are disabled, no mail transport is configured",
+        ));
+    }
+
+    let issued = db::blocked_access(&context.pool, move |conn| {
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            let student = {
+                use schema::students::dsl::*;
+
+                students
+                    .filter(email.eq(forgot_email))
+                    .first::<models::student::StudentRecord>(conn)
+                    .optional()?
+            };
+
+            let student = match student {
+                Some(student) => student,
+                None => return Ok(None),
+            };
+
+            use schema::password_reset_tokens::dsl::*;
+
+            // only the latest link works
+            diesel::update(password_reset_tokens.filter(student_id.eq(student.id)))
+                .set(used.eq(true))
+                .execute(conn)?;
+
+            let reset_token = auth::generate_random_token();
+            let expiration =
+                Duration::from_secs(mail_settings.reset_expiration_minutes as u64 * 60);
+
+            diesel::insert_into(password_reset_tokens)
+                .values(&models::token::NewPasswordResetToken {
+                    student_id: student.id,
+                    token_hash: auth::hash_token(&reset_token),
+                    expires: SystemTime::now() + expiration,
+                })
+                .execute(conn)?;
 
-    match auth::validate_token(&token.into_inner().token, token_settings.secret.clone()) {
-        Ok(_) => Ok(HttpResponse::Ok().body("Valid token")),
-        Err(e) => Ok(HttpResponse::Unauthorized().body(format!("{}", e))),
+            Ok(Some((student, reset_token)))
+        })
+    })
+    .await??;
+
+    if let Some((student, reset_token)) = issued {
+        let mail_settings = &context.settings.mail;
+        let mail = Mail {
+            to: student.email.clone(),
+            subject: "Reset your babibapp password".to_string(),
+            body: format!(
+                "Hi {},\n\nuse this link to choose a new password, it expires in {} minutes:\n{}\n\nIf you didn't ask for it, you can ignore this mail.",
+                student.first_name,
+                mail_settings.reset_expiration_minutes,
+                mail_settings.reset_url.replace("{token}", &reset_token)
+            ),
+        };
+
+        let mailer = context.mailer.clone();
+        // failures are only logged, the response must not tell whether the email exists
+        if let Err(e) = web::block(move || mailer.send(&mail)).await? {
+            log::error!("Failed to send password reset mail: {:?}", e);
+        }
     }
+
+    Ok(HttpResponse::Ok().body("If the email is registered, a reset link has been sent"))
+}
+
+#[post("/reset")]
+async fn reset(
+    context: web::Data<RequestContext>,
+    form: web::Json<ResetPasswordWrapper>,
+) -> RequestResult {
+    let password_settings = context.settings.password.clone();
+    let presented_hash = auth::hash_token(&form.token);
+    let new_password = form.password.clone();
+
+    let student = db::blocked_access(&context.pool, move |conn| {
+        conn.transaction::<_, BabibappError, _>(|| {
+            use schema::password_reset_tokens::dsl::*;
+
+            let reset_token = password_reset_tokens
+                .filter(token_hash.eq(presented_hash))
+                .filter(used.eq(false))
+                .filter(expires.gt(SystemTime::now()))
+                .for_update()
+                .first::<models::token::PasswordResetToken>(conn)
+                .optional()?
+                .ok_or_else(|| BabibappError::unauthorized("Invalid or expired reset token"))?;
+
+            let student = schema::students::table
+                .find(reset_token.student_id)
+                .first::<models::student::StudentRecord>(conn)?;
+
+            password::check_policy(&new_password, &student.email, &password_settings)?;
+            let hashed_password = bcrypt::hash(new_password)?;
+
+            diesel::update(password_reset_tokens.find(reset_token.id))
+                .set(used.eq(true))
+                .execute(conn)?;
+
+            let student = diesel::update(schema::students::table.find(student.id))
+                .set(schema::students::password_hash.eq(hashed_password))
+                .get_result::<models::student::StudentRecord>(conn)?;
+
+            auth::end_sessions(conn, student.id, None)?;
+
+            Ok(student)
+        })
+    })
+    .await??;
+
+    log::debug!("Database response: {:?}", student);
+
+    Ok(HttpResponse::Ok().json(models::student::StudentProfile::from(student)))
 }
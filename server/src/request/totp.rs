@@ -0,0 +1,186 @@
+use actix_web::{delete, post, web, HttpResponse};
+use diesel::prelude::*;
+use diesel::PgConnection;
+
+use babibapp_models as models;
+use babibapp_schema::schema;
+use models::wrappers::TotpCodeWrapper;
+
+use crate::auth::{self, StudentManager, StudentUser};
+use crate::db;
+use crate::error::BabibappError;
+use crate::request::{RequestContext, RequestResult};
+use crate::totp;
+
+pub fn config(cfg: &mut web::ServiceConfig) {
+    cfg.service(enroll)
+        .service(confirm)
+        .service(disable)
+        .service(recovery_codes)
+        .service(reset);
+}
+
+/// Creates a new secret, it is only used for logins once a first code was confirmed
+#[post("/enroll")]
+async fn enroll(
+    context: web::Data<RequestContext>,
+    StudentUser(claims): StudentUser,
+) -> RequestResult {
+    let issuer = context.settings.totp.issuer.clone();
+
+    let enrolment = db::blocked_access(&context.pool, move |conn| {
+        if totp::is_enabled(conn, claims.id)? {
+            return Err(BabibappError::conflict(
+                "Two-factor authentication is already enabled",
+            ));
+        }
+
+        let student = schema::students::table
+            .find(claims.id)
+            .first::<models::student::StudentRecord>(conn)?;
+
+        use schema::student_totp::dsl::*;
+
+        let new_secret = totp::generate_secret();
+
+        diesel::insert_into(student_totp)
+            .values(&models::totp::NewStudentTotp {
+                student_id: student.id,
+                secret: new_secret.clone(),
+            })
+            .on_conflict(student_id)
+            .do_update()
+            .set((
+                secret.eq(&new_secret),
+                enabled.eq(false),
+                last_step.eq(None::<i64>),
+            ))
+            .execute(conn)?;
+
+        Ok(models::totp::TotpEnrolment {
+            uri: totp::provisioning_uri(&new_secret, &student.email, &issuer),
+            secret: new_secret,
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(enrolment))
+}
+
+#[post("/confirm")]
+async fn confirm(
+    context: web::Data<RequestContext>,
+    StudentUser(claims): StudentUser,
+    form: web::Json<TotpCodeWrapper>,
+) -> RequestResult {
+    let codes = db::blocked_access(&context.pool, move |conn| {
+        conn.transaction::<_, BabibappError, _>(|| {
+            if totp::is_enabled(conn, claims.id)? {
+                return Err(BabibappError::conflict(
+                    "Two-factor authentication is already enabled",
+                ));
+            }
+
+            if !totp::check_code(conn, claims.id, &form.code, false)? {
+                return Err(BabibappError::unauthorized("Invalid code"));
+            }
+
+            {
+                use schema::student_totp::dsl::*;
+
+                diesel::update(student_totp.find(claims.id))
+                    .set(enabled.eq(true))
+                    .execute(conn)?;
+            }
+
+            Ok(totp::replace_recovery_codes(conn, claims.id)?)
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(models::totp::RecoveryCodes {
+        recovery_codes: codes,
+    }))
+}
+
+#[post("/disable")]
+async fn disable(
+    context: web::Data<RequestContext>,
+    StudentUser(claims): StudentUser,
+    form: web::Json<TotpCodeWrapper>,
+) -> RequestResult {
+    db::blocked_access(&context.pool, move |conn| {
+        conn.transaction::<_, BabibappError, _>(|| {
+            if !totp::check_code_or_recovery_code(conn, claims.id, &form.code)? {
+                return Err(BabibappError::unauthorized("Invalid code"));
+            }
+
+            remove_totp(conn, claims.id)?;
+            Ok(())
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().body("Two-factor authentication disabled"))
+}
+
+/// Replaces the recovery codes, the old ones stop working
+#[post("/recovery_codes")]
+async fn recovery_codes(
+    context: web::Data<RequestContext>,
+    StudentUser(claims): StudentUser,
+    form: web::Json<TotpCodeWrapper>,
+) -> RequestResult {
+    let codes = db::blocked_access(&context.pool, move |conn| {
+        conn.transaction::<_, BabibappError, _>(|| {
+            if !totp::check_code(conn, claims.id, &form.code, true)? {
+                return Err(BabibappError::unauthorized("Invalid code"));
+            }
+
+            Ok(totp::replace_recovery_codes(conn, claims.id)?)
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(models::totp::RecoveryCodes {
+        recovery_codes: codes,
+    }))
+}
+
+/// Removes the second factor of a student who lost both the authenticator and the recovery codes
+#[delete("/reset/{student_id}")]
+async fn reset(
+    context: web::Data<RequestContext>,
+    StudentManager(claims): StudentManager,
+    student_id: web::Path<i32>,
+) -> RequestResult {
+    let student_id = student_id.into_inner();
+
+    let removed = db::blocked_access(&context.pool, move |conn| {
+        conn.transaction::<_, BabibappError, _>(|| {
+            auth::check_outranks(conn, &claims, student_id)?;
+
+            Ok(remove_totp(conn, student_id)?)
+        })
+    })
+    .await??;
+
+    if removed {
+        Ok(HttpResponse::Ok().body("Two-factor authentication reset"))
+    } else {
+        Err(BabibappError::not_found(format!(
+            "No two-factor authentication found for student_id: {}",
+            student_id
+        )))
+    }
+}
+
+fn remove_totp(conn: &PgConnection, student: i32) -> QueryResult<bool> {
+    use schema::{student_totp, totp_recovery_codes};
+
+    diesel::delete(totp_recovery_codes::table.filter(totp_recovery_codes::student_id.eq(student)))
+        .execute(conn)?;
+    let removed = diesel::delete(student_totp::table.find(student)).execute(conn)?;
+
+    Ok(removed > 0)
+}
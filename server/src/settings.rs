@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::{fs, net::IpAddr};
 
 use anyhow::Context;
@@ -9,6 +10,16 @@ pub struct Settings {
     pub database: DatabaseSettings,
     pub token: TokenSettings,
     pub root: RootSettings,
+    #[serde(default)]
+    pub login: LoginSettings,
+    #[serde(default)]
+    pub password: PasswordSettings,
+    #[serde(default)]
+    pub mail: MailSettings,
+    #[serde(default)]
+    pub totp: TotpSettings,
+    #[serde(default)]
+    pub comment: CommentSettings,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -27,16 +38,264 @@ pub struct DatabaseSettings {
 }
 
 #[derive(Debug, Deserialize, Clone)]
+#[serde(from = "TokenSettingsFile")]
 pub struct TokenSettings {
-    pub secret: String,
-    pub expiration_hours: i64,
+    /// Shared secret for HS256, only used if no `keys` are configured
+    pub secret: Option<String>,
+    pub expiration_minutes: i64,
+    pub refresh_expiration_days: i64,
+    /// `kid` of the key new tokens are signed with
+    pub signing_key: Option<String>,
+    /// Keys accepted for verification, old keys stay here after a rotation until their tokens expired
+    pub keys: Vec<KeySettings>,
+}
+
+/// `[token]` as written in the settings file, still accepting the keys of older versions
+#[derive(Deserialize)]
+struct TokenSettingsFile {
+    #[serde(default)]
+    secret: Option<String>,
+    #[serde(default)]
+    expiration_minutes: Option<i64>,
+    /// Replaced by `expiration_minutes`, only read if that is missing
+    #[serde(default)]
+    expiration_hours: Option<i64>,
+    #[serde(default = "default_refresh_expiration_days")]
+    refresh_expiration_days: i64,
+    #[serde(default)]
+    signing_key: Option<String>,
+    #[serde(default)]
+    keys: Vec<KeySettings>,
+}
+
+fn default_refresh_expiration_days() -> i64 {
+    30
+}
+
+impl From<TokenSettingsFile> for TokenSettings {
+    fn from(file: TokenSettingsFile) -> Self {
+        let expiration_minutes = file
+            .expiration_minutes
+            .or_else(|| file.expiration_hours.map(|hours| hours * 60))
+            .unwrap_or(15);
+
+        TokenSettings {
+            secret: file.secret,
+            expiration_minutes,
+            refresh_expiration_days: file.refresh_expiration_days,
+            signing_key: file.signing_key,
+            keys: file.keys,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
+pub struct KeySettings {
+    pub kid: String,
+    pub algorithm: KeyAlgorithm,
+    pub public_key: PathBuf,
+    /// Only needed for the signing key
+    pub private_key: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAlgorithm {
+    RS256,
+    EdDSA,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(try_from = "RootSettingsFile")]
 pub struct RootSettings {
     pub email: String,
-    pub password: String,
+    /// bcrypt hash of the root password, see `babibapp hash-password`
+    pub password_hash: String,
     pub expiration_minutes: i64,
+    /// Refuse root logins as soon as a student is allowed to manage roles
+    pub disable_when_admin_exists: bool,
+}
+
+/// `[root]` as written in the settings file, still accepting the plain password of older versions
+#[derive(Deserialize)]
+struct RootSettingsFile {
+    email: String,
+    #[serde(default)]
+    password_hash: Option<String>,
+    /// Replaced by `password_hash`, hashed while loading if that is missing
+    #[serde(default)]
+    password: Option<String>,
+    expiration_minutes: i64,
+    #[serde(default)]
+    disable_when_admin_exists: bool,
+}
+
+impl TryFrom<RootSettingsFile> for RootSettings {
+    type Error = String;
+
+    fn try_from(file: RootSettingsFile) -> Result<Self, Self::Error> {
+        let password_hash = match (file.password_hash, file.password) {
+            (Some(password_hash), _) => password_hash,
+            (None, Some(password)) => {
+                log::warn!(
+                    "root.password is deprecated, replace it with root.password_hash from `babibapp hash-password`"
+                );
+                pwhash::bcrypt::hash(password)
+                    .map_err(|e| format!("Hashing root.password failed: {}", e))?
+            }
+            (None, None) => return Err("root.password_hash is missing".to_string()),
+        };
+
+        Ok(RootSettings {
+            email: file.email,
+            password_hash,
+            expiration_minutes: file.expiration_minutes,
+            disable_when_admin_exists: file.disable_when_admin_exists,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct LoginSettings {
+    /// Failed attempts for one email before it is locked
+    pub max_email_failures: i32,
+    /// Failed attempts from one address before it is locked
+    pub max_ip_failures: i32,
+    /// Length of the first lockout, doubled with every further failure
+    pub lockout_seconds: i64,
+    /// Upper bound of a lockout, counters are reset after this long without failures
+    pub max_lockout_seconds: i64,
+    /// Take the client address from `Forwarded`/`X-Forwarded-For`, only enable behind a trusted proxy
+    pub trust_forwarded_for: bool,
+    pub attempt_log_days: i64,
+}
+
+impl Default for LoginSettings {
+    fn default() -> Self {
+        LoginSettings {
+            max_email_failures: 5,
+            max_ip_failures: 20,
+            lockout_seconds: 30,
+            max_lockout_seconds: 60 * 60,
+            trust_forwarded_for: false,
+            attempt_log_days: 30,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct PasswordSettings {
+    pub min_length: usize,
+}
+
+impl Default for PasswordSettings {
+    fn default() -> Self {
+        PasswordSettings { min_length: 8 }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct MailSettings {
+    pub from: String,
+    /// Link sent for password resets, `{token}` is replaced with the reset token
+    pub reset_url: String,
+    pub reset_expiration_minutes: i64,
+    pub transport: MailTransport,
+}
+
+impl Default for MailSettings {
+    fn default() -> Self {
+        MailSettings {
+            from: "babibapp <babibapp@localhost>".to_string(),
+            reset_url: "{token}".to_string(),
+            reset_expiration_minutes: 30,
+            transport: MailTransport::Disabled,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MailTransport {
+    Smtp {
+        host: String,
+        port: Option<u16>,
+        username: Option<String>,
+        password: Option<String>,
+    },
+    /// Writes every mail to a file in `dir`, for local testing
+    File { dir: PathBuf },
+    /// Only logs mails, reset links included, for local testing
+    Log,
+    /// No mails are sent, so password resets by mail are refused
+    Disabled,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct TotpSettings {
+    /// Withhold the permissions of students that hold any until they enabled two-factor authentication
+    pub require_for_admins: bool,
+    /// Shown as account issuer in authenticator apps
+    pub issuer: String,
+    /// How long a correct password waits for the second factor
+    pub challenge_minutes: i64,
+}
+
+impl Default for TotpSettings {
+    fn default() -> Self {
+        TotpSettings {
+            require_for_admins: false,
+            issuer: "babibapp".to_string(),
+            challenge_minutes: 5,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct CommentSettings {
+    /// How deep replies may be nested, 0 disables replies
+    pub max_depth: i32,
+    /// Open reports after which a comment is hidden until a moderator looks at it, 0 disables it
+    pub auto_hide_reports: i64,
+    /// New comments about students stay pending until the student approves them
+    pub require_approval: bool,
+    /// Whether students may vote on their own comments
+    pub allow_self_votes: bool,
+    /// Whether comments hide their author from moderators, see `AnonymityPolicy`
+    pub anonymity: AnonymityPolicy,
+    /// Reactions students may give to comments, emoji or short codes
+    pub reactions: Vec<String>,
+}
+
+impl Default for CommentSettings {
+    fn default() -> Self {
+        CommentSettings {
+            max_depth: 8,
+            auto_hide_reports: 5,
+            require_approval: false,
+            allow_self_votes: true,
+            anonymity: AnonymityPolicy::Optional,
+            reactions: ["thumbsup", "heart", "joy", "tada", "thinking"]
+                .iter()
+                .map(|reaction| reaction.to_string())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AnonymityPolicy {
+    /// every comment is anonymous
+    Always,
+    /// the author decides for each comment, comments aren't anonymous unless asked for
+    Optional,
+    /// no comment is anonymous
+    Never,
 }
 
 impl Settings {
@@ -0,0 +1,371 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use diesel::prelude::*;
+use diesel::PgConnection;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+use babibapp_models as models;
+use babibapp_schema::schema;
+
+use crate::auth;
+use crate::error::BabibappError;
+
+/// Length of a time step (RFC 6238)
+const STEP_SECONDS: u64 = 30;
+const DIGITS: u32 = 6;
+/// Steps before and after the current one that are still accepted, to allow for clock drift
+const WINDOW: i64 = 1;
+const SECRET_BYTES: usize = 20;
+const RECOVERY_CODE_COUNT: usize = 10;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+pub fn generate_secret() -> String {
+    let secret: Vec<u8> = (0..SECRET_BYTES).map(|_| rand::random::<u8>()).collect();
+    base32_encode(&secret)
+}
+
+/// `otpauth://` URI understood by authenticator apps
+pub fn provisioning_uri(secret: &str, email: &str, issuer: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        percent_encode(issuer),
+        percent_encode(email),
+        secret,
+        percent_encode(issuer),
+        DIGITS,
+        STEP_SECONDS
+    )
+}
+
+/// Recovery codes in the form `xxxxx-xxxxx`
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code: String = rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(10)
+                .map(|c| char::from(c).to_ascii_lowercase())
+                .collect();
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect()
+}
+
+/// Hash of a recovery code, ignoring case, dashes and whitespace
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    auth::hash_token(&normalized)
+}
+
+/// Replaces all recovery codes of a student and returns the new ones in plain text
+pub fn replace_recovery_codes(conn: &PgConnection, student: i32) -> QueryResult<Vec<String>> {
+    use schema::totp_recovery_codes::dsl::*;
+
+    diesel::delete(totp_recovery_codes.filter(student_id.eq(student))).execute(conn)?;
+
+    let codes = generate_recovery_codes();
+    let new_codes: Vec<models::totp::NewRecoveryCode> = codes
+        .iter()
+        .map(|code| models::totp::NewRecoveryCode {
+            student_id: student,
+            code_hash: hash_recovery_code(code),
+        })
+        .collect();
+
+    diesel::insert_into(totp_recovery_codes)
+        .values(&new_codes)
+        .execute(conn)?;
+
+    Ok(codes)
+}
+
+/// Whether the student has to present a second factor when logging in
+pub fn is_enabled(conn: &PgConnection, student: i32) -> QueryResult<bool> {
+    use schema::student_totp::dsl::*;
+
+    let totp = student_totp
+        .find(student)
+        .select(enabled)
+        .first::<bool>(conn)
+        .optional()?;
+
+    Ok(totp.unwrap_or(false))
+}
+
+/// Checks a code of the authenticator against the stored secret of the student.
+/// Accepted codes are remembered, so each one only works once.
+pub fn check_code(
+    conn: &PgConnection,
+    student: i32,
+    code: &str,
+    require_enabled: bool,
+) -> Result<bool, BabibappError> {
+    use schema::student_totp::dsl::*;
+
+    let totp = student_totp
+        .find(student)
+        .for_update()
+        .first::<models::totp::StudentTotp>(conn)
+        .optional()?;
+
+    let totp = match totp {
+        Some(totp) if totp.enabled || !require_enabled => totp,
+        _ => return Ok(false),
+    };
+
+    let matched_step = match matching_step(&totp.secret, code, totp.last_step)? {
+        Some(step) => step,
+        None => return Ok(false),
+    };
+
+    diesel::update(student_totp.find(student))
+        .set(last_step.eq(matched_step))
+        .execute(conn)?;
+
+    Ok(true)
+}
+
+/// Checks a code of the authenticator or, failing that, an unused recovery code, which is used up
+pub fn check_code_or_recovery_code(
+    conn: &PgConnection,
+    student: i32,
+    code: &str,
+) -> Result<bool, BabibappError> {
+    if check_code(conn, student, code, true)? {
+        return Ok(true);
+    }
+
+    use schema::totp_recovery_codes::dsl::*;
+
+    let used_codes = diesel::update(
+        totp_recovery_codes
+            .filter(student_id.eq(student))
+            .filter(code_hash.eq(hash_recovery_code(code)))
+            .filter(used.eq(false)),
+    )
+    .set(used.eq(true))
+    .execute(conn)?;
+
+    Ok(used_codes > 0)
+}
+
+/// Time step a code belongs to, only steps after `last_step` are considered
+fn matching_step(
+    secret: &str,
+    code: &str,
+    last_step: Option<i64>,
+) -> Result<Option<i64>, BabibappError> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(None);
+    }
+
+    let secret = base32_decode(secret)
+        .ok_or_else(|| anyhow::anyhow!("Invalid TOTP secret in the database"))?;
+    let current_step = time_step(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
+
+    for step in (current_step - WINDOW)..=(current_step + WINDOW) {
+        if last_step.is_some_and(|last_step| step <= last_step) {
+            continue;
+        }
+        if code_at(&secret, step)? == code {
+            return Ok(Some(step));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Time step of a unix timestamp (RFC 6238)
+fn time_step(unix_seconds: u64) -> i64 {
+    (unix_seconds / STEP_SECONDS) as i64
+}
+
+/// Code an authenticator shows for a time step
+fn code_at(secret: &[u8], step: i64) -> Result<String, BabibappError> {
+    Ok(format!(
+        "{:0width$}",
+        hotp(secret, step)?,
+        width = DIGITS as usize
+    ))
+}
+
+/// HOTP value of a counter (RFC 4226)
+fn hotp(secret: &[u8], counter: i64) -> Result<u32, BabibappError> {
+    let key = PKey::hmac(secret)?;
+    let mut signer = Signer::new(MessageDigest::sha1(), &key)?;
+    signer.update(&counter.to_be_bytes())?;
+    let hmac = signer.sign_to_vec()?;
+
+    let offset = (hmac[hmac.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hmac[offset] & 0x7f,
+        hmac[offset + 1],
+        hmac[offset + 2],
+        hmac[offset + 3],
+    ]);
+
+    Ok(binary % 10u32.pow(DIGITS))
+}
+
+fn base32_encode(data: &[u8]) -> String {
+    let mut encoded = String::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for byte in data {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        encoded.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    encoded
+}
+
+fn base32_decode(encoded: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for c in encoded.chars().filter(|c| *c != '=') {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|a| *a as char == c.to_ascii_uppercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+
+    Some(decoded)
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Secret of the test vectors in RFC 4226 and RFC 6238 (SHA1)
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn hotp_matches_rfc4226() {
+        let expected = [
+            755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489,
+        ];
+        for (counter, value) in expected.iter().enumerate() {
+            assert_eq!(hotp(RFC_SECRET, counter as i64).unwrap(), *value);
+        }
+    }
+
+    #[test]
+    fn totp_matches_rfc6238() {
+        // the RFC lists 8 digit codes, these are their last 6 digits
+        let expected = [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ];
+        for (time, code) in expected {
+            assert_eq!(code_at(RFC_SECRET, time_step(time)).unwrap(), code);
+        }
+    }
+
+    #[test]
+    fn codes_are_accepted_once() {
+        let secret = generate_secret();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let code = code_at(&base32_decode(&secret).unwrap(), time_step(now)).unwrap();
+
+        let step = matching_step(&secret, &code, None).unwrap().unwrap();
+        assert_eq!(matching_step(&secret, &code, Some(step)).unwrap(), None);
+    }
+
+    #[test]
+    fn malformed_codes_are_rejected() {
+        let secret = generate_secret();
+        for code in ["", "12345", "1234567", "12345a"] {
+            assert_eq!(matching_step(&secret, code, None).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn base32_matches_rfc4648() {
+        let expected = [
+            ("", ""),
+            ("f", "MY"),
+            ("fo", "MZXQ"),
+            ("foo", "MZXW6"),
+            ("foob", "MZXW6YQ"),
+            ("fooba", "MZXW6YTB"),
+            ("foobar", "MZXW6YTBOI"),
+        ];
+        for (data, encoded) in expected {
+            assert_eq!(base32_encode(data.as_bytes()), encoded);
+            assert_eq!(base32_decode(encoded).unwrap(), data.as_bytes());
+        }
+    }
+
+    #[test]
+    fn base32_decode_ignores_case_and_padding() {
+        assert_eq!(base32_decode("mzxw6yq=").unwrap(), b"foob");
+        assert_eq!(base32_decode("MZXW6"), base32_decode("mzxw6"));
+    }
+
+    #[test]
+    fn base32_decode_rejects_other_characters() {
+        assert_eq!(base32_decode("MZXW1"), None);
+        assert_eq!(base32_decode("MZ W6"), None);
+    }
+
+    #[test]
+    fn base32_round_trips_secrets() {
+        for _ in 0..100 {
+            let secret = generate_secret();
+            assert_eq!(base32_decode(&secret).unwrap().len(), SECRET_BYTES);
+            assert_eq!(base32_encode(&base32_decode(&secret).unwrap()), secret);
+        }
+    }
+
+    #[test]
+    fn recovery_codes_are_normalized() {
+        assert_eq!(
+            hash_recovery_code("ab1cd-ef2gh"),
+            hash_recovery_code(" AB1CD EF2GH ")
+        );
+    }
+}